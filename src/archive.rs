@@ -0,0 +1,208 @@
+//! ytrs-managed record of completed downloads, kept as JSON next to the user's library
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::progress::write_atomic;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    pub url: String,
+    /// yt-dlp extractor key, e.g. `Youtube`; ids are only unique per extractor
+    pub extractor: String,
+    pub id: String,
+    pub path: PathBuf,
+    /// Unix seconds when the download finished
+    pub timestamp: u64,
+    pub mode: String,
+}
+
+impl ArchiveEntry {
+    #[must_use]
+    pub fn new(url: &str, output: CapturedOutput, mode: String) -> Self {
+        Self {
+            url: url.to_string(),
+            extractor: output.extractor,
+            id: output.id,
+            path: output.path,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            mode,
+        }
+    }
+
+    /// Ids are compared per extractor; yt-dlp lowercases extractor keys in its own archive
+    fn is_same_item(&self, other: &Self) -> bool {
+        self.extractor.eq_ignore_ascii_case(&other.extractor) && self.id == other.id
+    }
+}
+
+/// One finished output as reported by yt-dlp through [`crate::config::CAPTURE_OUTPUT_TEMPLATE`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapturedOutput {
+    pub extractor: String,
+    pub id: String,
+    pub title: String,
+    pub path: PathBuf,
+}
+
+/// Parses the tab-separated capture file, skipping malformed lines; the path is taken
+/// from the last field, so a tab in a title stays in the title
+#[must_use]
+pub fn parse_captured_outputs(contents: &str) -> Vec<CapturedOutput> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let (extractor, id, rest) = (fields.next()?, fields.next()?, fields.next()?);
+            let (title, path) = rest.rsplit_once('\t')?;
+            (!path.trim().is_empty()).then(|| CapturedOutput {
+                extractor: extractor.to_string(),
+                id: id.to_string(),
+                title: title.to_string(),
+                path: PathBuf::from(path.trim()),
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ArchiveFile {
+    entries: Vec<ArchiveEntry>,
+}
+
+/// Shared across download tasks; every new entry rewrites the file atomically
+#[derive(Debug)]
+pub struct JsonArchive {
+    path: PathBuf,
+    entries: Mutex<Vec<ArchiveEntry>>,
+}
+
+impl JsonArchive {
+    /// A missing file starts an empty archive
+    pub fn load(path: &Path) -> Result<Self> {
+        let entries = match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str::<ArchiveFile>(&contents)?.entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries: Mutex::new(entries),
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<ArchiveEntry>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Appends `entry` unless its id is already recorded; returns whether it was added
+    pub fn record(&self, entry: ArchiveEntry) -> Result<bool> {
+        let mut entries = self.lock();
+        if entries.iter().any(|e| e.is_same_item(&entry)) {
+            return Ok(false);
+        }
+        entries.push(entry);
+        let json = serde_json::to_string_pretty(&ArchiveFile {
+            entries: entries.clone(),
+        })?;
+        drop(entries);
+        write_atomic(&self.path, &json)?;
+        Ok(true)
+    }
+
+    /// The same ids in yt-dlp's `--download-archive` format, so it can skip them itself
+    #[must_use]
+    pub fn ytdlp_archive(&self) -> String {
+        self.lock()
+            .iter()
+            .map(|entry| format!("{} {}\n", entry.extractor.to_lowercase(), entry.id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str) -> ArchiveEntry {
+        ArchiveEntry {
+            url: format!("https://www.youtube.com/watch?v={id}"),
+            extractor: "Youtube".to_string(),
+            id: id.to_string(),
+            path: PathBuf::from(format!("/videos/{id}.webm")),
+            timestamp: 1_700_000_000,
+            mode: "Default".to_string(),
+        }
+    }
+
+    fn temp_archive(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("ytrs-archive-{}-{name}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_record_appends_entries() {
+        let path = temp_archive("append");
+        let archive = JsonArchive::load(&path).unwrap();
+        assert!(archive.record(entry("abc")).unwrap());
+        assert!(archive.record(entry("def")).unwrap());
+
+        let reloaded = JsonArchive::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(reloaded.lock().len(), 2);
+        assert_eq!(reloaded.ytdlp_archive(), "youtube abc\nyoutube def\n");
+    }
+
+    #[test]
+    fn test_record_skips_known_ids() {
+        let path = temp_archive("dedupe");
+        let archive = JsonArchive::load(&path).unwrap();
+        assert!(archive.record(entry("abc")).unwrap());
+
+        let mut again = entry("abc");
+        again.extractor = "youtube".to_string();
+        again.path = PathBuf::from("/elsewhere/abc.mkv");
+        assert!(!archive.record(again).unwrap());
+
+        let reloaded = JsonArchive::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(reloaded.ytdlp_archive(), "youtube abc\n");
+        assert_eq!(reloaded.lock()[0].path, PathBuf::from("/videos/abc.webm"));
+    }
+
+    #[test]
+    fn test_parse_captured_outputs() {
+        let outputs = parse_captured_outputs(
+            "Youtube\tabc\tA - B\t/videos/a - b.webm\n\nbroken line\nVimeo\t42\tTab\there\t/videos/c.mp4\n",
+        );
+
+        assert_eq!(
+            outputs,
+            [
+                CapturedOutput {
+                    extractor: "Youtube".to_string(),
+                    id: "abc".to_string(),
+                    title: "A - B".to_string(),
+                    path: PathBuf::from("/videos/a - b.webm"),
+                },
+                CapturedOutput {
+                    extractor: "Vimeo".to_string(),
+                    id: "42".to_string(),
+                    title: "Tab\there".to_string(),
+                    path: PathBuf::from("/videos/c.mp4"),
+                },
+            ]
+        );
+    }
+}
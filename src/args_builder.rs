@@ -3,25 +3,128 @@
 use std::borrow::Cow;
 use std::path::Path;
 
-use crate::cli::SocialMediaTarget;
+use crate::cli::{AudioCodec, QualityPreset, VideoCodec};
 use crate::config::{
-    ARIA2C_ARGS, BATCH_SLEEP_SECONDS, CONTAINER_SOCM, CONTAINER_VIDEO, FILENAME_AUDIO_PRIMARY,
-    FILENAME_PRIMARY, FILENAME_VIDEO_ONLY_PRIMARY, FORMAT_AUDIO_ONLY, FORMAT_DEFAULT,
-    FORMAT_SORT_AUDIO, FORMAT_SORT_DEFAULT, FORMAT_SORT_VIDEO, FORMAT_VIDEO_ONLY,
-    REQUEST_SLEEP_SECONDS,
+    ARIA2C_ARGS, ARIA2C_TURBO_ARGS, BATCH_SLEEP_SECONDS, CAPTURE_OUTPUT_TEMPLATE, CONTAINER_SOCM,
+    CONTAINER_VIDEO, CONTAINER_VIDEO_ONLY, FILENAME_AUDIO_PRIMARY, FILENAME_PRIMARY,
+    FILENAME_VIDEO_ONLY_PRIMARY, FORMAT_AUDIO_ONLY, FORMAT_SORT_AUDIO, FORMAT_SORT_VIDEO,
+    FORMAT_VIDEO_ONLY, REMOTE_COMPONENTS_DEFAULT, REQUEST_SLEEP_SECONDS, THUMBNAIL_CONTAINERS,
+    UPLOADER_DIR_TEMPLATE,
 };
-use crate::mode::DownloadMode;
+use crate::mode::{DownloadMode, SocialMediaPreset};
 
 #[derive(Default)]
 pub struct YtDlpArgs<'a> {
     pub destination_path: Option<&'a Path>,
     pub cookies_from: Option<&'a str>,
+    /// Netscape cookie file, as written by `ytrs export-cookies`
+    pub cookies_file: Option<&'a Path>,
     pub mode: DownloadMode,
     pub apply_rate_limit: bool,
+    /// Inserted before the extension so a second output doesn't overwrite the first
+    pub filename_suffix: Option<&'a str>,
+    /// yt-dlp output template used instead of the per-mode default
+    pub output_template: Option<&'a str>,
+    pub by_uploader: bool,
+    /// Redownload over existing files instead of resuming or skipping them
+    pub force: bool,
+    /// One progress line per update so captured stdout can be parsed line by line
+    pub newline_progress: bool,
+    /// Append each final output's extractor, id and path to this file once post-processing is done
+    pub capture_outputs_to: Option<&'a Path>,
+    /// yt-dlp `--download-archive` file listing ids to skip
+    pub download_archive: Option<&'a Path>,
+    /// Re-encode the audio track in video modes; ignored for audio-only and socm
+    pub reencode_audio: Option<AudioCodec>,
+    /// Replaces the default `ejs:github` source
+    pub remote_components: Option<&'a str>,
+    /// Leave out `--remote-components` entirely for offline use
+    pub no_remote_components: bool,
+    /// Most aggressive aria2c tuning for a single fast file
+    pub turbo: bool,
+    /// aria2c connections per download, in place of the tuned `-x`/`-s` values
+    pub connections: Option<u8>,
+    /// Leave downloading to yt-dlp's own downloader instead of aria2c
+    pub no_aria2c: bool,
+    /// yt-dlp's own debug output
+    pub verbose: bool,
+    /// Keep DASH/HLS fragments after merging, for debugging failed merges
+    pub keep_fragments: bool,
+    /// Skip, rather than fail, items longer than this many seconds
+    pub max_duration: Option<u64>,
+    /// Final container regardless of what was downloaded; may re-encode
+    pub force_ext: Option<&'a str>,
+    /// Prefer audio tracks in this language; yt-dlp's default is the original track
+    pub audio_lang: Option<&'a str>,
+    /// Format preset for the default mode
+    pub quality: QualityPreset,
+    /// Height cap for the archive preset in place of 2160p
+    pub max_height: Option<u32>,
+    /// Codec the archive preset ranks first
+    pub codec: VideoCodec,
+    /// Drop height caps from the format selector and fall back to any best format
+    pub relaxed_format: bool,
+    /// Split `Artist<sep>Title` video titles into artist and title tags
+    pub artist_title_separator: Option<&'a str>,
+    /// Only remux social media downloads; ytrs runs the re-encode itself afterwards
+    pub defer_encode: bool,
+    /// yt-dlp `--dateafter` value: `YYYYMMDD` or a relative date like `today-2weeks`
+    pub date_after: Option<&'a str>,
+    /// Stop at the first archived item even without `date_after`
+    pub break_on_existing: bool,
+    /// Keep going past unavailable items in a channel or playlist
+    pub ignore_errors: bool,
+    /// Playlist indices to download, e.g. `1-3,7,10-`
+    pub playlist_items: Option<&'a str>,
+    /// Bandwidth cap for this one download, in bytes per second
+    pub limit_rate: Option<u64>,
+    /// Comma-separated SponsorBlock categories to cut from the video
+    pub sponsorblock: Option<&'a str>,
+    /// Subtitle languages for `--sub-langs`, e.g. `en,es` or `all`
+    pub subs: Option<&'a str>,
+    /// Also fetch auto-generated captions in those languages
+    pub auto_subs: bool,
+    pub embed_thumbnail: bool,
+    pub embed_metadata: bool,
+    /// aria2c session file that unfinished transfers are saved to and resumed from
+    pub aria_session: Option<&'a Path>,
+    /// aria2c's own bandwidth cap, in bytes per second
+    pub aria_rate_limit: Option<u64>,
+}
+
+/// Whether `-d` names a single output file rather than a directory or a FIFO
+#[must_use]
+pub fn writes_to_file(destination: &Path) -> bool {
+    destination_kind(destination) == DestinationKind::File
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DestinationKind {
+    Directory,
+    Fifo,
+    File,
+}
+
+fn destination_kind(path: &Path) -> DestinationKind {
+    if path.is_dir() {
+        return DestinationKind::Directory;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if path.metadata().is_ok_and(|m| m.file_type().is_fifo()) {
+            return DestinationKind::Fifo;
+        }
+    }
+
+    DestinationKind::File
 }
 
 pub fn build_ytdlp_args<'a>(url: &'a str, args: &YtDlpArgs<'a>) -> Vec<Cow<'a, str>> {
-    let output_template = build_output_template(args.mode, args.destination_path);
+    let fifo = args
+        .destination_path
+        .filter(|dest| destination_kind(dest) == DestinationKind::Fifo);
 
     let capacity = match args.mode {
         DownloadMode::SocialMedia(_) => 24,
@@ -29,24 +132,106 @@ pub fn build_ytdlp_args<'a>(url: &'a str, args: &YtDlpArgs<'a>) -> Vec<Cow<'a, s
     };
     let mut result: Vec<Cow<'a, str>> = Vec::with_capacity(capacity);
 
+    if !args.no_remote_components {
+        result.extend([
+            Cow::Borrowed("--remote-components"),
+            Cow::Borrowed(args.remote_components.unwrap_or(REMOTE_COMPONENTS_DEFAULT)),
+        ]);
+    }
+
     result.extend([
-        Cow::Borrowed("--remote-components"),
-        Cow::Borrowed("ejs:github"),
         Cow::Borrowed("--prefer-free-formats"),
         Cow::Borrowed("--format-sort-force"),
         Cow::Borrowed("--no-mtime"),
         Cow::Borrowed("--output"),
-        Cow::Owned(output_template),
-        Cow::Borrowed("--external-downloader"),
-        Cow::Borrowed("aria2c"),
-        Cow::Borrowed("--external-downloader-args"),
-        Cow::Borrowed(ARIA2C_ARGS),
     ]);
 
+    if let Some(fifo) = fifo {
+        // aria2c can't write into a pipe, and a .part file would be renamed over the FIFO
+        result.extend([fifo.to_string_lossy(), Cow::Borrowed("--no-part")]);
+    } else {
+        result.push(Cow::Owned(build_output_template(args)));
+        if !args.no_aria2c {
+            result.extend([
+                Cow::Borrowed("--external-downloader"),
+                Cow::Borrowed("aria2c"),
+                Cow::Borrowed("--external-downloader-args"),
+                external_downloader_args(args),
+            ]);
+        }
+    }
+
     if let Some(cookies) = args.cookies_from {
         result.push(Cow::Borrowed("--cookies-from-browser"));
         result.push(Cow::Borrowed(cookies));
     }
+    if let Some(cookies) = args.cookies_file {
+        result.push(Cow::Borrowed("--cookies"));
+        result.push(cookies.to_string_lossy());
+    }
+
+    if args.force {
+        result.extend([
+            Cow::Borrowed("--force-overwrites"),
+            Cow::Borrowed("--no-continue"),
+        ]);
+    }
+
+    if args.newline_progress {
+        result.push(Cow::Borrowed("--newline"));
+    }
+
+    if args.verbose {
+        result.push(Cow::Borrowed("--verbose"));
+    }
+
+    if args.keep_fragments {
+        result.push(Cow::Borrowed("--keep-fragments"));
+    }
+
+    if let Some(capture_file) = args.capture_outputs_to {
+        result.extend([
+            Cow::Borrowed("--print-to-file"),
+            Cow::Borrowed(CAPTURE_OUTPUT_TEMPLATE),
+            capture_file.to_string_lossy(),
+        ]);
+    }
+
+    // --force downloads archived items again, so yt-dlp mustn't skip them
+    let download_archive = args.download_archive.filter(|_| !args.force);
+    if let Some(archive) = download_archive {
+        result.extend([
+            Cow::Borrowed("--download-archive"),
+            archive.to_string_lossy(),
+        ]);
+    }
+
+    if let Some(date) = args.date_after {
+        result.extend([Cow::Borrowed("--dateafter"), Cow::Borrowed(date)]);
+    }
+    // Channels list newest first, so the first archived item means the rest are old too
+    if (args.date_after.is_some() || args.break_on_existing) && download_archive.is_some() {
+        result.push(Cow::Borrowed("--break-on-existing"));
+    }
+
+    if args.ignore_errors {
+        result.push(Cow::Borrowed("--ignore-errors"));
+    }
+
+    if let Some(items) = args.playlist_items {
+        result.extend([Cow::Borrowed("--playlist-items"), Cow::Borrowed(items)]);
+    }
+
+    if let Some(max) = args.max_duration {
+        result.extend([
+            Cow::Borrowed("--match-filter"),
+            Cow::Owned(duration_filter(max)),
+        ]);
+    }
+
+    if let Some(rate) = args.limit_rate {
+        result.extend([Cow::Borrowed("--limit-rate"), Cow::Owned(rate.to_string())]);
+    }
 
     if args.apply_rate_limit {
         result.extend([
@@ -57,11 +242,66 @@ pub fn build_ytdlp_args<'a>(url: &'a str, args: &YtDlpArgs<'a>) -> Vec<Cow<'a, s
         ]);
     }
 
+    if let Some(container) = default_container(&args.mode) {
+        result.push(Cow::Borrowed("--merge-output-format"));
+        result.push(Cow::Borrowed(container));
+    }
+
+    if let (Some(codec), DownloadMode::Default | DownloadMode::VideoOnly) =
+        (args.reencode_audio, &args.mode)
+    {
+        result.extend([
+            Cow::Borrowed("--use-postprocessor"),
+            Cow::Borrowed("FFmpegCopyStream"),
+            Cow::Borrowed("--postprocessor-args"),
+            Cow::Owned(codec.postprocessor_args()),
+        ]);
+    }
+
+    if let Some(separator) = args.artist_title_separator {
+        result.extend([
+            Cow::Borrowed("--parse-metadata"),
+            Cow::Owned(artist_title_rule(separator)),
+        ]);
+    }
+    // The split artist and title only reach the file as embedded metadata
+    if args.embed_metadata || args.artist_title_separator.is_some() {
+        result.push(Cow::Borrowed("--embed-metadata"));
+    }
+    if args.embed_thumbnail {
+        result.push(Cow::Borrowed("--embed-thumbnail"));
+    }
+
+    if let Some(ext) = args.force_ext {
+        result.extend([Cow::Borrowed("--recode-video"), Cow::Borrowed(ext)]);
+    }
+
+    if let Some(categories) = args.sponsorblock {
+        result.extend([
+            Cow::Borrowed("--sponsorblock-remove"),
+            Cow::Borrowed(categories),
+        ]);
+    }
+
+    if let Some(langs) = args.subs {
+        build_subtitle_args(&mut result, langs, args.auto_subs, &args.mode);
+    }
+
     match &args.mode {
-        DownloadMode::Default => build_default_args(&mut result),
-        DownloadMode::AudioOnly => build_audio_args(&mut result),
+        DownloadMode::Default => {
+            build_default_args(&mut result, args);
+        }
+        DownloadMode::AudioOnly => build_audio_args(&mut result, args.audio_lang),
         DownloadMode::VideoOnly => build_video_args(&mut result),
-        DownloadMode::SocialMedia(target) => build_socm_args(&mut result, *target),
+        DownloadMode::SocialMedia(preset) => {
+            build_socm_args(&mut result, preset, args.audio_lang, args.defer_encode);
+        }
+    }
+
+    if args.relaxed_format
+        && let Some(pos) = result.iter().position(|arg| arg == "--format")
+    {
+        result[pos + 1] = Cow::Owned(relax_format_selector(&result[pos + 1]));
     }
 
     result.push(Cow::Borrowed(url));
@@ -69,38 +309,252 @@ pub fn build_ytdlp_args<'a>(url: &'a str, args: &YtDlpArgs<'a>) -> Vec<Cow<'a, s
     result
 }
 
-fn build_output_template(mode: DownloadMode, destination: Option<&Path>) -> String {
-    let template = match mode {
+/// Strips `[height<=N]` caps, drops alternatives that become duplicates and
+/// makes sure plain `b` is the last resort
+#[must_use]
+pub fn relax_format_selector(selector: &str) -> String {
+    let mut alternatives: Vec<String> = Vec::new();
+    for alternative in selector.split('/') {
+        let mut relaxed = alternative.to_string();
+        while let Some(start) = relaxed.find("[height<=") {
+            let end = relaxed[start..]
+                .find(']')
+                .map_or(relaxed.len(), |i| start + i + 1);
+            relaxed.replace_range(start..end, "");
+        }
+        if !alternatives.contains(&relaxed) {
+            alternatives.push(relaxed);
+        }
+    }
+    if !alternatives.iter().any(|alternative| alternative == "b") {
+        alternatives.push("b".to_string());
+    }
+    alternatives.join("/")
+}
+
+/// yt-dlp escapes the literal parts of the target pattern, so any separator is safe
+#[must_use]
+pub fn artist_title_rule(separator: &str) -> String {
+    format!("%(title)s:%(artist)s{separator}%(title)s")
+}
+
+/// One argument per line with its argv index, quoted and escaped so stray whitespace or
+/// control characters stand out
+#[must_use]
+pub fn render_argv(program: &str, args: &[Cow<'_, str>]) -> String {
+    std::iter::once(program)
+        .chain(args.iter().map(AsRef::as_ref))
+        .enumerate()
+        .map(|(index, arg)| format!("  [{index:>2}] {arg:?}\n"))
+        .collect()
+}
+
+/// One copy-pasteable shell line, quoting only the arguments that need it
+#[must_use]
+pub fn render_command(program: &str, args: &[Cow<'_, str>]) -> String {
+    let is_plain = |arg: &str| {
+        !arg.is_empty()
+            && arg
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_./:=,@%+".contains(c))
+    };
+    std::iter::once(program)
+        .chain(args.iter().map(AsRef::as_ref))
+        .map(|arg| {
+            if is_plain(arg) {
+                arg.to_string()
+            } else {
+                shell_quote(arg)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Items without a known duration (e.g. live streams) don't match and are skipped too
+#[must_use]
+pub fn duration_filter(max_seconds: u64) -> String {
+    format!("duration<={max_seconds}")
+}
+
+/// The tuned aria2c flags; `connections` replaces both the per-server cap (`-x`)
+/// and the number of pieces each file is split into (`-s`)
+#[must_use]
+pub fn aria2c_args(turbo: bool, connections: Option<u8>) -> Cow<'static, str> {
+    let base = if turbo {
+        ARIA2C_TURBO_ARGS
+    } else {
+        ARIA2C_ARGS
+    };
+    let Some(connections) = connections else {
+        return Cow::Borrowed(base);
+    };
+    let connections = connections.to_string();
+    let mut tokens: Vec<&str> = base.split_whitespace().collect();
+    for i in 1..tokens.len() {
+        if matches!(tokens[i - 1], "-x" | "-s") {
+            tokens[i] = &connections;
+        }
+    }
+    Cow::Owned(tokens.join(" "))
+}
+
+/// aria2c flags, plus the session file when resuming across runs
+fn external_downloader_args<'a>(args: &YtDlpArgs<'a>) -> Cow<'a, str> {
+    let base = aria2c_args(args.turbo, args.connections);
+    if args.aria_rate_limit.is_none() && args.aria_session.is_none() {
+        return base;
+    }
+    let mut downloader_args = base.into_owned();
+    if let Some(rate) = args.aria_rate_limit {
+        downloader_args.push_str(&format!(" --max-overall-download-limit={rate}"));
+    }
+    if let Some(session) = args.aria_session {
+        let quoted = shell_quote(&session.to_string_lossy());
+        downloader_args.push_str(&format!(" --save-session={quoted}"));
+        // aria2c refuses to start if the input file is missing, so only resume from one that exists
+        if session.is_file() {
+            downloader_args.push_str(&format!(" --input-file={quoted}"));
+        }
+    }
+    Cow::Owned(downloader_args)
+}
+
+/// Single-quotes `value` for yt-dlp, which splits downloader args like a shell
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Tries every alternative with its audio restricted to `lang` first, then the
+/// unrestricted selector, so videos without that dub still download
+#[must_use]
+pub fn prefer_audio_language(selector: &str, lang: &str) -> String {
+    let filter = format!("[language^={lang}]");
+    let preferred: Vec<String> = selector
+        .split('/')
+        .filter_map(|alternative| {
+            let mut has_audio = false;
+            let pieces: Vec<String> = alternative
+                .split('+')
+                .map(|piece| {
+                    let name_end = piece.find('[').unwrap_or(piece.len());
+                    if matches!(&piece[..name_end], "ba" | "b") {
+                        has_audio = true;
+                        format!("{}{filter}{}", &piece[..name_end], &piece[name_end..])
+                    } else {
+                        piece.to_string()
+                    }
+                })
+                .collect();
+            has_audio.then(|| pieces.join("+"))
+        })
+        .collect();
+
+    if preferred.is_empty() {
+        selector.to_string()
+    } else {
+        format!("{}/{selector}", preferred.join("/"))
+    }
+}
+
+fn format_selector<'a>(selector: Cow<'a, str>, audio_lang: Option<&str>) -> Cow<'a, str> {
+    match audio_lang {
+        Some(lang) => Cow::Owned(prefer_audio_language(&selector, lang)),
+        None => selector,
+    }
+}
+
+/// Merge container per mode; `None` keeps the extracted audio container as-is
+#[must_use]
+pub const fn default_container(mode: &DownloadMode) -> Option<&'static str> {
+    match mode {
+        DownloadMode::Default => Some(CONTAINER_VIDEO),
+        DownloadMode::VideoOnly => Some(CONTAINER_VIDEO_ONLY),
+        DownloadMode::SocialMedia(_) => Some(CONTAINER_SOCM),
+        DownloadMode::AudioOnly => None,
+    }
+}
+
+/// A container the mode may produce that `--embed-thumbnail` can't write into
+#[must_use]
+pub fn thumbnail_incompatible_container<'a>(
+    mode: &DownloadMode,
+    force_ext: Option<&'a str>,
+) -> Option<&'a str> {
+    // Audio is extracted to opus
+    let containers = force_ext.or(default_container(mode)).unwrap_or("opus");
+    containers
+        .split('/')
+        .find(|container| !THUMBNAIL_CONTAINERS.contains(container))
+}
+
+fn build_output_template(args: &YtDlpArgs<'_>) -> String {
+    let template = args.output_template.unwrap_or(match args.mode {
         DownloadMode::AudioOnly => FILENAME_AUDIO_PRIMARY,
         DownloadMode::VideoOnly => FILENAME_VIDEO_ONLY_PRIMARY,
         DownloadMode::SocialMedia(_) | DownloadMode::Default => FILENAME_PRIMARY,
+    });
+    let template = match args.filename_suffix {
+        Some(suffix) => insert_suffix(template, suffix),
+        None => template.to_string(),
+    };
+    let template = if args.by_uploader {
+        format!("{UPLOADER_DIR_TEMPLATE}/{template}")
+    } else {
+        template
     };
 
-    match destination {
-        Some(dest) if dest.is_dir() => dest.join(template).to_string_lossy().into_owned(),
-        Some(dest) => dest.to_string_lossy().into_owned(),
-        None => template.to_string(),
+    match args.destination_path {
+        Some(dest) if destination_kind(dest) == DestinationKind::Directory => {
+            dest.join(template).to_string_lossy().into_owned()
+        }
+        Some(dest) => {
+            let dest = dest.to_string_lossy();
+            match args.filename_suffix {
+                Some(suffix) => insert_suffix(&dest, suffix),
+                None => dest.into_owned(),
+            }
+        }
+        None => template,
     }
 }
 
-fn build_default_args(result: &mut Vec<Cow<'_, str>>) {
+fn insert_suffix(template: &str, suffix: &str) -> String {
+    let ext_start = template
+        .strip_suffix(".%(ext)s")
+        .map(str::len)
+        .or_else(|| {
+            let name_start = template.rfind('/').map_or(0, |i| i + 1);
+            template[name_start..].rfind('.').map(|i| name_start + i)
+        })
+        .unwrap_or(template.len());
+
+    format!(
+        "{}{suffix}{}",
+        &template[..ext_start],
+        &template[ext_start..]
+    )
+}
+
+fn build_default_args(result: &mut Vec<Cow<'_, str>>, args: &YtDlpArgs<'_>) {
     result.extend([
-        Cow::Borrowed("--merge-output-format"),
-        Cow::Borrowed(CONTAINER_VIDEO),
         Cow::Borrowed("--format"),
-        Cow::Borrowed(FORMAT_DEFAULT),
+        format_selector(
+            args.quality.format_selector(args.max_height),
+            args.audio_lang,
+        ),
         Cow::Borrowed("--format-sort"),
-        Cow::Borrowed(FORMAT_SORT_DEFAULT),
+        Cow::Borrowed(args.quality.format_sort(args.codec)),
     ]);
 }
 
-fn build_audio_args(result: &mut Vec<Cow<'_, str>>) {
+fn build_audio_args(result: &mut Vec<Cow<'_, str>>, audio_lang: Option<&str>) {
     result.extend([
         Cow::Borrowed("-x"),
         Cow::Borrowed("--audio-format"),
         Cow::Borrowed("opus"),
         Cow::Borrowed("--format"),
-        Cow::Borrowed(FORMAT_AUDIO_ONLY),
+        format_selector(Cow::Borrowed(FORMAT_AUDIO_ONLY), audio_lang),
         Cow::Borrowed("--format-sort"),
         Cow::Borrowed(FORMAT_SORT_AUDIO),
     ]);
@@ -108,8 +562,6 @@ fn build_audio_args(result: &mut Vec<Cow<'_, str>>) {
 
 fn build_video_args(result: &mut Vec<Cow<'_, str>>) {
     result.extend([
-        Cow::Borrowed("--merge-output-format"),
-        Cow::Borrowed(CONTAINER_VIDEO),
         Cow::Borrowed("--format"),
         Cow::Borrowed(FORMAT_VIDEO_ONLY),
         Cow::Borrowed("--format-sort"),
@@ -117,28 +569,56 @@ fn build_video_args(result: &mut Vec<Cow<'_, str>>) {
     ]);
 }
 
-fn build_socm_args(result: &mut Vec<Cow<'_, str>>, target: SocialMediaTarget) {
-    let format_selector = target.format_selector();
-    let format_sort = target.format_sort();
-    let pp_args = target.postprocessor_args();
+/// Every video container ytrs merges into (webm, mkv, mp4) can carry subtitles, so only
+/// audio downloads keep them as side files
+fn build_subtitle_args(
+    result: &mut Vec<Cow<'_, str>>,
+    langs: &str,
+    auto_subs: bool,
+    mode: &DownloadMode,
+) {
+    result.extend([
+        Cow::Borrowed("--write-subs"),
+        Cow::Borrowed("--sub-langs"),
+        Cow::Owned(langs.to_string()),
+    ]);
+    if auto_subs {
+        result.push(Cow::Borrowed("--write-auto-subs"));
+    }
+    if *mode != DownloadMode::AudioOnly {
+        result.push(Cow::Borrowed("--embed-subs"));
+    }
+}
+
+fn build_socm_args(
+    result: &mut Vec<Cow<'_, str>>,
+    preset: &SocialMediaPreset,
+    audio_lang: Option<&str>,
+    defer_encode: bool,
+) {
+    let socm_format = preset.format_selector();
+    let format_sort = preset.format_sort();
 
     result.extend([
-        Cow::Borrowed("--merge-output-format"),
-        Cow::Borrowed(CONTAINER_SOCM),
         Cow::Borrowed("--remux-video"),
         Cow::Borrowed("mp4"),
         Cow::Borrowed("--format"),
-        Cow::Owned(format_selector),
+        format_selector(Cow::Owned(socm_format), audio_lang),
         Cow::Borrowed("--format-sort"),
         Cow::Owned(format_sort),
-        Cow::Borrowed("--postprocessor-args"),
-        Cow::Owned(pp_args),
     ]);
+    if !defer_encode {
+        result.extend([
+            Cow::Borrowed("--postprocessor-args"),
+            Cow::Owned(preset.postprocessor_args()),
+        ]);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cli::SocialMediaTarget;
     use std::path::Path;
 
     #[test]
@@ -179,7 +659,7 @@ mod tests {
     #[test]
     fn test_build_ytdlp_args_socm_discord() {
         let args = YtDlpArgs {
-            mode: DownloadMode::SocialMedia(SocialMediaTarget::Discord),
+            mode: DownloadMode::SocialMedia(SocialMediaTarget::Discord.preset()),
             ..Default::default()
         };
         let result = build_ytdlp_args("https://example.com", &args);
@@ -189,10 +669,23 @@ mod tests {
         assert!(result.iter().any(|s| s.contains("height<=1080")));
     }
 
+    #[test]
+    fn test_build_ytdlp_args_socm_deferred_encode() {
+        let args = YtDlpArgs {
+            mode: DownloadMode::SocialMedia(SocialMediaTarget::Discord.preset()),
+            defer_encode: true,
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+
+        assert!(result.windows(2).any(|w| w == ["--remux-video", "mp4"]));
+        assert!(!result.iter().any(|s| s == "--postprocessor-args"));
+    }
+
     #[test]
     fn test_build_ytdlp_args_socm_instagram() {
         let args = YtDlpArgs {
-            mode: DownloadMode::SocialMedia(SocialMediaTarget::Instagram),
+            mode: DownloadMode::SocialMedia(SocialMediaTarget::Instagram.preset()),
             ..Default::default()
         };
         let result = build_ytdlp_args("https://example.com", &args);
@@ -222,6 +715,18 @@ mod tests {
 
         assert!(result.iter().any(|s| s == "--cookies-from-browser"));
         assert!(result.iter().any(|s| s == "firefox"));
+
+        let args = YtDlpArgs {
+            cookies_file: Some(Path::new("/srv/cookies.txt")),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        assert!(
+            result
+                .windows(2)
+                .any(|w| w == ["--cookies", "/srv/cookies.txt"])
+        );
+        assert!(!result.iter().any(|s| s == "--cookies-from-browser"));
     }
 
     #[test]
@@ -236,6 +741,729 @@ mod tests {
         assert!(result.iter().any(|s| s == "--sleep-interval"));
     }
 
+    #[test]
+    fn test_default_container_per_mode() {
+        assert_eq!(
+            default_container(&DownloadMode::Default),
+            Some("webm/mkv/mp4")
+        );
+        assert_eq!(default_container(&DownloadMode::VideoOnly), Some("mkv"));
+        assert_eq!(
+            default_container(&DownloadMode::SocialMedia(
+                SocialMediaTarget::WhatsApp.preset()
+            )),
+            Some("mp4")
+        );
+        assert_eq!(default_container(&DownloadMode::AudioOnly), None);
+    }
+
+    #[test]
+    fn test_audio_has_no_merge_format() {
+        let args = YtDlpArgs {
+            mode: DownloadMode::AudioOnly,
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+
+        assert!(!result.iter().any(|s| s == "--merge-output-format"));
+    }
+
+    #[test]
+    fn test_video_only_merges_to_mkv() {
+        let args = YtDlpArgs {
+            mode: DownloadMode::VideoOnly,
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        let pos = result
+            .iter()
+            .position(|s| s == "--merge-output-format")
+            .unwrap();
+
+        assert_eq!(result[pos + 1], "mkv");
+    }
+
+    #[test]
+    fn test_filename_suffix_in_template() {
+        let args = YtDlpArgs {
+            filename_suffix: Some(" [share]"),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+
+        assert!(result.iter().any(|s| s.ends_with(" [share].%(ext)s")));
+    }
+
+    #[test]
+    fn test_filename_suffix_on_file_destination() {
+        assert_eq!(insert_suffix("/tmp/clip.mp4", "-x"), "/tmp/clip-x.mp4");
+        assert_eq!(insert_suffix("/tmp.d/clip", "-x"), "/tmp.d/clip-x");
+    }
+
+    #[test]
+    fn test_by_uploader_nests_template() {
+        let args = YtDlpArgs {
+            by_uploader: true,
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        let pos = result.iter().position(|s| s == "--output").unwrap();
+
+        assert_eq!(
+            result[pos + 1],
+            format!("{UPLOADER_DIR_TEMPLATE}/{FILENAME_PRIMARY}")
+        );
+    }
+
+    #[test]
+    fn test_by_uploader_under_directory() {
+        let args = YtDlpArgs {
+            destination_path: Some(Path::new("/tmp")),
+            by_uploader: true,
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+
+        assert!(
+            result
+                .iter()
+                .any(|s| s.starts_with(&format!("/tmp/{UPLOADER_DIR_TEMPLATE}/")))
+        );
+    }
+
+    #[test]
+    fn test_custom_output_template() {
+        let output = |args: &YtDlpArgs<'_>| {
+            let result = build_ytdlp_args("https://example.com", args);
+            let pos = result.iter().position(|s| s == "--output").unwrap();
+            result[pos + 1].to_string()
+        };
+        let args = YtDlpArgs {
+            output_template: Some("%(id)s.%(ext)s"),
+            mode: DownloadMode::AudioOnly,
+            ..Default::default()
+        };
+        assert_eq!(output(&args), "%(id)s.%(ext)s");
+
+        let args = YtDlpArgs {
+            destination_path: Some(Path::new("/tmp")),
+            filename_suffix: Some(".archive"),
+            ..args
+        };
+        assert_eq!(output(&args), "/tmp/%(id)s.archive.%(ext)s");
+    }
+
+    #[test]
+    fn test_force_overwrites() {
+        let args = YtDlpArgs {
+            force: true,
+            download_archive: Some(Path::new("/srv/archive.txt")),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+
+        assert!(result.iter().any(|s| s == "--force-overwrites"));
+        assert!(result.iter().any(|s| s == "--no-continue"));
+        assert!(!result.iter().any(|s| s == "--no-overwrites"));
+        assert!(!result.iter().any(|s| s == "--download-archive"));
+    }
+
+    #[test]
+    fn test_no_force_by_default() {
+        let result = build_ytdlp_args("https://example.com", &YtDlpArgs::default());
+
+        assert!(!result.iter().any(|s| s == "--force-overwrites"));
+    }
+
+    #[test]
+    fn test_capture_outputs_to_file() {
+        let args = YtDlpArgs {
+            capture_outputs_to: Some(Path::new("/tmp/ytrs-1-0.paths")),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+
+        let pos = result.iter().position(|s| s == "--print-to-file").unwrap();
+        assert_eq!(
+            result[pos + 1],
+            "after_move:%(extractor_key)s\t%(id)s\t%(title)s\t%(filepath)s"
+        );
+        assert_eq!(result[pos + 2], "/tmp/ytrs-1-0.paths");
+        assert!(!result.iter().any(|s| s == "--download-archive"));
+    }
+
+    #[test]
+    fn test_download_archive_file() {
+        let args = YtDlpArgs {
+            download_archive: Some(Path::new("/tmp/ytrs-1.archive")),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+
+        let pos = result
+            .iter()
+            .position(|s| s == "--download-archive")
+            .unwrap();
+        assert_eq!(result[pos + 1], "/tmp/ytrs-1.archive");
+    }
+
+    #[test]
+    fn test_since_with_and_without_archive() {
+        let args = YtDlpArgs {
+            date_after: Some("today-1month"),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://www.youtube.com/@chan", &args);
+        let pos = result.iter().position(|s| s == "--dateafter").unwrap();
+        assert_eq!(result[pos + 1], "today-1month");
+        assert!(!result.iter().any(|s| s == "--break-on-existing"));
+
+        let args = YtDlpArgs {
+            download_archive: Some(Path::new("/tmp/ytrs-1.archive")),
+            ..args
+        };
+        let result = build_ytdlp_args("https://www.youtube.com/@chan", &args);
+        assert!(result.iter().any(|s| s == "--dateafter"));
+        assert!(result.iter().any(|s| s == "--break-on-existing"));
+    }
+
+    #[test]
+    fn test_sync_args() {
+        let args = YtDlpArgs {
+            break_on_existing: true,
+            ignore_errors: true,
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://www.youtube.com/@chan", &args);
+        assert!(result.iter().any(|s| s == "--ignore-errors"));
+        assert!(!result.iter().any(|s| s == "--break-on-existing"));
+
+        let args = YtDlpArgs {
+            download_archive: Some(Path::new("/srv/archive.txt")),
+            ..args
+        };
+        let result = build_ytdlp_args("https://www.youtube.com/@chan", &args);
+        assert!(result.iter().any(|s| s == "--break-on-existing"));
+        assert!(!result.iter().any(|s| s == "--dateafter"));
+    }
+
+    #[test]
+    fn test_playlist_items() {
+        let args = YtDlpArgs {
+            playlist_items: Some("1-3,7,10-"),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://www.youtube.com/playlist?list=PL1", &args);
+        let pos = result.iter().position(|s| s == "--playlist-items").unwrap();
+        assert_eq!(result[pos + 1], "1-3,7,10-");
+
+        let result = build_ytdlp_args("https://example.com", &YtDlpArgs::default());
+        assert!(!result.iter().any(|s| s == "--playlist-items"));
+    }
+
+    #[test]
+    fn test_sponsorblock_remove() {
+        let args = YtDlpArgs {
+            sponsorblock: Some("sponsor,intro"),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        let pos = result
+            .iter()
+            .position(|s| s == "--sponsorblock-remove")
+            .unwrap();
+        assert_eq!(result[pos + 1], "sponsor,intro");
+    }
+
+    #[test]
+    fn test_subtitle_args() {
+        let args = YtDlpArgs {
+            subs: Some("en,es"),
+            auto_subs: true,
+            mode: DownloadMode::SocialMedia(SocialMediaTarget::Discord.preset()),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        assert!(result.iter().any(|s| s == "--write-subs"));
+        assert!(result.windows(2).any(|w| w == ["--sub-langs", "en,es"]));
+        assert!(result.iter().any(|s| s == "--write-auto-subs"));
+        assert!(result.iter().any(|s| s == "--embed-subs"));
+
+        let audio = YtDlpArgs {
+            subs: Some("all"),
+            mode: DownloadMode::AudioOnly,
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &audio);
+        assert!(result.windows(2).any(|w| w == ["--sub-langs", "all"]));
+        assert!(
+            !result
+                .iter()
+                .any(|s| s == "--embed-subs" || s == "--write-auto-subs")
+        );
+
+        let result = build_ytdlp_args("https://example.com", &YtDlpArgs::default());
+        assert!(!result.iter().any(|s| s.contains("subs")));
+    }
+
+    #[test]
+    fn test_limit_rate() {
+        let args = YtDlpArgs {
+            limit_rate: Some(262_144),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        let pos = result.iter().position(|s| s == "--limit-rate").unwrap();
+        assert_eq!(result[pos + 1], "262144");
+
+        let result = build_ytdlp_args("https://example.com", &YtDlpArgs::default());
+        assert!(!result.iter().any(|s| s == "--limit-rate"));
+    }
+
+    #[test]
+    fn test_render_argv_one_arg_per_line() {
+        let args = [
+            Cow::Borrowed("--output"),
+            Cow::Owned("/media/My Videos/%(title)s.%(ext)s".to_string()),
+            Cow::Borrowed("a\tb"),
+        ];
+        let rendered = render_argv("yt-dlp", &args);
+
+        assert_eq!(
+            rendered,
+            "  [ 0] \"yt-dlp\"\n  [ 1] \"--output\"\n  [ 2] \"/media/My Videos/%(title)s.%(ext)s\"\n  [ 3] \"a\\tb\"\n"
+        );
+    }
+
+    #[test]
+    fn test_render_command_quotes_only_when_needed() {
+        let args = [
+            Cow::Borrowed("--format-sort"),
+            Cow::Borrowed("res:1080,vcodec:avc"),
+            Cow::Borrowed("--output"),
+            Cow::Borrowed("/media/My Videos/%(title)s.%(ext)s"),
+            Cow::Borrowed("it's"),
+            Cow::Borrowed(""),
+        ];
+
+        assert_eq!(
+            render_command("yt-dlp", &args),
+            r"yt-dlp --format-sort res:1080,vcodec:avc --output '/media/My Videos/%(title)s.%(ext)s' 'it'\''s' ''"
+        );
+    }
+
+    #[test]
+    fn test_reencode_audio_postprocessor() {
+        let args = YtDlpArgs {
+            reencode_audio: Some(AudioCodec::Opus),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+
+        let pos = result
+            .iter()
+            .position(|s| s == "--use-postprocessor")
+            .unwrap();
+        assert_eq!(result[pos + 1], "FFmpegCopyStream");
+        assert_eq!(result[pos + 2], "--postprocessor-args");
+        assert_eq!(result[pos + 3], "CopyStream:-c:v copy -c:a libopus");
+    }
+
+    #[test]
+    fn test_reencode_audio_ignored_for_audio_only() {
+        let args = YtDlpArgs {
+            mode: DownloadMode::AudioOnly,
+            reencode_audio: Some(AudioCodec::Mp3),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+
+        assert!(!result.iter().any(|s| s == "--use-postprocessor"));
+    }
+
+    fn remote_components(result: &[Cow<'_, str>]) -> Option<String> {
+        let pos = result.iter().position(|s| s == "--remote-components")?;
+        Some(result[pos + 1].to_string())
+    }
+
+    #[test]
+    fn test_remote_components_default() {
+        let result = build_ytdlp_args("https://example.com", &YtDlpArgs::default());
+        assert_eq!(remote_components(&result).as_deref(), Some("ejs:github"));
+    }
+
+    #[test]
+    fn test_remote_components_override() {
+        let args = YtDlpArgs {
+            remote_components: Some("ejs:npm"),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        assert_eq!(remote_components(&result).as_deref(), Some("ejs:npm"));
+    }
+
+    #[test]
+    fn test_no_remote_components() {
+        let args = YtDlpArgs {
+            no_remote_components: true,
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        assert_eq!(remote_components(&result), None);
+    }
+
+    #[test]
+    fn test_turbo_aria2c_args() {
+        let args = YtDlpArgs {
+            turbo: true,
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        let pos = result
+            .iter()
+            .position(|s| s == "--external-downloader-args")
+            .unwrap();
+
+        assert_eq!(
+            result[pos + 1],
+            "-x 16 -s 16 -k 1M --continue=true --file-allocation=falloc --disk-cache=256M --enable-color=false"
+        );
+        assert_eq!(aria2c_args(false, None), ARIA2C_ARGS);
+    }
+
+    #[test]
+    fn test_connection_cap() {
+        assert_eq!(
+            aria2c_args(false, Some(4)),
+            "-x 4 -s 4 -k 2M --file-allocation=falloc --disk-cache=64M --enable-color=false"
+        );
+        assert!(aria2c_args(true, Some(2)).starts_with("-x 2 -s 2 -k 1M --continue=true"));
+
+        let args = YtDlpArgs {
+            connections: Some(1),
+            aria_rate_limit: Some(1024),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        let pos = result
+            .iter()
+            .position(|s| s == "--external-downloader-args")
+            .unwrap();
+        assert!(result[pos + 1].starts_with("-x 1 -s 1 -k 2M"));
+        assert!(result[pos + 1].ends_with("--max-overall-download-limit=1024"));
+    }
+
+    #[test]
+    fn test_aria_rate_limit() {
+        let external_args = |args: &YtDlpArgs| {
+            let result = build_ytdlp_args("https://example.com", args);
+            let pos = result
+                .iter()
+                .position(|s| s == "--external-downloader-args")
+                .unwrap();
+            result[pos + 1].to_string()
+        };
+        let limited = YtDlpArgs {
+            aria_rate_limit: Some(2 * 1024 * 1024),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            external_args(&limited),
+            format!("{ARIA2C_ARGS} --max-overall-download-limit=2097152")
+        );
+        assert_eq!(external_args(&YtDlpArgs::default()), ARIA2C_ARGS);
+    }
+
+    #[test]
+    fn test_aria_session_args() {
+        let dir = std::env::temp_dir().join(format!("ytrs-aria-session-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let session = dir.join("big file.session");
+        let external_args = |args: &YtDlpArgs| {
+            let result = build_ytdlp_args("https://example.com", args);
+            let pos = result
+                .iter()
+                .position(|s| s == "--external-downloader-args")
+                .unwrap();
+            result[pos + 1].to_string()
+        };
+        let args = YtDlpArgs {
+            aria_session: Some(&session),
+            ..Default::default()
+        };
+
+        let first_run = external_args(&args);
+        std::fs::write(&session, "").unwrap();
+        let resumed = external_args(&args);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let quoted = format!("'{}'", session.display());
+        assert!(first_run.starts_with(ARIA2C_ARGS));
+        assert!(first_run.ends_with(&format!(" --save-session={quoted}")));
+        assert!(!first_run.contains("--input-file"));
+        assert!(resumed.ends_with(&format!(" --save-session={quoted} --input-file={quoted}")));
+        assert_eq!(external_args(&YtDlpArgs::default()), ARIA2C_ARGS);
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn test_max_duration_match_filter() {
+        let args = YtDlpArgs {
+            max_duration: Some(3600),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        let pos = result.iter().position(|s| s == "--match-filter").unwrap();
+
+        assert_eq!(result[pos + 1], "duration<=3600");
+    }
+
+    #[test]
+    fn test_force_ext_recodes_video() {
+        let args = YtDlpArgs {
+            force_ext: Some("mp4"),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        let pos = result.iter().position(|s| s == "--recode-video").unwrap();
+
+        assert_eq!(result[pos + 1], "mp4");
+        // The merge format stays as a separate, earlier step
+        assert!(result.iter().any(|s| s == "--merge-output-format"));
+    }
+
+    #[test]
+    fn test_artist_title_split_rule() {
+        assert_eq!(artist_title_rule(" - "), "%(title)s:%(artist)s - %(title)s");
+
+        let args = YtDlpArgs {
+            artist_title_separator: Some(" | "),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        let pos = result.iter().position(|s| s == "--parse-metadata").unwrap();
+
+        assert_eq!(result[pos + 1], "%(title)s:%(artist)s | %(title)s");
+        assert!(result.iter().any(|s| s == "--embed-metadata"));
+    }
+
+    #[test]
+    fn test_embed_flags() {
+        for mode in [
+            DownloadMode::Default,
+            DownloadMode::SocialMedia(SocialMediaTarget::Discord.preset()),
+        ] {
+            let args = YtDlpArgs {
+                mode,
+                embed_thumbnail: true,
+                embed_metadata: true,
+                artist_title_separator: Some(" - "),
+                ..Default::default()
+            };
+            let result = build_ytdlp_args("https://example.com", &args);
+            assert!(result.iter().any(|s| s == "--embed-thumbnail"));
+            let metadata = result.iter().filter(|s| *s == "--embed-metadata").count();
+            assert_eq!(metadata, 1);
+        }
+
+        let result = build_ytdlp_args("https://example.com", &YtDlpArgs::default());
+        assert!(!result.iter().any(|s| s.starts_with("--embed")));
+    }
+
+    #[test]
+    fn test_thumbnail_container_check() {
+        assert_eq!(
+            thumbnail_incompatible_container(&DownloadMode::Default, None),
+            Some("webm")
+        );
+        assert_eq!(
+            thumbnail_incompatible_container(&DownloadMode::Default, Some("mkv")),
+            None
+        );
+        assert_eq!(
+            thumbnail_incompatible_container(
+                &DownloadMode::SocialMedia(SocialMediaTarget::Discord.preset()),
+                None
+            ),
+            None
+        );
+        assert_eq!(
+            thumbnail_incompatible_container(&DownloadMode::AudioOnly, None),
+            None
+        );
+        assert_eq!(
+            thumbnail_incompatible_container(&DownloadMode::VideoOnly, Some("webm")),
+            Some("webm")
+        );
+    }
+
+    #[test]
+    fn test_audio_lang_preference() {
+        assert_eq!(
+            prefer_audio_language("bv*[height<=2160]+ba/b[height<=2160]", "en"),
+            "bv*[height<=2160]+ba[language^=en]/b[language^=en][height<=2160]/bv*[height<=2160]+ba/b[height<=2160]"
+        );
+        assert_eq!(
+            prefer_audio_language("bv[height<=2160]", "en"),
+            "bv[height<=2160]"
+        );
+
+        let args = YtDlpArgs {
+            mode: DownloadMode::AudioOnly,
+            audio_lang: Some("ja"),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        let pos = result.iter().position(|s| s == "--format").unwrap();
+        assert_eq!(result[pos + 1], "ba[language^=ja]/b[language^=ja]/ba/b");
+    }
+
+    #[test]
+    fn test_quality_preset_in_default_mode() {
+        let args = YtDlpArgs {
+            quality: QualityPreset::Balanced,
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+
+        let pos = result.iter().position(|s| s == "--format-sort").unwrap();
+        assert_eq!(result[pos + 1], "res:1080,vcodec:avc,acodec:aac,fps,size");
+        let pos = result.iter().position(|s| s == "--format").unwrap();
+        assert_eq!(
+            result[pos + 1],
+            QualityPreset::Balanced.format_selector(None)
+        );
+    }
+
+    #[test]
+    fn test_codec_picks_format_sort() {
+        let args = YtDlpArgs {
+            codec: VideoCodec::Av1,
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        let pos = result.iter().position(|s| s == "--format-sort").unwrap();
+        let sort = &result[pos + 1];
+        assert!(sort.find("vcodec:av01").unwrap() < sort.find("vcodec:vp9").unwrap());
+
+        let result = build_ytdlp_args("https://example.com", &YtDlpArgs::default());
+        let pos = result.iter().position(|s| s == "--format-sort").unwrap();
+        assert!(result[pos + 1].starts_with("res,fps,vcodec:vp9.2,"));
+    }
+
+    #[test]
+    fn test_max_height_caps_default_format() {
+        let format = |max_height| {
+            let args = YtDlpArgs {
+                max_height,
+                ..Default::default()
+            };
+            let result = build_ytdlp_args("https://example.com", &args);
+            let pos = result.iter().position(|s| s == "--format").unwrap();
+            result[pos + 1].to_string()
+        };
+
+        assert_eq!(format(None), "bv*[height<=2160]+ba/b[height<=2160]");
+        assert_eq!(format(Some(1080)), "bv*[height<=1080]+ba/b[height<=1080]");
+        assert_eq!(format(Some(4320)), "bv*[height<=4320]+ba/b[height<=4320]");
+    }
+
+    #[test]
+    fn test_relaxed_format_selector() {
+        assert_eq!(
+            relax_format_selector("bv*[height<=2160]+ba/b[height<=2160]"),
+            "bv*+ba/b"
+        );
+        assert_eq!(relax_format_selector("bv[height<=2160]"), "bv/b");
+        assert_eq!(
+            relax_format_selector("bv*[height<=1080]+ba/b[height<=1080]/bv*+ba/b"),
+            "bv*+ba/b"
+        );
+    }
+
+    #[test]
+    fn test_destination_kind() {
+        assert_eq!(
+            destination_kind(Path::new("/tmp")),
+            DestinationKind::Directory
+        );
+        assert_eq!(
+            destination_kind(Path::new("/tmp/ytrs-missing.mkv")),
+            DestinationKind::File
+        );
+    }
+
+    #[test]
+    fn test_verbose_passes_through() {
+        let args = YtDlpArgs {
+            verbose: true,
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        assert!(result.iter().any(|s| s == "--verbose"));
+
+        let result = build_ytdlp_args("https://example.com", &YtDlpArgs::default());
+        assert!(!result.iter().any(|s| s == "--verbose"));
+    }
+
+    #[test]
+    fn test_keep_fragments_passes_through() {
+        let args = YtDlpArgs {
+            keep_fragments: true,
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        assert!(result.iter().any(|s| s == "--keep-fragments"));
+
+        let result = build_ytdlp_args("https://example.com", &YtDlpArgs::default());
+        assert!(!result.iter().any(|s| s == "--keep-fragments"));
+    }
+
+    #[test]
+    fn test_no_aria2c_uses_native_downloader() {
+        let args = YtDlpArgs {
+            no_aria2c: true,
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+
+        assert!(!result.iter().any(|s| s == "aria2c"));
+        assert!(
+            !result
+                .iter()
+                .any(|s| s.starts_with("--external-downloader"))
+        );
+        let pos = result.iter().position(|s| s == "--output").unwrap();
+        assert!(result[pos + 1].ends_with(".%(ext)s"));
+    }
+
+    #[test]
+    fn test_fifo_destination_skips_aria2c() {
+        let fifo = std::env::temp_dir().join(format!("ytrs-test-fifo-{}", std::process::id()));
+        let created = std::process::Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .is_ok_and(|status| status.success());
+        if !created {
+            return;
+        }
+
+        let kind = destination_kind(&fifo);
+        let args = YtDlpArgs {
+            destination_path: Some(&fifo),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args("https://example.com", &args);
+        std::fs::remove_file(&fifo).unwrap();
+
+        assert_eq!(kind, DestinationKind::Fifo);
+        let pos = result.iter().position(|s| s == "--output").unwrap();
+        assert_eq!(result[pos + 1], fifo.to_string_lossy());
+        assert!(result.iter().any(|s| s == "--no-part"));
+        assert!(!result.iter().any(|s| s == "aria2c"));
+    }
+
     #[test]
     fn test_url_always_last() {
         let args = YtDlpArgs::default();
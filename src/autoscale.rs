@@ -0,0 +1,194 @@
+//! `--auto-parallel`: more concurrent downloads while they add throughput, fewer when
+//! they stop helping or start failing
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::config::AUTO_PARALLEL_MIN_GAIN;
+
+/// What to do with the concurrency limit after a round of downloads
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scaling {
+    Up,
+    Hold,
+    Down,
+}
+
+/// Decides once per round; a round is as many finished downloads as the current limit
+#[derive(Debug)]
+pub struct ParallelController {
+    limit: usize,
+    max: usize,
+    best: Option<f64>,
+    last_failures: usize,
+    last: Scaling,
+    /// Set once a step up stopped paying off or failures rose; no more probing after
+    settled: bool,
+}
+
+impl ParallelController {
+    #[must_use]
+    pub const fn new(start: usize, max: usize) -> Self {
+        Self {
+            limit: start,
+            max,
+            best: None,
+            last_failures: 0,
+            last: Scaling::Hold,
+            settled: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Takes one round's aggregate throughput and failure count, and moves the limit
+    pub fn observe(&mut self, throughput: f64, failures: usize) -> Scaling {
+        let improved = self
+            .best
+            .is_none_or(|best| throughput > best * (1.0 + AUTO_PARALLEL_MIN_GAIN));
+        let wanted = if failures > self.last_failures {
+            self.settled = true;
+            Scaling::Down
+        } else if improved {
+            self.best = Some(throughput);
+            if self.settled {
+                Scaling::Hold
+            } else {
+                Scaling::Up
+            }
+        } else if self.last == Scaling::Up {
+            // The extra download didn't add throughput, so give it back
+            self.settled = true;
+            Scaling::Down
+        } else {
+            Scaling::Hold
+        };
+        self.last_failures = failures;
+
+        let scaling = match wanted {
+            Scaling::Up if self.limit >= self.max => Scaling::Hold,
+            Scaling::Down if self.limit <= 1 => Scaling::Hold,
+            scaling => scaling,
+        };
+        match scaling {
+            Scaling::Up => self.limit += 1,
+            Scaling::Down => self.limit -= 1,
+            Scaling::Hold => {}
+        }
+        self.last = scaling;
+        scaling
+    }
+}
+
+/// Adds up finished downloads into rounds for the controller
+#[derive(Debug)]
+pub struct AutoParallel {
+    controller: ParallelController,
+    round_started: Instant,
+    round_bytes: u64,
+    round_done: usize,
+    round_failures: usize,
+}
+
+impl AutoParallel {
+    #[must_use]
+    pub fn new(start: usize, max: usize) -> Self {
+        Self {
+            controller: ParallelController::new(start, max),
+            round_started: Instant::now(),
+            round_bytes: 0,
+            round_done: 0,
+            round_failures: 0,
+        }
+    }
+
+    /// Records one finished download; at the end of a round, returns the decision
+    /// and the new limit
+    #[allow(clippy::cast_precision_loss)]
+    pub fn record(&mut self, bytes: u64, failed: bool) -> Option<(Scaling, usize)> {
+        self.round_bytes += bytes;
+        self.round_done += 1;
+        self.round_failures += usize::from(failed);
+        if self.round_done < self.controller.limit() {
+            return None;
+        }
+
+        let elapsed = self.round_started.elapsed().max(Duration::from_millis(1));
+        let throughput = self.round_bytes as f64 / elapsed.as_secs_f64();
+        let scaling = self.controller.observe(throughput, self.round_failures);
+        self.round_started = Instant::now();
+        self.round_bytes = 0;
+        self.round_done = 0;
+        self.round_failures = 0;
+        Some((scaling, self.controller.limit()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(controller: &mut ParallelController, rounds: &[(f64, usize)]) -> Vec<usize> {
+        rounds
+            .iter()
+            .map(|&(throughput, failures)| {
+                controller.observe(throughput, failures);
+                controller.limit()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_scales_up_until_throughput_plateaus() {
+        let mut controller = ParallelController::new(2, 8);
+        // 20.5 isn't 10% better than 20, so the step to 5 is undone and the limit stays
+        let limits = run(
+            &mut controller,
+            &[
+                (10.0, 0),
+                (15.0, 0),
+                (20.0, 0),
+                (20.5, 0),
+                (30.0, 0),
+                (19.0, 0),
+            ],
+        );
+        assert_eq!(limits, [3, 4, 5, 4, 4, 4]);
+    }
+
+    #[test]
+    fn test_backs_off_when_failures_rise() {
+        let mut controller = ParallelController::new(2, 8);
+        let limits = run(
+            &mut controller,
+            &[(10.0, 0), (20.0, 0), (25.0, 2), (30.0, 2)],
+        );
+        assert_eq!(limits, [3, 4, 3, 3]);
+
+        // Failing from the first round still never goes below one download
+        let mut controller = ParallelController::new(2, 8);
+        let limits = run(&mut controller, &[(10.0, 1), (10.0, 2), (10.0, 3)]);
+        assert_eq!(limits, [1, 1, 1]);
+    }
+
+    #[test]
+    fn test_stays_within_max() {
+        let mut controller = ParallelController::new(2, 3);
+        let limits = run(&mut controller, &[(10.0, 0), (20.0, 0), (40.0, 0)]);
+        assert_eq!(limits, [3, 3, 3]);
+    }
+
+    #[test]
+    fn test_rounds_follow_the_limit() {
+        let mut auto = AutoParallel::new(2, 8);
+        assert_eq!(auto.record(1000, false), None);
+        assert_eq!(auto.record(1000, false), Some((Scaling::Up, 3)));
+        assert_eq!(auto.record(1000, false), None);
+        assert_eq!(auto.record(0, true), None);
+        assert_eq!(auto.record(0, true), Some((Scaling::Down, 2)));
+    }
+}
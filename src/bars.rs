@@ -0,0 +1,119 @@
+//! Per-URL terminal progress bars for `--progress-style`
+
+use std::time::Duration;
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+use crate::cli::ProgressDisplay;
+use crate::progress::{ProgressUpdate, parse_progress_line};
+
+/// indicatif template for each style; `None` keeps yt-dlp's own output and the
+/// start/complete lines
+#[must_use]
+pub const fn template(style: ProgressDisplay) -> Option<&'static str> {
+    match style {
+        ProgressDisplay::Bar => Some("{prefix:.bold} [{bar:30.cyan/blue}] {percent:>3}% {msg}"),
+        ProgressDisplay::Spinner => Some("{spinner:.cyan} {prefix:.bold} {percent:>3}% {msg}"),
+        // No glyphs or colors, for terminals that can't render them
+        ProgressDisplay::Plain => Some("{prefix} {percent:>3}% {msg}"),
+        ProgressDisplay::None => None,
+    }
+}
+
+/// Adds a bar for `url` to `multi`, or returns `None` when the style has no bars
+#[must_use]
+pub fn new_bar(style: ProgressDisplay, multi: &MultiProgress, url: &str) -> Option<ProgressBar> {
+    let bar_style = ProgressStyle::with_template(template(style)?)
+        .ok()?
+        .progress_chars("=> ");
+    let bar = multi.add(ProgressBar::new(100));
+    bar.set_style(bar_style);
+    bar.set_prefix(url.to_string());
+    if style == ProgressDisplay::Spinner {
+        bar.enable_steady_tick(Duration::from_millis(120));
+    }
+    Some(bar)
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn update_bar(bar: &ProgressBar, update: &ProgressUpdate) {
+    bar.set_position(update.percent.clamp(0.0, 100.0) as u64);
+    let speed = update.speed.as_deref().unwrap_or_default();
+    let message = match &update.eta {
+        Some(eta) => format!("{speed} ETA {eta}"),
+        None => speed.to_string(),
+    };
+    bar.set_message(message);
+}
+
+/// Moves the bar on progress lines; any other output, like `[Merger]` steps or lines
+/// the parser doesn't recognise, stands in as the message so the bar never looks stuck
+pub fn relay_to_bar(bar: &ProgressBar, line: &str) {
+    match parse_progress_line(line) {
+        Some(update) => update_bar(bar, &update),
+        None if !line.trim().is_empty() => bar.set_message(line.trim().to_string()),
+        None => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_per_style() {
+        assert!(template(ProgressDisplay::Bar).unwrap().contains("{bar:30"));
+        assert!(
+            template(ProgressDisplay::Spinner)
+                .unwrap()
+                .starts_with("{spinner")
+        );
+        let plain = template(ProgressDisplay::Plain).unwrap();
+        assert!(!plain.contains("{bar") && !plain.contains("{spinner"));
+        assert_eq!(template(ProgressDisplay::None), None);
+
+        for style in [
+            ProgressDisplay::Bar,
+            ProgressDisplay::Spinner,
+            ProgressDisplay::Plain,
+        ] {
+            assert!(ProgressStyle::with_template(template(style).unwrap()).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_no_bar_without_template() {
+        let multi = MultiProgress::with_draw_target(indicatif::ProgressDrawTarget::hidden());
+        assert!(new_bar(ProgressDisplay::None, &multi, "https://a.example/1").is_none());
+
+        let bar = new_bar(ProgressDisplay::Plain, &multi, "https://a.example/1").unwrap();
+        update_bar(
+            &bar,
+            &ProgressUpdate {
+                percent: 42.3,
+                speed: Some("1.23MiB/s".to_string()),
+                eta: Some("00:05".to_string()),
+            },
+        );
+        assert_eq!(bar.position(), 42);
+        assert_eq!(bar.message(), "1.23MiB/s ETA 00:05");
+    }
+
+    #[test]
+    fn test_other_output_becomes_the_message() {
+        let multi = MultiProgress::with_draw_target(indicatif::ProgressDrawTarget::hidden());
+        let bar = new_bar(ProgressDisplay::Bar, &multi, "https://a.example/1").unwrap();
+
+        relay_to_bar(
+            &bar,
+            "[download]  42.3% of ~10.00MiB at 1.23MiB/s ETA 00:05",
+        );
+        relay_to_bar(&bar, "");
+        assert_eq!(bar.position(), 42);
+        assert_eq!(bar.message(), "1.23MiB/s ETA 00:05");
+
+        relay_to_bar(&bar, "[Merger] Merging formats into \"clip.webm\"");
+        assert_eq!(bar.position(), 42);
+        assert_eq!(bar.message(), "[Merger] Merging formats into \"clip.webm\"");
+    }
+}
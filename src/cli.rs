@@ -1,14 +1,28 @@
 //! CLI definitions for ytrs - clap derive macros with social media presets
 
+use std::ffi::OsStr;
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
+use std::time::Duration;
 
-use clap::{Parser, ValueEnum};
+use clap::builder::{PossibleValue, StringValueParser, TypedValueParser};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
 
+use crate::config::{ARTIST_TITLE_SEPARATOR, DEFAULT_PARALLEL, SPONSORBLOCK_CATEGORIES};
+use crate::cookies::validate_cookie_browser;
+use crate::downloader::DownloadOptions;
 use crate::error::{Result, YtrsError};
-use crate::mode::DownloadMode;
+use crate::expand::expand_path;
+use crate::info::InfoCommand;
+use crate::mode::{DownloadMode, UserPresets, resolve_preset};
+use crate::size::parse_size;
+use crate::staging::staging_base;
+use crate::timing::parse_duration;
+use crate::url_validator::load_url_list;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SocialMediaTarget {
     /// 16MB limit, H.264/AAC, 1080p
     #[value(name = "whatsapp", alias = "wa")]
@@ -48,6 +62,181 @@ impl std::fmt::Display for SocialMediaTarget {
     }
 }
 
+/// Takes any name so config-file presets work, while `--help` still lists the built-in
+/// targets; unknown names are rejected once the config file is merged
+#[derive(Clone)]
+struct SocmTargetParser;
+
+impl TypedValueParser for SocmTargetParser {
+    type Value = String;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &OsStr,
+    ) -> std::result::Result<String, clap::Error> {
+        StringValueParser::new().parse_ref(cmd, arg, value)
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(
+            SocialMediaTarget::value_variants()
+                .iter()
+                .filter_map(ValueEnum::to_possible_value),
+        ))
+    }
+}
+
+/// Audio codec for `--reencode-audio`; the video stream is always copied
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioCodec {
+    Aac,
+    Opus,
+    Mp3,
+}
+
+/// Video codec the archive preset ranks first
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    #[default]
+    Vp9,
+    /// Smaller files where AV1 decodes in hardware
+    Av1,
+    /// Plays on nearly anything, at larger sizes
+    H264,
+}
+
+/// Format selection for the default mode, from max quality down to small files
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QualityPreset {
+    /// Highest resolution up to 2160p, preferring VP9 and Opus
+    #[default]
+    Archive,
+    /// About 1080p in H.264 and AAC for compatibility
+    Balanced,
+    /// Smallest files at up to 480p
+    Small,
+}
+
+/// What `--on-collision` does when URLs in a batch resolve to the same output file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnCollision {
+    /// Print the clashing URLs and download anyway
+    Warn,
+    /// Append " (2)", " (3)", ... to later URLs' filenames
+    Index,
+    /// Keep only the first URL for each filename
+    Skip,
+}
+
+/// What a batch does when `-d` names a single file that every URL would overwrite
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileDestBatch {
+    /// Refuse to start the batch
+    #[default]
+    Error,
+    /// Append " (2)", " (3)", ... to every URL after the first, as --on-collision index does
+    Index,
+}
+
+/// How `--progress-style` shows each download's progress
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressDisplay {
+    /// A bar per URL
+    Bar,
+    /// A spinner with the percentage per URL
+    Spinner,
+    /// A text-only line per URL, without glyphs or colors
+    Plain,
+    /// yt-dlp's own output between "Starting:" and "Completed:" lines
+    #[default]
+    None,
+}
+
+/// Browsers whose profile directories `--list-browser-profiles` knows how to scan
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ProfileBrowser {
+    Firefox,
+    Chrome,
+}
+
+impl std::fmt::Display for ProfileBrowser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Firefox => "firefox",
+            Self::Chrome => "chrome",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ThemeName {
+    /// Cyan, green, yellow and red for dark backgrounds
+    #[default]
+    Dark,
+    /// Darker hues that stay readable on light backgrounds
+    Light,
+    /// No colors or text styling
+    Mono,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Export browser cookies to a Netscape cookie file for headless runs
+    ExportCookies {
+        /// Browser to read cookies from (e.g. firefox, chrome)
+        #[arg(long, value_name = "BROWSER")]
+        from: String,
+
+        /// Cookie file to write
+        #[arg(long, value_name = "PATH")]
+        to: PathBuf,
+    },
+
+    /// Re-run the URLs listed in a failed log with the current options
+    Retry {
+        /// File with one URL per line
+        #[arg(value_name = "FAILED_LOG")]
+        failed_log: PathBuf,
+    },
+
+    /// Re-run the failed URLs from a previous --report with the current options
+    RetryReport {
+        /// JSON report written by --report
+        #[arg(value_name = "REPORT")]
+        report: PathBuf,
+    },
+
+    /// Print a JSON Schema for the --report, --progress-file and --json formats
+    Schema,
+
+    /// Fetch only what's new on channels or playlists: skips archived items, stops at
+    /// the first one, and keeps going past unavailable videos
+    Sync {
+        /// yt-dlp download archive kept between syncs
+        #[arg(long, value_name = "PATH")]
+        archive: PathBuf,
+
+        /// Only items uploaded on or after DATE, as for --since
+        #[arg(long, value_name = "DATE", value_parser = parse_since)]
+        after: Option<String>,
+
+        /// Channel or playlist URLs, one per line
+        #[arg(long, value_name = "FILE")]
+        batch_file: Option<PathBuf>,
+
+        #[arg(value_name = "URL", required_unless_present = "batch_file")]
+        urls: Vec<String>,
+    },
+}
+
 #[derive(Parser, Debug)]
 #[command(
     name = "ytrs",
@@ -55,18 +244,45 @@ impl std::fmt::Display for SocialMediaTarget {
     about = "High-performance yt-dlp wrapper with social media optimization",
     long_about = "Downloads media from yt-dlp supported sites with maximum quality (VP9 > AV1 > H.264).\n\n\
                   Supports batch downloads, audio/video-only modes, and platform-specific \
-                  social media optimization for WhatsApp, Discord, Instagram, Messenger, and Signal."
+                  social media optimization for WhatsApp, Discord, Instagram, Messenger, and Signal.",
+    subcommand_negates_reqs = true
 )]
 pub struct Cli {
-    #[arg(short = 'd', long, value_name = "PATH")]
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Where downloads go; a quoted `~` or `$VAR` is expanded here
+    #[arg(short = 'd', long, value_name = "PATH", value_parser = parse_path)]
     pub destination: Option<PathBuf>,
 
+    /// Save into a subfolder per uploader
+    #[arg(long)]
+    pub by_uploader: bool,
+
+    /// yt-dlp output template for filenames, e.g. "%(title)s.%(ext)s"; joined onto
+    /// -d when that is a directory
+    #[arg(long, value_name = "TEMPLATE", value_parser = parse_template)]
+    pub output_template: Option<String>,
+
     #[arg(long, value_name = "BROWSER")]
     pub cookies_from: Option<String>,
 
-    /// Optimize for social media (wa, dc, ig, fb, sig, tg)
-    #[arg(long, value_name = "PLATFORM")]
-    pub socm: Option<SocialMediaTarget>,
+    /// Netscape cookie file, e.g. one written by `ytrs export-cookies`
+    #[arg(long, value_name = "PATH", conflicts_with = "cookies_from")]
+    pub cookies_file: Option<PathBuf>,
+
+    /// Optimize for social media (wa, dc, ig, fb, sig, tg), or a preset named in the
+    /// config file's socm_presets table
+    #[arg(long, value_name = "PLATFORM", value_parser = SocmTargetParser)]
+    pub socm: Option<String>,
+
+    /// `[socm_presets]` from the config file, consulted before the built-in targets
+    #[arg(skip)]
+    pub socm_presets: UserPresets,
+
+    /// With --socm, also keep the max-quality original next to the re-encode
+    #[arg(long)]
+    pub also_archive: bool,
 
     #[arg(short = 'a', long = "audio", conflicts_with_all = ["video_only", "socm"])]
     pub audio_only: bool,
@@ -74,14 +290,646 @@ pub struct Cli {
     #[arg(short = 'v', long = "video", conflicts_with_all = ["audio_only", "socm"])]
     pub video_only: bool,
 
-    #[arg(short = 'p', long, default_value = "2", value_name = "N")]
+    /// Re-encode only the audio track after download, copying the video as-is
+    #[arg(
+        long,
+        alias = "transcode-audio-to",
+        value_name = "CODEC",
+        conflicts_with_all = ["audio_only", "socm"]
+    )]
+    pub reencode_audio: Option<AudioCodec>,
+
+    #[arg(
+        short = 'p',
+        long,
+        visible_short_alias = 'j',
+        visible_alias = "jobs",
+        default_value_t = DEFAULT_PARALLEL,
+        value_name = "N"
+    )]
     pub parallel: NonZeroUsize,
 
-    #[arg(required = true, value_name = "URL")]
+    /// Start at 2 parallel downloads and add more while total throughput keeps
+    /// improving, backing off when it levels out or failures rise
+    #[arg(long, conflicts_with_all = ["parallel", "total_rate"])]
+    pub auto_parallel: bool,
+
+    /// Show the format yt-dlp would pick for a single URL and ask before downloading
+    #[arg(long)]
+    pub preview: bool,
+
+    /// Source for yt-dlp's remote challenge-solver components
+    #[arg(long, value_name = "SPEC", conflicts_with = "no_remote_components")]
+    pub remote_components: Option<String>,
+
+    /// Don't pass --remote-components to yt-dlp, e.g. for fully offline use
+    #[arg(long)]
+    pub no_remote_components: bool,
+
+    /// Format preset for the default mode
+    #[arg(
+        long,
+        value_name = "PRESET",
+        default_value = "archive",
+        conflicts_with_all = ["audio_only", "video_only", "socm"]
+    )]
+    pub quality: QualityPreset,
+
+    /// Cap the default mode's resolution at this height instead of 2160p, e.g. 1080
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with_all = ["audio_only", "video_only", "socm"]
+    )]
+    pub max_height: Option<u32>,
+
+    /// Video codec the default mode prefers among formats of the same resolution
+    #[arg(
+        long,
+        value_name = "CODEC",
+        default_value = "vp9",
+        conflicts_with_all = ["audio_only", "video_only", "socm"]
+    )]
+    pub codec: VideoCodec,
+
+    /// Recode every download into this container so the extension is always the same
+    #[arg(
+        long,
+        value_name = "EXT",
+        value_parser = ["mp4", "mkv", "webm", "mov", "avi", "flv", "gif"],
+        conflicts_with_all = ["audio_only", "socm"]
+    )]
+    pub force_ext: Option<String>,
+
+    /// Tag artist and title from "Artist - Title" video titles; set another separator with =SEP
+    #[arg(
+        long,
+        value_name = "SEP",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ARTIST_TITLE_SEPARATOR
+    )]
+    pub artist_title_split: Option<String>,
+
+    /// Cut these SponsorBlock segments out of videos, e.g. sponsor,selfpromo,intro or all
+    #[arg(long, value_name = "CATEGORIES")]
+    pub sponsorblock: Option<String>,
+
+    /// Download subtitles in these languages, e.g. en,es or all, and embed them in videos;
+    /// auto-best picks one per video from $LANG, falling back to English
+    #[arg(long, value_name = "LANGS")]
+    pub subs: Option<String>,
+
+    /// With --subs, also take auto-generated captions
+    #[arg(long, requires = "subs")]
+    pub auto_subs: bool,
+
+    /// Embed the video's thumbnail as cover art
+    #[arg(long)]
+    pub embed_thumbnail: bool,
+
+    /// Embed title, uploader, date, description and chapters as file metadata
+    #[arg(long)]
+    pub embed_metadata: bool,
+
+    /// Only items uploaded on or after DATE: YYYYMMDD, YYYY-MM-DD, today-2weeks or just 2weeks;
+    /// with an archive, stops at the first archived item for quick channel syncs
+    #[arg(long, value_name = "DATE", value_parser = parse_since)]
+    pub since: Option<String>,
+
+    /// Prefer this audio language (e.g. en, pt-BR) on videos with dubbed tracks;
+    /// without it the original track wins
+    #[arg(long, value_name = "LANG", value_parser = parse_language_code)]
+    pub audio_lang: Option<String>,
+
+    /// Resolve every URL's output filename up front and handle clashes within the batch
+    #[arg(long, value_name = "POLICY")]
+    pub on_collision: Option<OnCollision>,
+
+    /// When -d is a file and there is more than one URL: `error`, or `index` the filenames
+    #[arg(long, value_name = "POLICY", default_value = "error")]
+    pub on_file_dest_batch: FileDestBatch,
+
+    /// Hide yt-dlp's output for downloads that succeed; failures print its full stderr
+    #[arg(long)]
+    pub quiet_on_success: bool,
+
+    /// Download to a local temp dir first, then move finished files into -d;
+    /// for slow NFS or SMB destinations
+    #[arg(long, requires = "destination")]
+    pub stage: bool,
+
+    /// Local directory for --stage instead of the OS temp dir
+    #[arg(long, value_name = "DIR", requires = "stage")]
+    pub temp_dir: Option<PathBuf>,
+
+    /// Retry each failed download up to N more times with a short backoff; errors a retry
+    /// can't fix (auth, private or removed videos, unsupported sites) fail right away
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub retries: usize,
+
+    /// When the requested format isn't available, retry once without the height cap
+    #[arg(long)]
+    pub retry_with_lower_quality: bool,
+
+    /// How to show download progress; anything but `none` replaces yt-dlp's output
+    #[arg(long, value_name = "STYLE", default_value = "none")]
+    pub progress_style: ProgressDisplay,
+
+    /// Shorthand for --progress-style; a bare --progress means bars
+    #[arg(
+        long,
+        value_name = "STYLE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "bar",
+        conflicts_with = "progress_style"
+    )]
+    pub progress: Option<ProgressDisplay>,
+
+    /// Exit 0 even when some downloads failed, still printing the full summary
+    #[arg(long)]
+    pub exit_zero_on_partial: bool,
+
+    /// Exit 0 when at least this percentage of the downloads that ran completed
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        value_parser = clap::value_parser!(u8).range(0..=100),
+        conflicts_with = "exit_zero_on_partial"
+    )]
+    pub success_threshold: Option<u8>,
+
+    /// Print the exact argv of every yt-dlp run, one quoted argument per line
+    #[arg(long)]
+    pub verbose_args: bool,
+
+    /// Keep DASH/HLS fragments on disk after the download, to debug failed merges
+    #[arg(long)]
+    pub keep_fragments: bool,
+
+    /// Show the resolved settings before downloading, like the --socm preset values, and
+    /// pass --verbose to yt-dlp
+    #[arg(long, conflicts_with = "quiet")]
+    pub verbose: bool,
+
+    /// Only print errors and, if anything failed, the failure summary; yt-dlp's own
+    /// output still shows
+    #[arg(long)]
+    pub quiet: bool,
+
+    /// Print the yt-dlp command for each URL, shell-quoted, without running anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Print nothing unless a download fails, then only the failure summary; for cron jobs
+    #[arg(long)]
+    pub fail_summary_only: bool,
+
+    /// Print only a JSON result on stdout: {"total", "succeeded", "failed": list of URLs};
+    /// yt-dlp's output is hidden unless a download fails
+    #[arg(long, conflicts_with_all = ["dry_run", "concurrency_report", "verbose", "fail_summary_only"])]
+    pub json: bool,
+
+    /// With --socm, run at most N re-encodes at once, separately from -p downloads
+    #[arg(long, value_name = "N")]
+    pub max_concurrent_ffmpeg: Option<NonZeroUsize>,
+
+    /// With --socm, re-encode even downloads that are already in the preset's codecs and
+    /// within its size and resolution
+    #[arg(long)]
+    pub force_reencode: bool,
+
+    /// With --socm, two-pass encode at a bitrate worked out from the preset's size cap
+    /// and the video's length, so the file fits; plain CRF when the length is unknown
+    #[arg(long)]
+    pub fit_size: bool,
+
+    /// Check each finished file with ffprobe and fail the download if it isn't playable,
+    /// e.g. after a truncated merge
+    #[arg(long)]
+    pub verify: bool,
+
+    /// After the batch, report downloads that look like the same video by their frames
+    #[cfg(feature = "perceptual-dedupe")]
+    #[arg(long)]
+    pub perceptual_dedupe: bool,
+
+    /// Before downloading, check that each URL's host accepts a connection on port 443
+    #[cfg(feature = "net-check")]
+    #[arg(long)]
+    pub net_check: bool,
+
+    /// With --net-check, leave out URLs whose host didn't answer
+    #[cfg(feature = "net-check")]
+    #[arg(long, requires = "net_check")]
+    pub skip_unreachable: bool,
+
+    /// POST a JSON summary of the finished batch to this URL
+    #[cfg(feature = "webhook")]
+    #[arg(long, value_name = "URL")]
+    pub webhook: Option<String>,
+
+    /// Record every completed download (URL, id, path, time, mode) in this JSON file
+    /// and skip ids already recorded there
+    #[arg(long, value_name = "PATH")]
+    pub json_archive: Option<PathBuf>,
+
+    /// yt-dlp download archive: ids of completed downloads are added to this file, and
+    /// ids already in it are skipped
+    #[arg(long, value_name = "PATH", conflicts_with = "json_archive")]
+    pub archive: Option<PathBuf>,
+
+    /// Only these items of a playlist, e.g. 1-3,7,10-
+    #[arg(long, value_name = "SPEC")]
+    pub playlist_items: Option<String>,
+
+    /// Skip (not fail) videos longer than this many seconds
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_duration: Option<u64>,
+
+    /// Max out aria2c connections and caching for the fastest single-file download;
+    /// much heavier on the server and more likely to get throttled
+    #[arg(long)]
+    pub turbo: bool,
+
+    /// aria2c connections for each download (1-16), replacing the tuned -x/-s values;
+    /// -p downloads run side by side, so up to -p times this many are open at once
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(1..=16))]
+    pub connections: Option<u8>,
+
+    /// Let yt-dlp download with its built-in downloader instead of aria2c, which then
+    /// doesn't need to be installed
+    #[arg(
+        long,
+        conflicts_with_all = ["turbo", "connections", "aria_session", "rate_limit"]
+    )]
+    pub no_aria2c: bool,
+
+    /// Save unfinished aria2c transfers to this session file and resume them on the
+    /// next run; meant for one large download at a time
+    #[arg(long, value_name = "PATH")]
+    pub aria_session: Option<PathBuf>,
+
+    /// After the batch, print wall-clock vs summed download time to help tune -p
+    #[arg(long)]
+    pub concurrency_report: bool,
+
+    /// Read more URLs, or with --socm local files and globs like ~/Videos/*.mkv, one per
+    /// line; `-` reads them from stdin
+    #[arg(long, value_name = "FILE")]
+    pub batch_file: Option<PathBuf>,
+
+    /// Print the effective download settings as JSON and exit
+    #[arg(long)]
+    pub print_config: bool,
+
+    /// Print the ordered plan for every cleaned URL, then exit unless --yes is given
+    #[arg(long)]
+    pub plan: bool,
+
+    /// Answer yes to confirmations, e.g. to run the batch after --plan or --preview
+    #[arg(short = 'y', long)]
+    pub yes: bool,
+
+    /// Redownload from scratch, overwriting existing files
+    #[arg(long)]
+    pub force: bool,
+
+    /// Keep repeated URLs instead of collapsing them, e.g. to force a re-download
+    #[arg(long)]
+    pub no_dedupe: bool,
+
+    /// Show which URLs would be kept, skipped as invalid, or dropped as duplicates, then exit
+    #[arg(long)]
+    pub sanitize_report: bool,
+
+    /// List required and optional tools with their paths and versions, then exit
+    #[arg(long, exclusive = true)]
+    pub check_deps: bool,
+
+    /// Print the user agent yt-dlp sends and exit
+    #[arg(long, exclusive = true)]
+    pub dump_user_agent: bool,
+
+    /// List the browser targets yt-dlp can impersonate, then exit
+    #[arg(long, exclusive = true)]
+    pub list_impersonate_targets: bool,
+
+    /// List the cookie-bearing profiles of a browser for --cookies-from BROWSER:PROFILE, then exit
+    #[arg(long, value_name = "BROWSER", exclusive = true)]
+    pub list_browser_profiles: Option<ProfileBrowser>,
+
+    /// List the thumbnails available for a single URL, then exit
+    #[arg(long)]
+    pub list_thumbnails: bool,
+
+    /// List the formats each URL offers instead of downloading
+    #[arg(short = 'F', long)]
+    pub list_formats: bool,
+
+    /// Like --list-formats, as a ytrs table of id, ext, resolution, fps, codecs and size
+    #[arg(long, conflicts_with = "list_formats")]
+    pub list_formats_json: bool,
+
+    /// Keep a JSON snapshot of per-URL progress at this path for other tools to poll
+    #[arg(long, value_name = "PATH")]
+    pub progress_file: Option<PathBuf>,
+
+    /// Stop the whole batch on the first authentication failure, e.g. expired cookies
+    #[arg(long)]
+    pub abort_on_auth_error: bool,
+
+    /// On a terminal, offer to retry an auth failure with cookies from a browser
+    #[arg(long, conflicts_with_all = ["cookies_from", "cookies_file"])]
+    pub prompt_for_cookies_on_auth_failure: bool,
+
+    /// Stop the whole batch once this many downloads have failed
+    #[arg(long, value_name = "N")]
+    pub max_failures: Option<NonZeroUsize>,
+
+    /// Write a JSON report of each URL's outcome here when the batch ends
+    #[arg(long, value_name = "PATH")]
+    pub report: Option<PathBuf>,
+
+    /// Write a CSV of the batch here when it ends: url,title,id,path,status,size
+    #[arg(long, value_name = "PATH")]
+    pub manifest: Option<PathBuf>,
+
+    /// Write failed URLs here, one per line, for `ytrs retry`
+    #[arg(long, value_name = "PATH")]
+    pub failed_log: Option<PathBuf>,
+
+    /// Wait this many seconds between starting each URL in a batch
+    #[arg(long, value_name = "SECS")]
+    pub cooldown: Option<u64>,
+
+    /// Stop the batch after this long, e.g. 90m or 1h30m, keeping what finished and
+    /// writing the summary and --report
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub max_runtime: Option<Duration>,
+
+    /// Stop starting new URLs once completed downloads total this much, e.g. 50G
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_total_size: Option<u64>,
+
+    /// Bandwidth cap for the whole batch per second, e.g. 4M, split evenly across the
+    /// downloads running at once
+    #[arg(long, value_name = "RATE", value_parser = parse_size)]
+    pub total_rate: Option<u64>,
+
+    /// Bandwidth cap per second for each download's aria2c, e.g. 2M or 500K
+    #[arg(long, value_name = "RATE", value_parser = parse_size)]
+    pub rate_limit: Option<u64>,
+
+    /// Color palette for terminal output
+    #[arg(long, value_enum, default_value_t = ThemeName::Dark, value_name = "THEME")]
+    pub theme: ThemeName,
+
+    #[arg(
+        required_unless_present_any = [
+            "check_deps",
+            "dump_user_agent",
+            "list_impersonate_targets",
+            "list_browser_profiles",
+            "print_config",
+            "batch_file"
+        ],
+        value_name = "URL"
+    )]
     pub urls: Vec<String>,
 }
 
 impl Cli {
+    /// The yt-dlp info runs requested instead of a download, in order; empty for a download
+    pub fn info_commands(&self) -> Result<Vec<InfoCommand>> {
+        if self.dump_user_agent {
+            return Ok(vec![InfoCommand::DumpUserAgent]);
+        }
+        if self.list_impersonate_targets {
+            return Ok(vec![InfoCommand::ListImpersonateTargets]);
+        }
+        if self.list_thumbnails {
+            let [url] = self.urls.as_slice() else {
+                return Err(YtrsError::SingleUrlOnly("--list-thumbnails"));
+            };
+            return Ok(vec![InfoCommand::ListThumbnails(url.trim().to_string())]);
+        }
+        if self.list_formats || self.list_formats_json {
+            self.check_cookies()?;
+            let mut urls = self.urls.clone();
+            if let Some(batch_file) = &self.batch_file {
+                urls.extend(load_url_list(batch_file)?);
+            }
+            return Ok(urls
+                .iter()
+                .map(|url| InfoCommand::ListFormats {
+                    url: url.trim().to_string(),
+                    cookies_from: self.cookies_from.clone(),
+                    cookies_file: self.cookies_file.clone(),
+                    as_table: self.list_formats_json,
+                })
+                .collect());
+        }
+        Ok(Vec::new())
+    }
+
+    /// Folds `ytrs sync` into the top-level flags it stands for
+    pub fn apply_sync(&mut self) -> Result<()> {
+        let Some(Command::Sync {
+            archive,
+            after,
+            batch_file,
+            urls,
+        }) = &self.command
+        else {
+            return Ok(());
+        };
+        if self.json_archive.is_some() {
+            return Err(YtrsError::InvalidModeCombo(
+                "sync keeps its own --archive; drop --json-archive".to_string(),
+            ));
+        }
+        self.archive = Some(archive.clone());
+        self.since = after.clone().or_else(|| self.since.take());
+        self.batch_file = batch_file.clone();
+        self.urls = urls.clone();
+        Ok(())
+    }
+
+    /// Rejects a cookie browser yt-dlp doesn't know, or two cookie sources at once
+    fn check_cookies(&self) -> Result<()> {
+        // Only a config file can set both; yt-dlp would reject the pair
+        if self.cookies_from.is_some() && self.cookies_file.is_some() {
+            return Err(YtrsError::ConflictingCookies);
+        }
+        if let Some(browser) = &self.cookies_from {
+            validate_cookie_browser(browser)?;
+        }
+        Ok(())
+    }
+
+    pub fn download_options(&self) -> Result<DownloadOptions> {
+        self.check_cookies()?;
+        if self.max_height.is_some() && self.quality != QualityPreset::Archive {
+            return Err(YtrsError::InvalidModeCombo(
+                "--max-height replaces the archive preset's cap; drop --quality".to_string(),
+            ));
+        }
+        if self.codec != VideoCodec::Vp9 && self.quality != QualityPreset::Archive {
+            return Err(YtrsError::InvalidModeCombo(
+                "--codec reorders the archive preset's codecs; drop --quality".to_string(),
+            ));
+        }
+        let mode = self.download_mode()?;
+        // Checked here rather than by clap so a config file's socm default counts
+        if !matches!(mode, DownloadMode::SocialMedia(_))
+            && let Some(flag) = self.socm_only_flag()
+        {
+            return Err(YtrsError::InvalidModeCombo(format!("{flag} needs --socm")));
+        }
+        Ok(DownloadOptions {
+            destination_path: self.destination.clone(),
+            cookies_from: self.cookies_from.clone(),
+            cookies_file: self.cookies_file.clone(),
+            mode,
+            also_archive: self.also_archive,
+            by_uploader: self.by_uploader,
+            output_template: self.output_template.clone(),
+            force: self.force,
+            dedupe: !self.no_dedupe,
+            parallel: if self.auto_parallel {
+                DEFAULT_PARALLEL
+            } else {
+                self.parallel
+            },
+            auto_parallel: self.auto_parallel,
+            cooldown: self.cooldown.map(Duration::from_secs),
+            max_runtime: self.max_runtime,
+            progress_file: self.progress_file.clone(),
+            max_total_size: self.max_total_size,
+            total_rate: self.total_rate,
+            reencode_audio: self.reencode_audio,
+            report: self.report.clone(),
+            manifest: self.manifest.clone(),
+            failed_log: self.failed_log.clone(),
+            abort_on_auth_error: self.abort_on_auth_error,
+            prompt_for_cookies_on_auth_failure: self.prompt_for_cookies_on_auth_failure,
+            max_failures: self.max_failures,
+            remote_components: self.remote_components.clone(),
+            no_remote_components: self.no_remote_components,
+            turbo: self.turbo,
+            connections: self.connections,
+            no_aria2c: self.no_aria2c,
+            aria_session: self.aria_session.clone(),
+            rate_limit: self.rate_limit,
+            max_duration: self.max_duration,
+            playlist_items: self
+                .playlist_items
+                .as_deref()
+                .map(playlist_items)
+                .transpose()?,
+            force_ext: self.force_ext.clone(),
+            artist_title_separator: self.artist_title_split.clone(),
+            since: self.since.clone(),
+            sync: matches!(self.command, Some(Command::Sync { .. })),
+            subs: self.subs.clone(),
+            auto_subs: self.auto_subs,
+            embed_thumbnail: self.embed_thumbnail,
+            embed_metadata: self.embed_metadata,
+            sponsorblock: self
+                .sponsorblock
+                .as_deref()
+                .map(sponsorblock_categories)
+                .transpose()?,
+            json_archive: self.json_archive.clone(),
+            archive: self.archive.clone(),
+            audio_lang: self.audio_lang.clone(),
+            concurrency_report: self.concurrency_report,
+            on_collision: self.on_collision,
+            on_file_dest_batch: self.on_file_dest_batch,
+            quiet_on_success: self.quiet_on_success || self.fail_summary_only || self.json,
+            quality: self.quality,
+            max_height: self.max_height,
+            codec: self.codec,
+            staging: self.staging()?,
+            retry_lower_quality: self.retry_with_lower_quality,
+            retries: self.retries,
+            progress_style: if self.fail_summary_only || self.json {
+                ProgressDisplay::None
+            } else {
+                self.progress.unwrap_or(self.progress_style)
+            },
+            fail_summary_only: self.fail_summary_only,
+            quiet: self.quiet,
+            json: self.json,
+            verbose: self.verbose,
+            keep_fragments: self.keep_fragments,
+            verbose_args: self.verbose_args,
+            exit_zero_on_partial: self.exit_zero_on_partial,
+            success_threshold: self.success_threshold,
+            max_concurrent_ffmpeg: self.max_concurrent_ffmpeg,
+            force_reencode: self.force_reencode,
+            fit_size: self.fit_size,
+            verify: self.verify,
+            dry_run: self.dry_run,
+            perceptual_dedupe: self.perceptual_dedupe(),
+            webhook: self.webhook(),
+        })
+    }
+
+    #[cfg(feature = "webhook")]
+    fn webhook(&self) -> Option<String> {
+        self.webhook.clone()
+    }
+
+    #[cfg(not(feature = "webhook"))]
+    const fn webhook(&self) -> Option<String> {
+        None
+    }
+
+    #[cfg(feature = "perceptual-dedupe")]
+    const fn perceptual_dedupe(&self) -> bool {
+        self.perceptual_dedupe
+    }
+
+    #[cfg(not(feature = "perceptual-dedupe"))]
+    const fn perceptual_dedupe(&self) -> bool {
+        false
+    }
+
+    fn staging(&self) -> Result<Option<PathBuf>> {
+        if !self.stage {
+            return Ok(None);
+        }
+        if self
+            .destination
+            .as_deref()
+            .is_some_and(|dest| dest.exists() && !dest.is_dir())
+        {
+            return Err(YtrsError::InvalidModeCombo(
+                "--stage needs -d to be a directory".to_string(),
+            ));
+        }
+        Ok(Some(staging_base(self.temp_dir.as_deref())))
+    }
+
+    /// The first flag given that only applies to social media re-encodes
+    fn socm_only_flag(&self) -> Option<&'static str> {
+        [
+            ("--also-archive", self.also_archive),
+            (
+                "--max-concurrent-ffmpeg",
+                self.max_concurrent_ffmpeg.is_some(),
+            ),
+            ("--force-reencode", self.force_reencode),
+            ("--fit-size", self.fit_size),
+        ]
+        .into_iter()
+        .find_map(|(flag, set)| set.then_some(flag))
+    }
+
     pub fn download_mode(&self) -> Result<DownloadMode> {
         if self.audio_only && self.video_only {
             return Err(YtrsError::InvalidModeCombo(
@@ -104,16 +952,132 @@ impl Cli {
         Ok(match (self.audio_only, self.video_only, &self.socm) {
             (true, false, None) => DownloadMode::AudioOnly,
             (false, true, None) => DownloadMode::VideoOnly,
-            (false, false, Some(target)) => DownloadMode::SocialMedia(*target),
+            (false, false, Some(name)) => DownloadMode::SocialMedia(
+                resolve_preset(name, &self.socm_presets)
+                    .ok_or_else(|| YtrsError::UnknownSocmTarget(name.clone()))?,
+            ),
             (false, false, None) => DownloadMode::Default,
             _ => unreachable!("Invalid mode combination should be caught by clap"),
         })
     }
 }
 
+/// Accepts BCP 47-style codes like `en`, `ja` or `pt-BR`; they end up inside a
+/// yt-dlp format filter, so nothing else gets through
+fn parse_language_code(input: &str) -> std::result::Result<String, String> {
+    let mut subtags = input.split('-');
+    let primary = subtags.next().unwrap_or_default();
+    let valid = (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|tag| {
+            (2..=8).contains(&tag.len()) && tag.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+
+    if valid {
+        Ok(input.to_string())
+    } else {
+        Err(format!(
+            "invalid language code '{input}', expected e.g. en, ja or pt-BR"
+        ))
+    }
+}
+
+/// Expands `~` and `$VAR` in a path the shell left alone, e.g. a quoted one
+fn parse_path(input: &str) -> std::result::Result<PathBuf, String> {
+    Ok(PathBuf::from(expand_path(input)))
+}
+
+/// Expands `~` and `$VAR` in an output template, leaving its `%(field)s` parts alone
+fn parse_template(input: &str) -> std::result::Result<String, String> {
+    Ok(expand_path(input))
+}
+
+/// Checks a `--playlist-items` spec is comma-separated `N`, `N-M` or `N-` items
+fn playlist_items(input: &str) -> Result<String> {
+    let index = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let items: Vec<&str> = input.split(',').map(str::trim).collect();
+    let valid = items.iter().all(|item| match item.split_once('-') {
+        Some((start, "")) => index(start),
+        Some((start, end)) => index(start) && index(end),
+        None => index(item),
+    });
+    if !valid {
+        return Err(YtrsError::InvalidPlaylistSpec(input.to_string()));
+    }
+    Ok(items.join(","))
+}
+
+/// Checks a `--sponsorblock` list against yt-dlp's categories, expanding `all`
+fn sponsorblock_categories(input: &str) -> Result<String> {
+    let mut categories: Vec<&str> = Vec::new();
+    for category in input.split(',').map(str::trim) {
+        let expanded: &[&str] = if category == "all" {
+            SPONSORBLOCK_CATEGORIES
+        } else if let Some(known) = SPONSORBLOCK_CATEGORIES.iter().find(|c| **c == category) {
+            std::slice::from_ref(known)
+        } else {
+            return Err(YtrsError::InvalidSponsorBlockCategory(category.to_string()));
+        };
+        for category in expanded {
+            if !categories.contains(category) {
+                categories.push(category);
+            }
+        }
+    }
+    Ok(categories.join(","))
+}
+
+/// Normalizes `--since` into a `--dateafter` value yt-dlp accepts
+fn parse_since(input: &str) -> std::result::Result<String, String> {
+    let date = input.trim().to_ascii_lowercase();
+    let invalid =
+        || format!("invalid date '{input}', expected YYYYMMDD, YYYY-MM-DD, today-2weeks or 2weeks");
+
+    let digits = match date.as_bytes() {
+        [_, _, _, _, b'-', _, _, b'-', _, _] => date.replace('-', ""),
+        _ => date.clone(),
+    };
+    if digits.len() == 8 && digits.chars().all(|c| c.is_ascii_digit()) {
+        let month: u32 = digits[4..6].parse().unwrap_or_default();
+        let day: u32 = digits[6..].parse().unwrap_or_default();
+        return if (1..=12).contains(&month) && (1..=31).contains(&day) {
+            Ok(digits)
+        } else {
+            Err(invalid())
+        };
+    }
+
+    for base in ["today", "now", "yesterday"] {
+        if let Some(offset) = date.strip_prefix(base) {
+            return match offset.strip_prefix(['-', '+']) {
+                None if offset.is_empty() => Ok(date),
+                Some(offset) if is_date_offset(offset) => Ok(date),
+                _ => Err(invalid()),
+            };
+        }
+    }
+    if is_date_offset(&date) {
+        return Ok(format!("today-{date}"));
+    }
+    Err(invalid())
+}
+
+/// `2weeks`, `1month`, `3days`: a count followed by a calendar unit
+fn is_date_offset(offset: &str) -> bool {
+    let unit_start = offset
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(offset.len());
+    let (count, unit) = offset.split_at(unit_start);
+    let unit = unit.strip_suffix('s').unwrap_or(unit);
+    !count.is_empty() && ["day", "week", "month", "year"].contains(&unit)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::downloader::download_plan;
+    use crate::mode::SocialMediaPreset;
+    use std::path::Path;
 
     #[test]
     fn test_social_media_target_display() {
@@ -126,60 +1090,473 @@ mod tests {
 
     #[test]
     fn test_download_mode_default() {
-        let cli = Cli {
-            destination: None,
-            cookies_from: None,
-            socm: None,
-            audio_only: false,
-            video_only: false,
-            parallel: NonZeroUsize::new(2).unwrap(),
-            urls: vec!["https://example.com".to_string()],
-        };
+        let cli = Cli::parse_from(["ytrs", "https://example.com"]);
         assert_eq!(cli.download_mode().unwrap(), DownloadMode::Default);
     }
 
     #[test]
     fn test_download_mode_audio() {
-        let cli = Cli {
-            destination: None,
-            cookies_from: None,
-            socm: None,
-            audio_only: true,
-            video_only: false,
-            parallel: NonZeroUsize::new(2).unwrap(),
-            urls: vec!["https://example.com".to_string()],
-        };
+        let cli = Cli::parse_from(["ytrs", "-a", "https://example.com"]);
         assert_eq!(cli.download_mode().unwrap(), DownloadMode::AudioOnly);
     }
 
     #[test]
     fn test_download_mode_video() {
-        let cli = Cli {
-            destination: None,
-            cookies_from: None,
-            socm: None,
-            audio_only: false,
-            video_only: true,
-            parallel: NonZeroUsize::new(2).unwrap(),
-            urls: vec!["https://example.com".to_string()],
-        };
+        let cli = Cli::parse_from(["ytrs", "-v", "https://example.com"]);
         assert_eq!(cli.download_mode().unwrap(), DownloadMode::VideoOnly);
     }
 
     #[test]
     fn test_download_mode_socm() {
-        let cli = Cli {
-            destination: None,
-            cookies_from: None,
-            socm: Some(SocialMediaTarget::Discord),
-            audio_only: false,
-            video_only: false,
-            parallel: NonZeroUsize::new(2).unwrap(),
-            urls: vec!["https://example.com".to_string()],
-        };
+        let cli = Cli::parse_from(["ytrs", "--socm", "discord", "https://example.com"]);
+        assert_eq!(
+            cli.download_mode().unwrap(),
+            DownloadMode::SocialMedia(SocialMediaTarget::Discord.preset())
+        );
+    }
+
+    #[test]
+    fn test_socm_takes_config_preset_names() {
+        let mut cli = Cli::parse_from(["ytrs", "--socm", "mastodon", "https://example.com"]);
         assert!(matches!(
+            cli.download_mode(),
+            Err(YtrsError::UnknownSocmTarget(name)) if name == "mastodon"
+        ));
+
+        let mastodon = SocialMediaPreset {
+            name: "mastodon".to_string(),
+            max_height: 480,
+            max_size_mb: 50,
+            ..SocialMediaTarget::Discord.preset()
+        };
+        cli.socm_presets
+            .insert("mastodon".to_string(), mastodon.clone());
+        assert_eq!(
             cli.download_mode().unwrap(),
-            DownloadMode::SocialMedia(SocialMediaTarget::Discord)
+            DownloadMode::SocialMedia(mastodon)
+        );
+    }
+
+    #[test]
+    fn test_also_archive_requires_socm() {
+        let cli = Cli::parse_from(["ytrs", "--also-archive", "https://example.com"]);
+        assert!(matches!(
+            cli.download_options(),
+            Err(YtrsError::InvalidModeCombo(_))
+        ));
+        let cli = Cli::parse_from([
+            "ytrs",
+            "--socm",
+            "wa",
+            "--also-archive",
+            "https://example.com",
+        ]);
+        assert!(cli.also_archive);
+    }
+
+    #[test]
+    fn test_audio_lang_is_validated() {
+        let url = "https://example.com";
+        for lang in ["en", "ja", "pt-BR", "zh-Hant"] {
+            let cli = Cli::parse_from(["ytrs", "--audio-lang", lang, url]);
+            assert_eq!(cli.audio_lang.as_deref(), Some(lang));
+        }
+        for lang in ["e", "english", "en_US", "en]", "en-"] {
+            assert!(Cli::try_parse_from(["ytrs", "--audio-lang", lang, url]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_archive_option() {
+        let cli = Cli::parse_from(["ytrs", "--archive", "seen.txt", "https://example.com"]);
+        let options = cli.download_options().unwrap();
+        assert_eq!(options.archive.as_deref(), Some(Path::new("seen.txt")));
+
+        assert!(
+            Cli::try_parse_from([
+                "ytrs",
+                "--archive",
+                "seen.txt",
+                "--json-archive",
+                "seen.json",
+                "https://example.com"
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_playlist_items() {
+        for (input, expected) in [("5", "5"), ("1-3,7,10-", "1-3,7,10-"), ("1-3, 7", "1-3,7")] {
+            assert_eq!(playlist_items(input).unwrap(), expected);
+        }
+        for input in ["", "1,,3", "-3", "a-b", "1-2-3", "1:3", "3-x"] {
+            assert!(
+                matches!(playlist_items(input), Err(YtrsError::InvalidPlaylistSpec(ref s)) if s == input),
+                "{input}"
+            );
+        }
+
+        let cli = Cli::parse_from([
+            "ytrs",
+            "--playlist-items",
+            "2-4",
+            "https://www.youtube.com/playlist?list=PL1",
+        ]);
+        assert_eq!(
+            cli.download_options().unwrap().playlist_items.as_deref(),
+            Some("2-4")
+        );
+    }
+
+    #[test]
+    fn test_max_height() {
+        let cli = Cli::parse_from(["ytrs", "--max-height", "1080", "https://example.com"]);
+        assert_eq!(cli.download_options().unwrap().max_height, Some(1080));
+
+        let cli = Cli::parse_from([
+            "ytrs",
+            "--max-height",
+            "1080",
+            "--quality",
+            "small",
+            "https://example.com",
+        ]);
+        assert!(matches!(
+            cli.download_options(),
+            Err(YtrsError::InvalidModeCombo(_))
+        ));
+        assert!(Cli::try_parse_from(["ytrs", "--max-height", "0", "https://example.com"]).is_err());
+        assert!(
+            Cli::try_parse_from(["ytrs", "--max-height", "720", "-a", "https://example.com"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_sponsorblock_categories() {
+        assert_eq!(
+            sponsorblock_categories("sponsor, selfpromo,intro,sponsor").unwrap(),
+            "sponsor,selfpromo,intro"
+        );
+        assert_eq!(
+            sponsorblock_categories("all").unwrap(),
+            SPONSORBLOCK_CATEGORIES.join(",")
+        );
+        assert!(matches!(
+            sponsorblock_categories("sponsor,adverts"),
+            Err(YtrsError::InvalidSponsorBlockCategory(ref c)) if c == "adverts"
+        ));
+
+        let cli = Cli::parse_from(["ytrs", "--sponsorblock", "nope", "https://example.com"]);
+        assert!(cli.download_options().is_err());
+    }
+
+    #[test]
+    fn test_since_is_validated() {
+        for (input, expected) in [
+            ("20240115", "20240115"),
+            ("2024-01-15", "20240115"),
+            ("today-2weeks", "today-2weeks"),
+            ("yesterday", "yesterday"),
+            ("1month", "today-1month"),
+            ("3Days", "today-3days"),
+        ] {
+            assert_eq!(parse_since(input).as_deref(), Ok(expected));
+        }
+        for input in [
+            "2024-13-01",
+            "20240100",
+            "last month",
+            "today-2",
+            "weeks",
+            "today+x",
+        ] {
+            assert!(parse_since(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_print_config_reflects_cli_overrides() {
+        let cli = Cli::parse_from([
+            "ytrs",
+            "--print-config",
+            "-p",
+            "5",
+            "--socm",
+            "discord",
+            "--cooldown",
+            "3",
+        ]);
+        let config = serde_json::to_value(cli.download_options().unwrap()).unwrap();
+
+        assert_eq!(config["parallel"], 5);
+        assert_eq!(config["mode"]["social_media"]["name"], "Discord");
+        assert_eq!(config["mode"]["social_media"]["max_size_mb"], 25);
+        assert_eq!(config["cooldown"], 3);
+        assert_eq!(config["dedupe"], true);
+        assert_eq!(config["quality"], "archive");
+    }
+
+    #[test]
+    fn test_connections_range() {
+        let url = "https://example.com";
+        let cli = Cli::parse_from(["ytrs", "--connections", "16", url]);
+        assert_eq!(cli.download_options().unwrap().connections, Some(16));
+        for n in ["0", "17"] {
+            assert!(Cli::try_parse_from(["ytrs", "--connections", n, url]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_jobs_alias_matches_parallel() {
+        let url = "https://example.com";
+        let parallel = Cli::parse_from(["ytrs", "-p", "4", url]).parallel;
+        assert_eq!(parallel.get(), 4);
+        assert_eq!(Cli::parse_from(["ytrs", "-j", "4", url]).parallel, parallel);
+        assert_eq!(
+            Cli::parse_from(["ytrs", "--jobs", "4", url]).parallel,
+            parallel
+        );
+        assert_eq!(
+            Cli::parse_from(["ytrs", "--parallel", "4", url]).parallel,
+            parallel
+        );
+    }
+
+    #[test]
+    fn test_auto_parallel_conflicts_with_total_rate() {
+        let url = "https://example.com";
+        assert!(Cli::try_parse_from(["ytrs", "--auto-parallel", url]).is_ok());
+        // The rate is split across the starting slots, so more slots would exceed it
+        assert!(
+            Cli::try_parse_from(["ytrs", "--auto-parallel", "--total-rate", "4M", url]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_progress_shorthand() {
+        let style = |args: &[&str]| {
+            Cli::parse_from(args)
+                .download_options()
+                .unwrap()
+                .progress_style
+        };
+        let url = "https://example.com";
+
+        // A bare --progress mustn't swallow the URL after it
+        let cli = Cli::parse_from(["ytrs", "--progress", url]);
+        assert_eq!(cli.urls, [url]);
+        assert_eq!(style(&["ytrs", "--progress", url]), ProgressDisplay::Bar);
+        assert_eq!(
+            style(&["ytrs", "--progress=plain", url]),
+            ProgressDisplay::Plain
+        );
+        assert_eq!(style(&["ytrs", url]), ProgressDisplay::None);
+        assert!(
+            Cli::try_parse_from(["ytrs", "--progress", "--progress-style", "bar", url]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_list_browser_profiles_without_urls() {
+        let cli = Cli::parse_from(["ytrs", "--list-browser-profiles", "firefox"]);
+        assert_eq!(cli.list_browser_profiles, Some(ProfileBrowser::Firefox));
+        assert!(Cli::try_parse_from(["ytrs", "--list-browser-profiles", "lynx"]).is_err());
+    }
+
+    #[test]
+    fn test_list_impersonate_targets_without_urls() {
+        let cli = Cli::parse_from(["ytrs", "--list-impersonate-targets"]);
+        assert_eq!(
+            cli.info_commands().unwrap(),
+            [InfoCommand::ListImpersonateTargets]
+        );
+    }
+
+    #[test]
+    fn test_batch_file_without_urls() {
+        let cli = Cli::parse_from(["ytrs", "--batch-file", "-"]);
+        assert_eq!(cli.batch_file, Some(PathBuf::from("-")));
+        assert!(cli.urls.is_empty());
+        assert!(Cli::try_parse_from(["ytrs", "-a"]).is_err());
+    }
+
+    #[test]
+    fn test_dump_user_agent_without_urls() {
+        let cli = Cli::parse_from(["ytrs", "--dump-user-agent"]);
+        assert_eq!(cli.info_commands().unwrap(), [InfoCommand::DumpUserAgent]);
+        assert!(cli.urls.is_empty());
+    }
+
+    #[test]
+    fn test_export_cookies_subcommand() {
+        let cli = Cli::parse_from([
+            "ytrs",
+            "export-cookies",
+            "--from",
+            "firefox",
+            "--to",
+            "cookies.txt",
+        ]);
+        match cli.command {
+            Some(Command::ExportCookies { from, to }) => {
+                assert_eq!(from, "firefox");
+                assert_eq!(to, PathBuf::from("cookies.txt"));
+            }
+            other => panic!("unexpected command: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_export_cookies_requires_destination() {
+        assert!(Cli::try_parse_from(["ytrs", "export-cookies", "--from", "firefox"]).is_err());
+    }
+
+    #[test]
+    fn test_sync_resolves_to_archive_options() {
+        let mut cli = Cli::try_parse_from([
+            "ytrs",
+            "sync",
+            "--archive",
+            "a.txt",
+            "--after",
+            "20240101",
+            "--batch-file",
+            "channels.txt",
+        ])
+        .unwrap();
+        cli.apply_sync().unwrap();
+        assert_eq!(cli.batch_file, Some(PathBuf::from("channels.txt")));
+
+        let options = cli.download_options().unwrap();
+        assert_eq!(options.archive, Some(PathBuf::from("a.txt")));
+        assert_eq!(options.since.as_deref(), Some("20240101"));
+        assert!(options.sync);
+
+        let args = options.ytdlp_args(&download_plan(&options.mode, false)[0], false);
+        assert!(args.ignore_errors && args.break_on_existing);
+
+        let mut plain = Cli::try_parse_from(["ytrs", "https://example.com"]).unwrap();
+        plain.apply_sync().unwrap();
+        assert!(!plain.download_options().unwrap().sync);
+    }
+
+    #[test]
+    fn test_sync_requires_archive() {
+        assert!(Cli::try_parse_from(["ytrs", "sync", "https://www.youtube.com/@chan"]).is_err());
+        assert!(Cli::try_parse_from(["ytrs", "sync", "--archive", "a.txt"]).is_err());
+
+        let mut cli = Cli::try_parse_from([
+            "ytrs",
+            "--json-archive",
+            "library.json",
+            "sync",
+            "--archive",
+            "a.txt",
+            "https://www.youtube.com/@chan",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.apply_sync(),
+            Err(YtrsError::InvalidModeCombo(_))
+        ));
+    }
+
+    #[test]
+    fn test_retry_subcommand_keeps_global_options() {
+        let cli = Cli::parse_from(["ytrs", "-a", "-p", "3", "retry", "failed.txt"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Retry { ref failed_log }) if failed_log == Path::new("failed.txt")
+        ));
+        assert_eq!(cli.download_mode().unwrap(), DownloadMode::AudioOnly);
+        assert_eq!(cli.parallel.get(), 3);
+    }
+
+    #[test]
+    fn test_reencode_audio_rejects_audio_mode() {
+        let cli = Cli::parse_from(["ytrs", "--reencode-audio", "aac", "https://example.com"]);
+        assert_eq!(cli.reencode_audio, Some(AudioCodec::Aac));
+        assert!(
+            Cli::try_parse_from([
+                "ytrs",
+                "-a",
+                "--reencode-audio",
+                "mp3",
+                "https://example.com"
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_max_duration_must_be_positive() {
+        assert!(Cli::try_parse_from(["ytrs", "--max-duration", "0", "https://a.example"]).is_err());
+        let cli = Cli::parse_from(["ytrs", "--max-duration", "600", "https://a.example"]);
+        assert_eq!(cli.max_duration, Some(600));
+    }
+
+    #[test]
+    fn test_artist_title_split_default_separator() {
+        let cli = Cli::parse_from(["ytrs", "-a", "--artist-title-split", "https://a.example"]);
+        assert_eq!(cli.artist_title_split.as_deref(), Some(" - "));
+
+        let cli = Cli::parse_from(["ytrs", "--artist-title-split=/", "https://a.example"]);
+        assert_eq!(cli.artist_title_split.as_deref(), Some("/"));
+    }
+
+    #[test]
+    fn test_no_info_command_for_downloads() {
+        let cli = Cli::parse_from(["ytrs", "https://example.com"]);
+        assert!(cli.info_commands().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_list_thumbnails_single_url_only() {
+        let cli = Cli::parse_from(["ytrs", "--list-thumbnails", "https://example.com/v"]);
+        assert_eq!(
+            cli.info_commands().unwrap(),
+            [InfoCommand::ListThumbnails(
+                "https://example.com/v".to_string()
+            )]
+        );
+
+        let batch = Cli::parse_from([
+            "ytrs",
+            "--list-thumbnails",
+            "https://a.example",
+            "https://b.example",
+        ]);
+        assert!(matches!(
+            batch.info_commands(),
+            Err(YtrsError::SingleUrlOnly("--list-thumbnails"))
         ));
     }
+
+    #[test]
+    fn test_list_formats_per_url() {
+        let cli = Cli::parse_from([
+            "ytrs",
+            "-F",
+            "--socm",
+            "discord",
+            "--cookies-from",
+            "firefox",
+            "https://a.example",
+            "https://b.example",
+        ]);
+        let commands = cli.info_commands().unwrap();
+
+        assert_eq!(commands.len(), 2);
+        assert_eq!(
+            commands[1],
+            InfoCommand::ListFormats {
+                url: "https://b.example".to_string(),
+                cookies_from: Some("firefox".to_string()),
+                cookies_file: None,
+                as_table: false,
+            }
+        );
+    }
 }
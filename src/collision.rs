@@ -0,0 +1,227 @@
+//! Detects URLs in a batch that would write the same output file
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::process::Stdio;
+
+use tokio::process::Command;
+
+use crate::args_builder::{YtDlpArgs, build_ytdlp_args};
+use crate::cli::OnCollision;
+use crate::video_info::VideoInfo;
+
+/// One output path claimed by more than one URL, in batch order
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Collision {
+    pub filename: String,
+    pub urls: Vec<String>,
+}
+
+/// Resolved output filenames of every entry behind `url`; empty if yt-dlp can't resolve it,
+/// in which case the real download reports the problem
+pub async fn prefetch_filenames(url: &str, args: &YtDlpArgs<'_>) -> Vec<String> {
+    let mut cmd_args = build_ytdlp_args(url, args);
+    cmd_args.insert(cmd_args.len() - 1, Cow::Borrowed("--dump-json"));
+
+    let Ok(output) = Command::new("yt-dlp")
+        .args(cmd_args.iter().map(AsRef::<str>::as_ref))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await
+    else {
+        return Vec::new();
+    };
+
+    expected_filenames(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// yt-dlp fills `filename` from the output template, one JSON object per entry
+fn expected_filenames(dump: &str) -> Vec<String> {
+    dump.lines()
+        .filter_map(|line| VideoInfo::from_json(line).ok()?.filename)
+        .collect()
+}
+
+#[must_use]
+pub fn find_collisions(prefetched: &[(String, Vec<String>)]) -> Vec<Collision> {
+    let mut collisions: Vec<Collision> = Vec::new();
+    for (url, filenames) in prefetched {
+        for filename in filenames {
+            match collisions.iter_mut().find(|c| &c.filename == filename) {
+                Some(collision) if !collision.urls.contains(url) => {
+                    collision.urls.push(url.clone());
+                }
+                Some(_) => {}
+                None => collisions.push(Collision {
+                    filename: filename.clone(),
+                    urls: vec![url.clone()],
+                }),
+            }
+        }
+    }
+    collisions.retain(|c| c.urls.len() > 1);
+    collisions
+}
+
+/// What to do with each colliding URL; the first URL to claim a filename always keeps it
+#[derive(Debug, Default)]
+pub struct CollisionPlan {
+    pub skip: Vec<String>,
+    /// Filename suffix per URL, e.g. ` (2)`
+    pub suffixes: HashMap<String, String>,
+}
+
+impl CollisionPlan {
+    /// `prefetched` is every URL's resolved filenames, so an index suffix never lands on
+    /// a name another URL already writes
+    #[must_use]
+    pub fn new(
+        prefetched: &[(String, Vec<String>)],
+        collisions: &[Collision],
+        policy: OnCollision,
+    ) -> Self {
+        let mut plan = Self::default();
+        let mut reserved: HashSet<String> = prefetched
+            .iter()
+            .flat_map(|(_, filenames)| filenames.iter().cloned())
+            .collect();
+        for collision in collisions {
+            for (position, url) in collision.urls.iter().enumerate().skip(1) {
+                match policy {
+                    OnCollision::Warn => {}
+                    OnCollision::Skip => {
+                        if !plan.skip.contains(url) {
+                            plan.skip.push(url.clone());
+                        }
+                    }
+                    OnCollision::Index if plan.suffixes.contains_key(url) => {}
+                    OnCollision::Index => {
+                        let filenames = prefetched
+                            .iter()
+                            .find(|(prefetched_url, _)| prefetched_url == url)
+                            .map_or(&[][..], |(_, filenames)| filenames.as_slice());
+                        let suffix = reserve_suffix(&mut reserved, filenames, position + 1);
+                        plan.suffixes.insert(url.clone(), suffix);
+                    }
+                }
+            }
+        }
+        plan
+    }
+}
+
+/// The first ` (n)` from `start` on that is free for all of `filenames`, which are then
+/// reserved with it
+fn reserve_suffix(reserved: &mut HashSet<String>, filenames: &[String], start: usize) -> String {
+    let mut index = start;
+    loop {
+        let suffix = format!(" ({index})");
+        let renamed: Vec<String> = filenames
+            .iter()
+            .map(|filename| with_suffix(filename, &suffix))
+            .collect();
+        if renamed.iter().all(|name| !reserved.contains(name)) {
+            reserved.extend(renamed);
+            return suffix;
+        }
+        index += 1;
+    }
+}
+
+/// Where yt-dlp puts `filename_suffix`: just before the extension
+fn with_suffix(filename: &str, suffix: &str) -> String {
+    match filename.rsplit_once('.') {
+        Some((stem, ext)) => format!("{stem}{suffix}.{ext}"),
+        None => format!("{filename}{suffix}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIRST: &str =
+        r#"{"id": "a1", "title": "Intro", "filename": "Intro - Chan (1080p, vp9, Youtube).webm"}"#;
+    const SECOND: &str =
+        r#"{"id": "b2", "title": "Intro", "filename": "Intro - Chan (1080p, vp9, Youtube).webm"}"#;
+    const OTHER: &str =
+        r#"{"id": "c3", "title": "Outro", "filename": "Outro - Chan (1080p, vp9, Youtube).webm"}"#;
+
+    fn prefetched() -> Vec<(String, Vec<String>)> {
+        [
+            ("https://a.example/1", FIRST),
+            ("https://a.example/2", SECOND),
+            ("https://a.example/3", OTHER),
+        ]
+        .into_iter()
+        .map(|(url, dump)| (url.to_string(), expected_filenames(dump)))
+        .collect()
+    }
+
+    #[test]
+    fn test_find_collisions_from_metadata() {
+        let collisions = find_collisions(&prefetched());
+
+        assert_eq!(
+            collisions,
+            [Collision {
+                filename: "Intro - Chan (1080p, vp9, Youtube).webm".to_string(),
+                urls: vec![
+                    "https://a.example/1".to_string(),
+                    "https://a.example/2".to_string()
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_collision_plan_per_policy() {
+        let collisions = find_collisions(&prefetched());
+
+        let warn = CollisionPlan::new(&prefetched(), &collisions, OnCollision::Warn);
+        assert!(warn.skip.is_empty() && warn.suffixes.is_empty());
+
+        let skip = CollisionPlan::new(&prefetched(), &collisions, OnCollision::Skip);
+        assert_eq!(skip.skip, ["https://a.example/2"]);
+
+        let index = CollisionPlan::new(&prefetched(), &collisions, OnCollision::Index);
+        assert_eq!(index.suffixes.len(), 1);
+        assert_eq!(index.suffixes["https://a.example/2"], " (2)");
+    }
+
+    #[test]
+    fn test_index_suffixes_are_reserved() {
+        let name = |suffix: &str| format!("Intro - Chan (1080p, vp9, Youtube){suffix}.webm");
+        // The fourth URL's real title already ends up as the name " (2)" would give
+        let prefetched: Vec<(String, Vec<String>)> = [
+            ("https://a.example/1", name("")),
+            ("https://a.example/2", name("")),
+            ("https://a.example/3", name("")),
+            ("https://a.example/4", name(" (2)")),
+        ]
+        .into_iter()
+        .map(|(url, filename)| (url.to_string(), vec![filename]))
+        .collect();
+
+        let plan = CollisionPlan::new(
+            &prefetched,
+            &find_collisions(&prefetched),
+            OnCollision::Index,
+        );
+
+        assert_eq!(plan.suffixes["https://a.example/2"], " (3)");
+        assert_eq!(plan.suffixes["https://a.example/3"], " (4)");
+        let mut written: Vec<String> = prefetched
+            .iter()
+            .flat_map(|(url, filenames)| {
+                let suffix = plan.suffixes.get(url).map_or("", String::as_str);
+                filenames.iter().map(move |f| with_suffix(f, suffix))
+            })
+            .collect();
+        let count = written.len();
+        written.sort();
+        written.dedup();
+        assert_eq!(written.len(), count);
+    }
+}
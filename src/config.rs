@@ -1,35 +1,122 @@
 //! Configuration constants for ytrs
 
+use std::num::NonZeroUsize;
+
 // Filename templates use yt-dlp's fallback syntax: %(field,alternative|default)s
 
 pub const FILENAME_PRIMARY: &str = "%(title)s - %(uploader,channel,creator|Unknown)s (%(height)sp, %(vcodec)s, %(extractor_key)s).%(ext)s";
 pub const FILENAME_AUDIO_PRIMARY: &str =
     "%(title)s - %(uploader,channel,creator|Unknown)s (%(extractor_key)s).%(ext)s";
+// Per-uploader subfolder prepended to the filename template by --by-uploader
+pub const UPLOADER_DIR_TEMPLATE: &str = "%(uploader,channel,creator|Unknown)s";
+
+// Marks the re-encoded copy when --also-archive keeps the original alongside it
+pub const SHARE_COPY_SUFFIX: &str = " [share]";
 pub const FILENAME_VIDEO_ONLY_PRIMARY: &str = "%(title)s - %(uploader,channel,creator|Unknown)s (%(height)sp, %(vcodec)s, %(extractor_key)s, video-only).%(ext)s";
 
-// Height capped at 2160p
-pub const FORMAT_DEFAULT: &str = "bv*[height<=2160]+ba/b[height<=2160]";
+// Height cap of the archive preset unless --max-height sets another
+pub const DEFAULT_MAX_HEIGHT: u32 = 2160;
 pub const FORMAT_AUDIO_ONLY: &str = "ba/b";
 pub const FORMAT_VIDEO_ONLY: &str = "bv[height<=2160]";
 
 // VP9 > AV1 > H.264; Opus > FLAC > AAC > MP3; hdr:12 excludes Dolby Vision
 pub const FORMAT_SORT_DEFAULT: &str = "res,fps,vcodec:vp9.2,vcodec:vp9,vcodec:av01,vcodec:hev1,vcodec:avc,hdr:12,acodec:opus,acodec:flac,acodec:aac,acodec:mp3,size";
+// --codec av1 and h264: the same ranking with that codec moved to the front
+pub const FORMAT_SORT_AV1: &str = "res,fps,vcodec:av01,vcodec:vp9.2,vcodec:vp9,vcodec:hev1,vcodec:avc,hdr:12,acodec:opus,acodec:flac,acodec:aac,acodec:mp3,size";
+pub const FORMAT_SORT_H264: &str = "res,fps,vcodec:avc,vcodec:vp9.2,vcodec:vp9,vcodec:av01,vcodec:hev1,hdr:12,acodec:opus,acodec:flac,acodec:aac,acodec:mp3,size";
 pub const FORMAT_SORT_AUDIO: &str = "acodec:opus,acodec:flac,acodec:aac,acodec:mp3,abr";
 pub const FORMAT_SORT_VIDEO: &str =
     "res,fps,vcodec:vp9.2,vcodec:vp9,vcodec:av01,vcodec:hev1,vcodec:avc,hdr:12,size";
 
+// --quality balanced: nearest to 1080p in H.264 + AAC, which plays everywhere
+pub const FORMAT_BALANCED: &str = "bv*[height<=1080]+ba/b[height<=1080]/bv*+ba/b";
+pub const FORMAT_SORT_BALANCED: &str = "res:1080,vcodec:avc,acodec:aac,fps,size";
+// --quality small: smallest files at up to 480p, falling back to the worst available
+pub const FORMAT_SMALL: &str = "bv*[height<=480]+ba/b[height<=480]/wv*+wa/w";
+pub const FORMAT_SORT_SMALL: &str = "res:480,+size,+br";
+
 pub const CONTAINER_VIDEO: &str = "webm/mkv/mp4";
 #[allow(dead_code)]
 pub const CONTAINER_AUDIO: &str = "opus/ogg/m4a";
+pub const CONTAINER_VIDEO_ONLY: &str = "mkv";
 pub const CONTAINER_SOCM: &str = "mp4";
+// What yt-dlp's --embed-thumbnail can write into; notably not webm
+pub const THUMBNAIL_CONTAINERS: &[&str] = &[
+    "mp3", "mkv", "mka", "ogg", "opus", "flac", "m4a", "mp4", "m4v", "mov",
+];
+
+// Default for --artist-title-split, matching the common "Artist - Title" naming
+pub const ARTIST_TITLE_SEPARATOR: &str = " - ";
+
+// Browsers yt-dlp can read cookies from with --cookies-from-browser
+pub const COOKIE_BROWSERS: &[&str] = &[
+    "brave", "chrome", "chromium", "edge", "firefox", "opera", "safari", "vivaldi", "whale",
+];
+
+// SponsorBlock categories yt-dlp can cut out with --sponsorblock-remove; `all` expands to these
+pub const SPONSORBLOCK_CATEGORIES: &[&str] = &[
+    "sponsor",
+    "intro",
+    "outro",
+    "selfpromo",
+    "preview",
+    "filler",
+    "interaction",
+    "music_offtopic",
+    "hook",
+];
+
+// Where yt-dlp fetches its challenge-solver scripts; see --remote-components
+pub const REMOTE_COMPONENTS_DEFAULT: &str = "ejs:github";
 
 pub const ARIA2C_ARGS: &str =
     "-x 8 -s 16 -k 2M --file-allocation=falloc --disk-cache=64M --enable-color=false";
+// --turbo: aria2c's connection cap, smaller pieces, resume and a larger write cache
+pub const ARIA2C_TURBO_ARGS: &str = "-x 16 -s 16 -k 1M --continue=true --file-allocation=falloc --disk-cache=256M --enable-color=false";
+
+// Written to --print-to-file after each output is final: extractor, id, title and path,
+// tab-separated
+pub const CAPTURE_OUTPUT_TEMPLATE: &str =
+    "after_move:%(extractor_key)s\t%(id)s\t%(title)s\t%(filepath)s";
+
+// yt-dlp's stdout note when --match-filter rejects an item; it still exits 0
+pub const FILTER_SKIP_MARKER: &str = "does not pass filter";
+
+// yt-dlp's exit code when it stops a playlist on purpose, e.g. for --break-on-existing
+pub const YTDLP_STOPPED_EARLY_EXIT: i32 = 101;
+
+// --fit-size aims this far under the size cap, leaving room for the mp4 container
+// and the encoder overshooting its bitrate
+pub const FIT_SIZE_HEADROOM: f64 = 0.97;
+// x264/x265's crf scale; config-file socm presets are checked against it
+pub const MAX_CRF: u8 = 51;
 
+pub const PROGRESS_WRITE_INTERVAL_MS: u64 = 500;
+pub const DEFAULT_PARALLEL: NonZeroUsize = NonZeroUsize::new(2).unwrap();
+// `--auto-parallel` starts at `DEFAULT_PARALLEL` and never runs more than this many
+pub const AUTO_PARALLEL_MAX: usize = 8;
+// A round has to beat the best throughput so far by this fraction to count as a gain
+pub const AUTO_PARALLEL_MIN_GAIN: f64 = 0.1;
 pub const BATCH_SLEEP_THRESHOLD: usize = 10;
 pub const BATCH_SLEEP_SECONDS: u64 = 5;
 pub const REQUEST_SLEEP_SECONDS: f64 = 0.5;
+// First `--retries` backoff, doubled for each further attempt
+pub const RETRY_BACKOFF_SECONDS: u64 = 2;
+// `--net-check` connects here on every host, and gives each one this long to answer
+pub const NET_CHECK_PORT: u16 = 443;
+pub const NET_CHECK_TIMEOUT_SECONDS: u64 = 5;
+// How long `--webhook` waits for the endpoint before warning and moving on
+pub const WEBHOOK_TIMEOUT_SECONDS: u64 = 10;
 pub const REQUIRED_DEPENDENCIES: &[&str] = &["yt-dlp", "aria2c", "ffmpeg"];
+// JS runtime yt-dlp uses to solve YouTube challenges via the ejs remote components
+pub const OPTIONAL_DEPENDENCIES: &[&str] = &["deno"];
+// Oldest yt-dlp with `--remote-components`
+pub const MIN_YTDLP_VERSION: &str = "2025.11.12";
+pub const MIN_FFMPEG_VERSION: &str = "4.4";
+pub const MIN_DEPENDENCY_VERSIONS: &[(&str, &str)] = &[
+    ("yt-dlp", MIN_YTDLP_VERSION),
+    ("ffmpeg", MIN_FFMPEG_VERSION),
+];
 
 #[cfg(test)]
 mod tests {
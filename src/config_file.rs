@@ -0,0 +1,333 @@
+//! Default flags from `~/.config/ytrs/config.toml`; anything given on the command line wins
+
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use serde::Deserialize;
+
+use crate::cli::Cli;
+use crate::error::{Result, YtrsError};
+use crate::expand::expand_path;
+use crate::mode::{UserPresets, resolve_preset};
+
+/// Flags that pick a download mode; a `socm` default would clash with any of them
+const MODE_FLAGS: &[&str] = &[
+    "socm",
+    "audio_only",
+    "video_only",
+    "quality",
+    "max_height",
+    "codec",
+    "reencode_audio",
+    "force_ext",
+];
+
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    pub destination: Option<PathBuf>,
+    pub parallel: Option<NonZeroUsize>,
+    pub cookies_from: Option<String>,
+    pub cookies_file: Option<PathBuf>,
+    /// Target name or alias, as for `--socm`
+    pub socm: Option<String>,
+    /// Extra `--socm` targets, one `[socm_presets.<name>]` table each
+    #[serde(default)]
+    pub socm_presets: UserPresets,
+}
+
+/// `$XDG_CONFIG_HOME/ytrs/config.toml`, falling back to `~/.config`
+fn config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("ytrs").join("config.toml"))
+}
+
+/// Reads the user's config file; no file means no defaults
+pub fn load_config() -> Result<FileConfig> {
+    config_path().map_or_else(|| Ok(FileConfig::default()), |path| load_config_from(&path))
+}
+
+fn load_config_from(path: &Path) -> Result<FileConfig> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => FileConfig::parse(&contents, path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(FileConfig::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl FileConfig {
+    fn parse(contents: &str, path: &Path) -> Result<Self> {
+        let invalid = |reason: String| YtrsError::InvalidConfig {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let mut config: Self =
+            toml::from_str(contents).map_err(|e| invalid(e.message().to_string()))?;
+        for (name, preset) in &mut config.socm_presets {
+            preset.name.clone_from(name);
+            preset.validate().map_err(invalid)?;
+        }
+        if let Some(target) = &config.socm
+            && resolve_preset(target, &config.socm_presets).is_none()
+        {
+            return Err(invalid(format!("unknown socm target '{target}'")));
+        }
+        Ok(config)
+    }
+
+    /// Fills in whatever the command line didn't set
+    pub fn apply(self, cli: &mut Cli, matches: &ArgMatches) {
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        if let Some(destination) = self.destination
+            && !from_cli("destination")
+        {
+            cli.destination = Some(PathBuf::from(expand_path(&destination.to_string_lossy())));
+        }
+        if let Some(parallel) = self.parallel
+            && !from_cli("parallel")
+        {
+            cli.parallel = parallel;
+        }
+        // Either cookie flag on the command line replaces both file settings
+        if !from_cli("cookies_from") && !from_cli("cookies_file") {
+            if let Some(browser) = self.cookies_from {
+                cli.cookies_from = Some(browser);
+            }
+            if let Some(file) = self.cookies_file {
+                cli.cookies_file = Some(PathBuf::from(expand_path(&file.to_string_lossy())));
+            }
+        }
+        if let Some(target) = self.socm
+            && !MODE_FLAGS.iter().any(|id| from_cli(id))
+        {
+            cli.socm = Some(target);
+        }
+        cli.socm_presets = self.socm_presets;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mode::DownloadMode;
+    use clap::{CommandFactory, FromArgMatches};
+
+    const CONFIG: &str = r#"
+destination = "/media/videos"
+parallel = 4
+cookies_from = "firefox"
+socm = "dc"
+"#;
+
+    fn merged(config: &str, args: &[&str]) -> Cli {
+        let matches = Cli::command().get_matches_from(args);
+        let mut cli = Cli::from_arg_matches(&matches).unwrap();
+        FileConfig::parse(config, Path::new("config.toml"))
+            .unwrap()
+            .apply(&mut cli, &matches);
+        cli
+    }
+
+    #[test]
+    fn test_file_over_builtin_default() {
+        let cli = merged(CONFIG, &["ytrs", "https://example.com"]);
+
+        assert_eq!(cli.destination, Some(PathBuf::from("/media/videos")));
+        assert_eq!(cli.parallel.get(), 4);
+        assert_eq!(cli.cookies_from.as_deref(), Some("firefox"));
+        assert_eq!(cli.socm.as_deref(), Some("dc"));
+
+        let cli = merged("", &["ytrs", "https://example.com"]);
+        assert_eq!(cli.parallel.get(), 2);
+        assert_eq!(cli.socm, None);
+    }
+
+    #[test]
+    fn test_cli_over_file() {
+        let cli = merged(
+            CONFIG,
+            &[
+                "ytrs",
+                "-p",
+                "2",
+                "-d",
+                "/tmp/out",
+                "--cookies-from",
+                "chrome",
+                "https://example.com",
+            ],
+        );
+        assert_eq!(cli.parallel.get(), 2);
+        assert_eq!(cli.destination, Some(PathBuf::from("/tmp/out")));
+        assert_eq!(cli.cookies_from.as_deref(), Some("chrome"));
+
+        let cli = merged(
+            CONFIG,
+            &[
+                "ytrs",
+                "--cookies-file",
+                "/srv/cookies.txt",
+                "https://example.com",
+            ],
+        );
+        assert_eq!(cli.cookies_from, None);
+        assert_eq!(cli.cookies_file, Some(PathBuf::from("/srv/cookies.txt")));
+        assert!(cli.download_options().is_ok());
+
+        // Picking another mode on the command line drops the file's socm default
+        let cli = merged(CONFIG, &["ytrs", "-a", "https://example.com"]);
+        assert_eq!(cli.socm, None);
+        assert!(cli.audio_only);
+    }
+
+    #[test]
+    fn test_print_config_cli_over_file() {
+        let cli = merged(
+            CONFIG,
+            &[
+                "ytrs",
+                "--print-config",
+                "-p",
+                "5",
+                "--cookies-from",
+                "chrome",
+            ],
+        );
+        // The same rendering `--print-config` prints
+        let printed = serde_json::to_string_pretty(&cli.download_options().unwrap()).unwrap();
+        let config: serde_json::Value = serde_json::from_str(&printed).unwrap();
+
+        assert_eq!(config["parallel"], 5);
+        assert_eq!(config["cookies_from"], "chrome");
+        // Settings the command line left alone still come from the file
+        assert_eq!(config["destination_path"], "/media/videos");
+        assert_eq!(config["mode"]["social_media"]["name"], "Discord");
+    }
+
+    #[test]
+    fn test_socm_only_flags_with_file_socm() {
+        let cli = merged(
+            "socm = \"dc\"",
+            &["ytrs", "--fit-size", "https://example.com"],
+        );
+        let options = cli.download_options().unwrap();
+        assert!(options.fit_size);
+        assert!(matches!(options.mode, DownloadMode::SocialMedia(_)));
+
+        let cli = merged("", &["ytrs", "--fit-size", "https://example.com"]);
+        assert!(matches!(
+            cli.download_options(),
+            Err(YtrsError::InvalidModeCombo(_))
+        ));
+    }
+
+    #[test]
+    fn test_file_paths_expand_vars() {
+        let config = "cookies_file = \"$HOME/cookies.txt\"";
+        let cli = merged(config, &["ytrs", "https://example.com"]);
+        assert_eq!(
+            cli.cookies_file,
+            Some(PathBuf::from(expand_path("$HOME/cookies.txt")))
+        );
+    }
+
+    #[test]
+    fn test_both_cookie_sources_from_file_conflict() {
+        let config = "cookies_from = \"firefox\"\ncookies_file = \"/srv/cookies.txt\"";
+        let cli = merged(config, &["ytrs", "https://example.com"]);
+        assert!(matches!(
+            cli.download_options(),
+            Err(YtrsError::ConflictingCookies)
+        ));
+        let cli = merged(config, &["ytrs", "-F", "https://example.com"]);
+        assert!(matches!(
+            cli.info_commands(),
+            Err(YtrsError::ConflictingCookies)
+        ));
+
+        let cli = merged(
+            config,
+            &["ytrs", "--cookies-from", "chrome", "https://example.com"],
+        );
+        let options = cli.download_options().unwrap();
+        assert_eq!(options.cookies_from.as_deref(), Some("chrome"));
+        assert_eq!(options.cookies_file, None);
+    }
+
+    #[test]
+    fn test_missing_and_malformed_files() {
+        let dir = std::env::temp_dir().join(format!("ytrs-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+
+        let missing = load_config_from(&path);
+        std::fs::write(&path, "parallel = \"lots\"").unwrap();
+        let malformed = load_config_from(&path);
+        std::fs::write(&path, "paralel = 4").unwrap();
+        let misspelled = load_config_from(&path);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(missing.unwrap(), FileConfig::default());
+        assert!(matches!(malformed, Err(YtrsError::InvalidConfig { .. })));
+        assert!(matches!(misspelled, Err(YtrsError::InvalidConfig { .. })));
+        assert!(matches!(
+            FileConfig::parse("socm = \"myspace\"", &path),
+            Err(YtrsError::InvalidConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn test_custom_socm_presets() {
+        let config = r#"
+socm = "mastodon"
+
+[socm_presets.mastodon]
+max_size_mb = 50
+max_height = 480
+crf = 26
+"#;
+        let cli = merged(config, &["ytrs", "https://example.com"]);
+        let DownloadMode::SocialMedia(preset) = cli.download_mode().unwrap() else {
+            panic!("expected the mastodon preset");
+        };
+        assert_eq!(preset.name, "mastodon");
+        assert_eq!(
+            (preset.max_size_mb, preset.max_height, preset.crf),
+            (50, 480, 26)
+        );
+        assert_eq!(preset.video_codec, "libx264");
+        assert_eq!(preset.audio_codec, "aac");
+
+        // Presets stay usable by name when the command line picks the target
+        let cli = merged(config, &["ytrs", "--socm", "ig", "https://example.com"]);
+        assert_eq!(
+            cli.download_mode().unwrap().to_string(),
+            "Social Media (Instagram)"
+        );
+    }
+
+    #[test]
+    fn test_invalid_socm_presets() {
+        let path = Path::new("config.toml");
+        for config in [
+            "[socm_presets.bad]\nmax_size_mb = 50\nmax_height = 480\ncrf = 60",
+            "[socm_presets.bad]\nmax_size_mb = 50\nmax_height = 480\nvideo_codec = \"\"",
+            "[socm_presets.bad]\nmax_size_mb = 50",
+            "[socm_presets.bad]\nmax_size_mb = 50\nmax_height = 480\nbitrate = \"1M\"",
+        ] {
+            assert!(
+                matches!(
+                    FileConfig::parse(config, path),
+                    Err(YtrsError::InvalidConfig { .. })
+                ),
+                "{config}"
+            );
+        }
+    }
+}
@@ -0,0 +1,287 @@
+//! Browser cookie export for headless runs
+
+use std::borrow::Cow;
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::SystemTime;
+
+use crate::cli::ProfileBrowser;
+use crate::config::COOKIE_BROWSERS;
+use crate::error::{Result, YtrsError, stderr_tail};
+use crate::theme::Themed;
+
+/// Checks the browser of a `BROWSER[+KEYRING][:PROFILE]` spec against the ones yt-dlp
+/// supports, so a typo fails before anything is spawned
+pub fn validate_cookie_browser(spec: &str) -> Result<()> {
+    let browser = spec.split([':', '+']).next().unwrap_or_default().trim();
+    if COOKIE_BROWSERS
+        .iter()
+        .any(|known| known.eq_ignore_ascii_case(browser))
+    {
+        Ok(())
+    } else {
+        Err(YtrsError::UnsupportedBrowser(browser.to_string()))
+    }
+}
+
+pub fn export_cookies_args<'a>(browser: &'a str, destination: &'a Path) -> Vec<Cow<'a, str>> {
+    vec![
+        Cow::Borrowed("--cookies-from-browser"),
+        Cow::Borrowed(browser),
+        Cow::Borrowed("--cookies"),
+        destination.to_string_lossy(),
+        Cow::Borrowed("--skip-download"),
+        Cow::Borrowed("--quiet"),
+        Cow::Borrowed("--no-warnings"),
+    ]
+}
+
+pub fn validate_writable(path: &Path) -> Result<()> {
+    let not_writable = |reason: String| YtrsError::DestinationNotWritable {
+        path: path.to_path_buf(),
+        reason,
+    };
+
+    if path.is_dir() {
+        return Err(not_writable("path is a directory".to_string()));
+    }
+
+    let parent = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    if !parent.is_dir() {
+        return Err(not_writable("parent directory does not exist".to_string()));
+    }
+
+    // Probe with a real open so permission bits, ACLs and read-only mounts are all honoured
+    if path.exists() {
+        OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map_err(|e| not_writable(e.to_string()))?;
+    } else {
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|e| not_writable(e.to_string()))?;
+        std::fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    path.metadata().and_then(|m| m.modified()).ok()
+}
+
+pub fn export_cookies(browser: &str, destination: &Path) -> Result<()> {
+    validate_cookie_browser(browser)?;
+    validate_writable(destination)?;
+
+    let before = modified_time(destination);
+    let output = Command::new("yt-dlp")
+        .args(
+            export_cookies_args(browser, destination)
+                .iter()
+                .map(AsRef::as_ref),
+        )
+        .stdin(Stdio::null())
+        .output()?;
+
+    // yt-dlp exits non-zero without a URL but still flushes the cookie jar on shutdown,
+    // so a freshly written file is the real success signal
+    let written = modified_time(destination).is_some_and(|after| Some(after) != before);
+    if !written {
+        return Err(YtrsError::YtDlpFailed {
+            code: output.status.code(),
+            stderr_tail: stderr_tail(&String::from_utf8_lossy(&output.stderr)),
+        });
+    }
+
+    println!(
+        "{} {}",
+        "Cookies exported to".success(),
+        destination.display().to_string().success()
+    );
+    Ok(())
+}
+
+/// A profile directory yt-dlp can read cookies from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BrowserProfile {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl ProfileBrowser {
+    /// Files that mark a directory as a profile with a cookie store
+    const fn cookie_files(self) -> &'static [&'static str] {
+        match self {
+            Self::Firefox => &["cookies.sqlite"],
+            Self::Chrome => &["Cookies", "Network/Cookies"],
+        }
+    }
+
+    /// Known profile roots on this platform, including snap and flatpak installs on Linux
+    fn profile_roots(self) -> Vec<PathBuf> {
+        let env_dir = |var: &str| std::env::var_os(var).map(PathBuf::from);
+        let home = env_dir("HOME");
+        let roots: Vec<Option<PathBuf>> = match self {
+            Self::Firefox => vec![
+                home.as_ref().map(|h| h.join(".mozilla/firefox")),
+                home.as_ref()
+                    .map(|h| h.join("snap/firefox/common/.mozilla/firefox")),
+                home.as_ref()
+                    .map(|h| h.join(".var/app/org.mozilla.firefox/.mozilla/firefox")),
+                home.as_ref()
+                    .map(|h| h.join("Library/Application Support/Firefox/Profiles")),
+                env_dir("APPDATA").map(|d| d.join("Mozilla/Firefox/Profiles")),
+            ],
+            Self::Chrome => vec![
+                home.as_ref().map(|h| h.join(".config/google-chrome")),
+                home.as_ref()
+                    .map(|h| h.join(".var/app/com.google.Chrome/config/google-chrome")),
+                home.as_ref()
+                    .map(|h| h.join("Library/Application Support/Google/Chrome")),
+                env_dir("LOCALAPPDATA").map(|d| d.join("Google/Chrome/User Data")),
+            ],
+        };
+        roots.into_iter().flatten().collect()
+    }
+}
+
+/// Subdirectories of `root` that hold one of `cookie_files`, sorted by name
+#[must_use]
+pub fn find_profiles(root: &Path, cookie_files: &[&str]) -> Vec<BrowserProfile> {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut profiles: Vec<BrowserProfile> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| cookie_files.iter().any(|file| path.join(file).is_file()))
+        .map(|path| BrowserProfile {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            path,
+        })
+        .collect();
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    profiles
+}
+
+pub fn list_browser_profiles(browser: ProfileBrowser) {
+    let profiles: Vec<BrowserProfile> = browser
+        .profile_roots()
+        .iter()
+        .flat_map(|root| find_profiles(root, browser.cookie_files()))
+        .collect();
+
+    if profiles.is_empty() {
+        eprintln!(
+            "{} No {browser} profiles with cookies found",
+            "Warning:".warning()
+        );
+        return;
+    }
+
+    for profile in profiles {
+        println!(
+            "{}\n  --cookies-from {browser}:{}",
+            profile.name.info(),
+            profile.path.display()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_cookie_browser() {
+        for spec in [
+            "firefox",
+            "Chrome",
+            "whale",
+            "firefox:work",
+            "chrome+gnomekeyring",
+            "chromium+kwallet:Profile 1",
+            "firefox:/home/me/.mozilla/firefox/abc.default",
+        ] {
+            assert!(validate_cookie_browser(spec).is_ok(), "{spec}");
+        }
+
+        let err = validate_cookie_browser("firfox").unwrap_err();
+        assert!(matches!(&err, YtrsError::UnsupportedBrowser(name) if name == "firfox"));
+        assert!(err.to_string().contains("brave, chrome"));
+        assert!(matches!(
+            validate_cookie_browser("chorme:Default"),
+            Err(YtrsError::UnsupportedBrowser(name)) if name == "chorme"
+        ));
+        assert!(validate_cookie_browser("").is_err());
+    }
+
+    #[test]
+    fn test_export_cookies_args() {
+        let args = export_cookies_args("firefox", Path::new("cookies.txt"));
+        assert_eq!(args[0], "--cookies-from-browser");
+        assert_eq!(args[1], "firefox");
+        assert_eq!(args[2], "--cookies");
+        assert_eq!(args[3], "cookies.txt");
+        assert!(args.iter().any(|s| s == "--skip-download"));
+    }
+
+    #[test]
+    fn test_validate_writable_temp_file() {
+        let path = std::env::temp_dir().join("ytrs-test-cookies-writable.txt");
+        assert!(validate_writable(&path).is_ok());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_validate_writable_missing_parent() {
+        let path = Path::new("/nonexistent-ytrs-dir/cookies.txt");
+        assert!(matches!(
+            validate_writable(path),
+            Err(YtrsError::DestinationNotWritable { .. })
+        ));
+    }
+
+    #[test]
+    fn test_find_profiles_in_mock_layout() {
+        let root = std::env::temp_dir().join(format!("ytrs-profiles-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for dir in [
+            "b1c2.default-release",
+            "a9x8.work",
+            "Crash Reports",
+            "Profile 1/Network",
+        ] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        std::fs::write(root.join("b1c2.default-release/cookies.sqlite"), "").unwrap();
+        std::fs::write(root.join("a9x8.work/cookies.sqlite"), "").unwrap();
+        std::fs::write(root.join("Profile 1/Network/Cookies"), "").unwrap();
+        std::fs::write(root.join("profiles.ini"), "").unwrap();
+
+        let firefox = find_profiles(&root, ProfileBrowser::Firefox.cookie_files());
+        let chrome = find_profiles(&root, ProfileBrowser::Chrome.cookie_files());
+        let _ = std::fs::remove_dir_all(&root);
+
+        let names: Vec<&str> = firefox.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["a9x8.work", "b1c2.default-release"]);
+        assert_eq!(firefox[0].path, root.join("a9x8.work"));
+        assert_eq!(chrome.len(), 1);
+        assert_eq!(chrome[0].name, "Profile 1");
+    }
+
+    #[test]
+    fn test_validate_writable_directory() {
+        assert!(validate_writable(&std::env::temp_dir()).is_err());
+    }
+}
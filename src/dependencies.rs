@@ -1,4 +1,10 @@
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use colored::Colorize;
+
 use crate::error::{Result, YtrsError};
+use crate::theme::Themed;
 
 pub fn check_dependencies(cmds: &[&str]) -> Result<()> {
     for cmd in cmds {
@@ -9,6 +15,124 @@ pub fn check_dependencies(cmds: &[&str]) -> Result<()> {
     Ok(())
 }
 
+/// Numeric parts of the first version-looking word: `2024.08.06` from yt-dlp,
+/// `6.1.1-3ubuntu5` or `n7.0` from ffmpeg; `None` for git builds like `N-113348-g0a58`
+#[must_use]
+pub fn parse_version(output: &str) -> Option<Vec<u32>> {
+    let word = output
+        .split_whitespace()
+        .map(|word| word.strip_prefix(['n', 'v']).unwrap_or(word))
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))?;
+    let end = word
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(word.len());
+    word[..end]
+        .split('.')
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse().ok())
+        .collect()
+}
+
+/// Errors on the first tool older than its minimum; versions that can't be read pass,
+/// since custom builds often print something unparseable
+pub fn check_versions(
+    minimums: &[(&str, &str)],
+    version_of: impl Fn(&str) -> Option<String>,
+) -> Result<()> {
+    for (cmd, required) in minimums {
+        let Some(found) = version_of(cmd).as_deref().and_then(parse_version) else {
+            continue;
+        };
+        if parse_version(required).is_some_and(|required| found < required) {
+            let found: Vec<String> = found.iter().map(ToString::to_string).collect();
+            return Err(YtrsError::OutdatedDependency {
+                name: (*cmd).to_string(),
+                found: found.join("."),
+                required: (*required).to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Where a tool resolved on PATH and the first line of its version output
+pub struct Resolution {
+    pub path: PathBuf,
+    pub version: Option<String>,
+}
+
+pub fn resolve_dependency(cmd: &str) -> Option<Resolution> {
+    let path = which::which(cmd).ok()?;
+    // ffmpeg only understands the single-dash form
+    let version_flag = if cmd == "ffmpeg" {
+        "-version"
+    } else {
+        "--version"
+    };
+
+    let version = Command::new(&path)
+        .arg(version_flag)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .next()
+                .map(|line| line.trim().to_string())
+        });
+
+    Some(Resolution { path, version })
+}
+
+/// First line of the tool's version output, if it's on PATH and answers
+pub fn installed_version(cmd: &str) -> Option<String> {
+    resolve_dependency(cmd)?.version
+}
+
+pub fn build_dependency_report(
+    required: &[&str],
+    optional: &[&str],
+    resolve: impl Fn(&str) -> Option<Resolution>,
+) -> String {
+    let width = required
+        .iter()
+        .chain(optional)
+        .map(|cmd| cmd.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut report = String::new();
+    let entries = required
+        .iter()
+        .map(|cmd| (cmd, "required"))
+        .chain(optional.iter().map(|cmd| (cmd, "optional")));
+
+    for (cmd, kind) in entries {
+        let line = match resolve(cmd) {
+            Some(resolution) => format!(
+                "  {} {cmd:<width$}  {kind}  {}  {}\n",
+                "found  ".success(),
+                resolution.path.display(),
+                resolution
+                    .version
+                    .as_deref()
+                    .unwrap_or("unknown version")
+                    .dimmed()
+            ),
+            None if kind == "required" => {
+                format!("  {} {cmd:<width$}  {kind}\n", "missing".error())
+            }
+            None => format!("  {} {cmd:<width$}  {kind}\n", "missing".warning()),
+        };
+        report.push_str(&line);
+    }
+
+    report
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -19,6 +143,77 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_dependency_report_with_mock_resolutions() {
+        let report = build_dependency_report(&["yt-dlp", "aria2c"], &["deno"], |cmd| {
+            (cmd == "yt-dlp").then(|| Resolution {
+                path: PathBuf::from("/usr/bin/yt-dlp"),
+                version: Some("2024.08.06".to_string()),
+            })
+        });
+        let lines: Vec<&str> = report.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("found") && lines[0].contains("required"));
+        assert!(lines[0].contains("/usr/bin/yt-dlp") && lines[0].contains("2024.08.06"));
+        assert!(lines[1].contains("missing") && lines[1].contains("aria2c"));
+        assert!(lines[2].contains("missing") && lines[2].contains("optional"));
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("2024.08.06"), Some(vec![2024, 8, 6]));
+        assert_eq!(
+            parse_version("2025.11.12.232910"),
+            Some(vec![2025, 11, 12, 232_910])
+        );
+        assert_eq!(
+            parse_version("ffmpeg version 6.1.1-3ubuntu5 Copyright (c) 2000-2023"),
+            Some(vec![6, 1, 1])
+        );
+        assert_eq!(
+            parse_version("ffmpeg version n7.0 Copyright (c) 2000-2024"),
+            Some(vec![7, 0])
+        );
+        assert_eq!(parse_version("ffmpeg version N-113348-g0a5813fc68"), None);
+        assert_eq!(parse_version(""), None);
+    }
+
+    #[test]
+    fn test_check_versions() {
+        let minimums = [("yt-dlp", "2025.11.12"), ("ffmpeg", "4.4")];
+        let installed = |ytdlp: &'static str| {
+            move |cmd: &str| {
+                Some(match cmd {
+                    "yt-dlp" => ytdlp.to_string(),
+                    _ => "ffmpeg version 6.1.1 Copyright".to_string(),
+                })
+            }
+        };
+
+        assert!(check_versions(&minimums, installed("2025.11.12")).is_ok());
+        assert!(check_versions(&minimums, installed("2026.01.03")).is_ok());
+        assert!(check_versions(&minimums, installed("custom build")).is_ok());
+        assert!(check_versions(&minimums, |_| None).is_ok());
+        match check_versions(&minimums, installed("2024.08.06")) {
+            Err(YtrsError::OutdatedDependency {
+                name,
+                found,
+                required,
+            }) => {
+                assert_eq!(name, "yt-dlp");
+                assert_eq!(found, "2024.8.6");
+                assert_eq!(required, "2025.11.12");
+            }
+            other => panic!("expected OutdatedDependency, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_resolve_dependency_missing() {
+        assert!(resolve_dependency("nonexistent_command_xyz").is_none());
+    }
+
     #[test]
     fn test_check_missing_command() {
         let result = check_dependencies(&["nonexistent_command_xyz"]);
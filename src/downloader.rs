@@ -1,193 +1,1464 @@
 //! Download orchestration with async execution and concurrency control
 
+use std::collections::HashMap;
 use std::num::NonZeroUsize;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::Arc;
+use std::sync::PoisonError;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
 
-use crate::args_builder::{YtDlpArgs, build_ytdlp_args};
-use crate::config::BATCH_SLEEP_THRESHOLD;
-use crate::error::{Result, YtrsError, extract_error_reason};
+use crate::archive::{ArchiveEntry, CapturedOutput, JsonArchive, parse_captured_outputs};
+use crate::args_builder::{
+    YtDlpArgs, build_ytdlp_args, render_argv, render_command, writes_to_file,
+};
+use crate::autoscale::{AutoParallel, Scaling};
+use crate::bars::{new_bar, relay_to_bar};
+use crate::cli::{
+    AudioCodec, FileDestBatch, OnCollision, ProgressDisplay, QualityPreset, VideoCodec,
+};
+use crate::collision::{Collision, CollisionPlan, find_collisions, prefetch_filenames};
+use crate::config::{
+    AUTO_PARALLEL_MAX, BATCH_SLEEP_THRESHOLD, DEFAULT_PARALLEL, FILTER_SKIP_MARKER,
+    REQUIRED_DEPENDENCIES, RETRY_BACKOFF_SECONDS, SHARE_COPY_SUFFIX, YTDLP_STOPPED_EARLY_EXIT,
+};
+use crate::cookies::validate_writable;
+use crate::error::{
+    ErrorKind, Result, YtrsError, classify_error, extract_error_reason, match_error_pattern,
+    stderr_tail,
+};
+use crate::local_media::{MediaProbe, probe_media, reencode_download};
+use crate::manifest::write_manifest;
 use crate::mode::DownloadMode;
+use crate::progress::{ProgressFile, ProgressStatus, parse_progress_line};
+use crate::prompt::ask;
+use crate::report::{BatchSummary, DownloadReport, ReportEntry, ReportStatus};
+use crate::size::format_bytes;
+use crate::staging::{move_to_destination, staging_dir};
+use crate::subtitles::{AUTO_BEST, pick_subtitle_lang, probe_subtitle_langs};
+use crate::theme::Themed;
+use crate::timing::ConcurrencyReport;
 use crate::url_validator::sanitize_and_deduplicate;
 use colored::Colorize;
 use futures::StreamExt;
+use indicatif::{MultiProgress, ProgressBar};
+use serde::Serialize;
 use signal_hook::consts::{SIGINT, SIGTERM};
 use signal_hook_tokio::Signals;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
 use tokio::process::Command;
-use tokio::sync::{Mutex, Semaphore};
+use tokio::sync::{Mutex, Notify, OwnedSemaphorePermit, Semaphore};
 use tokio::task::JoinSet;
+use tokio::time::Instant;
 
-pub async fn download_single(
-    url: &str,
-    destination_path: Option<&Path>,
-    cookies_from: Option<&str>,
-    mode: DownloadMode,
-) -> Result<()> {
-    let args = YtDlpArgs {
-        destination_path,
-        cookies_from,
-        mode,
-        apply_rate_limit: false,
-    };
+/// One yt-dlp invocation within the plan for a single URL
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DownloadStage {
+    pub mode: DownloadMode,
+    pub filename_suffix: Option<&'static str>,
+}
+
+/// `--also-archive` grabs the max-quality original before the socm re-encode
+#[must_use]
+pub fn download_plan(mode: &DownloadMode, also_archive: bool) -> Vec<DownloadStage> {
+    let mode = mode.clone();
+    match mode {
+        DownloadMode::SocialMedia(_) if also_archive => vec![
+            DownloadStage {
+                mode: DownloadMode::Default,
+                filename_suffix: None,
+            },
+            DownloadStage {
+                mode,
+                filename_suffix: Some(SHARE_COPY_SUFFIX),
+            },
+        ],
+        _ => vec![DownloadStage {
+            mode,
+            filename_suffix: None,
+        }],
+    }
+}
+
+/// Failed yt-dlp run with its captured stderr kept for error context
+struct RunFailure {
+    reason: String,
+    kind: ErrorKind,
+    stderr: String,
+}
+
+impl RunFailure {
+    fn other(reason: String) -> Self {
+        Self {
+            reason,
+            kind: ErrorKind::Other,
+            stderr: String::new(),
+        }
+    }
 
-    let cmd_args = build_ytdlp_args(url, &args);
+    /// yt-dlp's own stderr tail, kept only when the reason couldn't be classified
+    fn detail(&self) -> Option<String> {
+        if match_error_pattern(&self.stderr).is_some() {
+            return None;
+        }
+        stderr_tail(&self.stderr)
+    }
+}
+
+/// How a successful yt-dlp run ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RunOutcome {
+    Downloaded,
+    /// `--match-filter` rejected the item; yt-dlp exits 0 without downloading
+    FilteredOut,
+}
+
+fn is_filter_skip(line: &str) -> bool {
+    line.contains(FILTER_SKIP_MARKER)
+}
+
+/// Where a running download reports its progress
+#[derive(Clone, Copy)]
+struct OutputSinks<'a> {
+    progress: Option<&'a ProgressFile>,
+    bar: Option<&'a ProgressBar>,
+    /// Swallow yt-dlp's stdout instead of relaying it
+    quiet: bool,
+    /// Print the argv before spawning
+    log_argv: bool,
+}
+
+/// Runs yt-dlp to completion, returning a human-readable reason on failure
+async fn run_ytdlp(
+    program: &str,
+    url: &str,
+    args: &YtDlpArgs<'_>,
+    sinks: OutputSinks<'_>,
+) -> std::result::Result<RunOutcome, RunFailure> {
+    let cmd_args = build_ytdlp_args(url, args);
+    if sinks.log_argv {
+        eprint!(
+            "{} {}\n{}",
+            "Command:".dimmed(),
+            url,
+            render_argv("yt-dlp", &cmd_args)
+        );
+    }
     let cmd_args_str: Vec<String> = cmd_args
         .iter()
         .map(std::string::ToString::to_string)
         .collect();
 
-    let mut child = Command::new("yt-dlp")
+    // Line-by-line output is only requested when something needs to read it
+    let relay = !sinks.quiet && sinks.bar.is_none();
+    let stdout = if args.newline_progress || !relay {
+        Stdio::piped()
+    } else {
+        Stdio::inherit()
+    };
+    let mut child = Command::new(program)
         .args(&cmd_args_str)
-        .stdout(Stdio::inherit())
+        .stdout(stdout)
         .stderr(Stdio::piped())
-        .spawn()?;
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| RunFailure::other(format!("Failed to spawn yt-dlp: {e}")))?;
 
-    let exit_status = child.wait().await?;
-
-    if !exit_status.success() {
-        // Read stderr for error context
+    // Drain stderr alongside stdout so a chatty process can't fill the pipe and stall
+    let stderr = child.stderr.take();
+    let read_stderr = async {
         let mut stderr_output = String::new();
-        if let Some(mut stderr) = child.stderr.take() {
+        if let Some(mut stderr) = stderr {
             let _ = stderr.read_to_string(&mut stderr_output).await;
         }
+        stderr_output
+    };
+    let stdout = child.stdout.take();
+    let relay_stdout = async {
+        let mut filtered_out = false;
+        if let Some(stdout) = stdout {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if relay {
+                    println!("{line}");
+                }
+                filtered_out |= is_filter_skip(&line);
+                if let Some(bar) = sinks.bar {
+                    relay_to_bar(bar, &line);
+                }
+                if let Some(progress) = sinks.progress
+                    && let Some(update) = parse_progress_line(&line)
+                {
+                    progress.update(url, update);
+                }
+            }
+        }
+        filtered_out
+    };
+    let (filtered_out, stderr_output) = tokio::join!(relay_stdout, read_stderr);
 
-        let reason = extract_error_reason(&stderr_output, exit_status.code());
-        return Err(YtrsError::DownloadFailed {
-            url: url.to_string(),
-            reason,
+    let exit_status = child
+        .wait()
+        .await
+        .map_err(|e| RunFailure::other(format!("Process error: {e}")))?;
+
+    // --break-on-existing stopping a channel at its archived items is the expected end
+    if exit_status.success() || exit_status.code() == Some(YTDLP_STOPPED_EARLY_EXIT) {
+        return Ok(if filtered_out {
+            RunOutcome::FilteredOut
+        } else {
+            RunOutcome::Downloaded
         });
     }
 
-    Ok(())
+    Err(RunFailure {
+        reason: extract_error_reason(&stderr_output, exit_status.code()),
+        kind: classify_error(&stderr_output),
+        stderr: stderr_output,
+    })
+}
+
+/// Settings shared by every download in a run
+#[derive(Clone, Debug, Serialize)]
+pub struct DownloadOptions {
+    pub destination_path: Option<PathBuf>,
+    pub cookies_from: Option<String>,
+    pub cookies_file: Option<PathBuf>,
+    pub mode: DownloadMode,
+    pub also_archive: bool,
+    pub by_uploader: bool,
+    pub output_template: Option<String>,
+    pub force: bool,
+    pub dedupe: bool,
+    pub parallel: NonZeroUsize,
+    /// Treat `parallel` as a starting point and scale it with measured throughput
+    pub auto_parallel: bool,
+    /// Minimum gap between starting top-level URLs in a batch
+    #[serde(serialize_with = "serialize_secs")]
+    pub cooldown: Option<Duration>,
+    /// Stop the batch, keeping what finished, once it has run this long
+    #[serde(serialize_with = "serialize_secs")]
+    pub max_runtime: Option<Duration>,
+    /// JSON file kept up to date with per-URL progress for external monitoring
+    pub progress_file: Option<PathBuf>,
+    /// Stop launching new URLs once completed files add up to this many bytes
+    pub max_total_size: Option<u64>,
+    /// Bytes per second shared by all running downloads
+    pub total_rate: Option<u64>,
+    pub reencode_audio: Option<AudioCodec>,
+    /// JSON report of every URL's outcome, written when the batch ends
+    pub report: Option<PathBuf>,
+    /// CSV of every downloaded file with its URL, title, id and size, written when the
+    /// batch ends
+    pub manifest: Option<PathBuf>,
+    /// Failed URLs, one per line, written when the batch ends with any failures
+    pub failed_log: Option<PathBuf>,
+    /// Stop the whole batch on the first authentication failure
+    pub abort_on_auth_error: bool,
+    /// Ask on the terminal for a cookie browser when a URL fails auth without cookies
+    pub prompt_for_cookies_on_auth_failure: bool,
+    /// Stop the whole batch once this many downloads have failed
+    pub max_failures: Option<NonZeroUsize>,
+    pub remote_components: Option<String>,
+    pub no_remote_components: bool,
+    pub turbo: bool,
+    /// aria2c connections per download; `parallel` downloads each open this many
+    pub connections: Option<u8>,
+    /// Use yt-dlp's built-in downloader, so aria2c needn't be installed
+    pub no_aria2c: bool,
+    /// aria2c session file for resuming large downloads across runs
+    pub aria_session: Option<PathBuf>,
+    /// Bytes per second for each download's aria2c
+    pub rate_limit: Option<u64>,
+    /// Skip items longer than this many seconds instead of downloading them
+    pub max_duration: Option<u64>,
+    pub force_ext: Option<String>,
+    pub artist_title_separator: Option<String>,
+    /// Only items uploaded on or after this `--dateafter` date
+    pub since: Option<String>,
+    /// `ytrs sync`: keep going past broken items and stop at the first archived one
+    pub sync: bool,
+    /// Validated `--playlist-items` spec
+    pub playlist_items: Option<String>,
+    /// Validated SponsorBlock categories to remove
+    pub sponsorblock: Option<String>,
+    /// Subtitle languages to download and embed
+    pub subs: Option<String>,
+    pub auto_subs: bool,
+    pub embed_thumbnail: bool,
+    pub embed_metadata: bool,
+    /// ytrs-managed JSON record of completed downloads; recorded ids are skipped
+    pub json_archive: Option<PathBuf>,
+    /// The user's own yt-dlp `--download-archive` file
+    pub archive: Option<PathBuf>,
+    pub audio_lang: Option<String>,
+    /// Print wall-clock vs summed download time after the batch
+    pub concurrency_report: bool,
+    /// Prefetch output filenames and handle URLs that would overwrite each other
+    pub on_collision: Option<OnCollision>,
+    /// Whether a file destination shared by several URLs is an error or gets indexed names
+    pub on_file_dest_batch: FileDestBatch,
+    /// Hide yt-dlp's output unless the download fails, then show its whole stderr
+    pub quiet_on_success: bool,
+    pub quality: QualityPreset,
+    /// Height cap for the archive quality preset in place of 2160p
+    pub max_height: Option<u32>,
+    /// Codec the archive quality preset ranks first
+    pub codec: VideoCodec,
+    /// Download into a private dir under this local path, then move into the destination
+    pub staging: Option<PathBuf>,
+    /// Retry once with a relaxed format selector when the requested format is missing
+    pub retry_lower_quality: bool,
+    /// Extra attempts for a download that failed with a transient error
+    pub retries: usize,
+    pub progress_style: ProgressDisplay,
+    /// Only the failure summary is printed, and only if something failed
+    pub fail_summary_only: bool,
+    /// No status lines from ytrs; failures and the failure summary still print
+    pub quiet: bool,
+    /// Pass `--verbose` to yt-dlp
+    pub verbose: bool,
+    /// Keep downloaded fragments after merging
+    pub keep_fragments: bool,
+    /// Print a [`BatchSummary`] as JSON instead of any status output
+    pub json: bool,
+    /// Log each spawned yt-dlp argv, one argument per line
+    pub verbose_args: bool,
+    /// Exit successfully even if some downloads failed
+    pub exit_zero_on_partial: bool,
+    /// Exit successfully if at least this percentage of the downloads that ran completed
+    pub success_threshold: Option<u8>,
+    /// Hash completed files and report near-duplicates; needs the `perceptual-dedupe` feature
+    pub perceptual_dedupe: bool,
+    /// POST a summary of the finished batch here; needs the `webhook` feature
+    pub webhook: Option<String>,
+    /// Re-encode social media downloads in ytrs, at most this many at once
+    pub max_concurrent_ffmpeg: Option<NonZeroUsize>,
+    /// Re-encode social media downloads even when they already fit the preset
+    pub force_reencode: bool,
+    /// Size social media re-encodes to the preset's cap with a two-pass encode
+    pub fit_size: bool,
+    /// Fail downloads whose finished files ffprobe can't read
+    pub verify: bool,
+    /// Print the yt-dlp command lines instead of running them
+    pub dry_run: bool,
+}
+
+fn serialize_secs<S: serde::Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    duration.map(|d| d.as_secs()).serialize(serializer)
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            destination_path: None,
+            cookies_from: None,
+            cookies_file: None,
+            mode: DownloadMode::default(),
+            also_archive: false,
+            by_uploader: false,
+            output_template: None,
+            force: false,
+            dedupe: true,
+            parallel: DEFAULT_PARALLEL,
+            auto_parallel: false,
+            cooldown: None,
+            max_runtime: None,
+            progress_file: None,
+            max_total_size: None,
+            total_rate: None,
+            reencode_audio: None,
+            report: None,
+            manifest: None,
+            failed_log: None,
+            abort_on_auth_error: false,
+            prompt_for_cookies_on_auth_failure: false,
+            max_failures: None,
+            remote_components: None,
+            no_remote_components: false,
+            turbo: false,
+            connections: None,
+            no_aria2c: false,
+            aria_session: None,
+            rate_limit: None,
+            max_duration: None,
+            force_ext: None,
+            artist_title_separator: None,
+            since: None,
+            sync: false,
+            playlist_items: None,
+            sponsorblock: None,
+            subs: None,
+            auto_subs: false,
+            embed_thumbnail: false,
+            embed_metadata: false,
+            json_archive: None,
+            archive: None,
+            audio_lang: None,
+            concurrency_report: false,
+            on_collision: None,
+            on_file_dest_batch: FileDestBatch::default(),
+            quiet_on_success: false,
+            quality: QualityPreset::default(),
+            max_height: None,
+            codec: VideoCodec::default(),
+            staging: None,
+            retry_lower_quality: false,
+            retries: 0,
+            progress_style: ProgressDisplay::default(),
+            fail_summary_only: false,
+            quiet: false,
+            verbose: false,
+            keep_fragments: false,
+            json: false,
+            verbose_args: false,
+            exit_zero_on_partial: false,
+            success_threshold: None,
+            perceptual_dedupe: false,
+            webhook: None,
+            max_concurrent_ffmpeg: None,
+            force_reencode: false,
+            fit_size: false,
+            verify: false,
+            dry_run: false,
+        }
+    }
+}
+
+impl DownloadOptions {
+    #[must_use]
+    pub fn ytdlp_args(&self, stage: &DownloadStage, apply_rate_limit: bool) -> YtDlpArgs<'_> {
+        YtDlpArgs {
+            destination_path: self.destination_path.as_deref(),
+            cookies_from: self.cookies_from.as_deref(),
+            cookies_file: self.cookies_file.as_deref(),
+            mode: stage.mode.clone(),
+            apply_rate_limit,
+            filename_suffix: stage.filename_suffix,
+            by_uploader: self.by_uploader,
+            output_template: self.output_template.as_deref(),
+            force: self.force,
+            newline_progress: self.progress_file.is_some()
+                || self.max_duration.is_some()
+                || self.progress_style != ProgressDisplay::None,
+            capture_outputs_to: None,
+            download_archive: None,
+            reencode_audio: self.reencode_audio,
+            remote_components: self.remote_components.as_deref(),
+            no_remote_components: self.no_remote_components,
+            turbo: self.turbo,
+            connections: self.connections,
+            no_aria2c: self.no_aria2c,
+            verbose: self.verbose,
+            keep_fragments: self.keep_fragments,
+            aria_session: self.aria_session.as_deref(),
+            aria_rate_limit: self.rate_limit,
+            max_duration: self.max_duration,
+            force_ext: self.force_ext.as_deref(),
+            artist_title_separator: self.artist_title_separator.as_deref(),
+            audio_lang: self.audio_lang.as_deref(),
+            quality: self.quality,
+            max_height: self.max_height,
+            codec: self.codec,
+            relaxed_format: false,
+            defer_encode: self.defers_encode(),
+            date_after: self.since.as_deref(),
+            break_on_existing: self.sync,
+            ignore_errors: self.sync,
+            playlist_items: self.playlist_items.as_deref(),
+            sponsorblock: self.sponsorblock.as_deref(),
+            subs: self.subs.as_deref(),
+            auto_subs: self.auto_subs,
+            embed_thumbnail: self.embed_thumbnail,
+            embed_metadata: self.embed_metadata,
+            // Depends on how many downloads share --total-rate, set per batch
+            limit_rate: None,
+        }
+    }
+
+    #[must_use]
+    pub fn retries_with_lower_quality(&self, kind: ErrorKind) -> bool {
+        self.retry_lower_quality && kind == ErrorKind::FormatUnavailable
+    }
+
+    /// Whether a batch with some failures still counts as a success; skipped URLs
+    /// don't count either way
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn accepts_partial(&self, completed: usize, failed: usize) -> bool {
+        if self.exit_zero_on_partial {
+            return true;
+        }
+        let Some(threshold) = self.success_threshold else {
+            return false;
+        };
+        let ran = completed + failed;
+        ran == 0 || completed as f64 * 100.0 >= f64::from(threshold) * ran as f64
+    }
+
+    /// The tools these options run; aria2c only when it's the downloader, ffprobe
+    /// only for `verify`
+    #[must_use]
+    pub fn required_dependencies(&self) -> Vec<&'static str> {
+        let mut dependencies: Vec<&'static str> = REQUIRED_DEPENDENCIES
+            .iter()
+            .copied()
+            .filter(|name| !(self.no_aria2c && *name == "aria2c"))
+            .collect();
+        if self.verify {
+            dependencies.push("ffprobe");
+        }
+        dependencies
+    }
+
+    /// Whether ytrs prints its own progress and status lines
+    #[must_use]
+    pub const fn prints_status(&self) -> bool {
+        !self.fail_summary_only && !self.quiet && !self.json
+    }
+
+    /// Whether yt-dlp only remuxes social media downloads, leaving ytrs to probe them
+    /// and re-encode the ones that don't already fit the preset
+    #[must_use]
+    pub const fn defers_encode(&self) -> bool {
+        self.max_concurrent_ffmpeg.is_some() || self.fit_size || !self.force_reencode
+    }
+
+    /// Whether a failure of this kind should stop the rest of the batch
+    #[must_use]
+    pub fn aborts_batch(&self, kind: ErrorKind) -> bool {
+        self.abort_on_auth_error && kind == ErrorKind::Auth
+    }
+
+    /// Whether a failure of this kind is worth asking the user for a cookie browser
+    #[must_use]
+    pub fn prompts_for_cookies(&self, kind: ErrorKind) -> bool {
+        self.prompt_for_cookies_on_auth_failure
+            && self.cookies_from.is_none()
+            && self.cookies_file.is_none()
+            && kind == ErrorKind::Auth
+    }
+
+    fn progress_tracker(&self) -> Result<Option<ProgressFile>> {
+        self.progress_file
+            .as_ref()
+            .map(|path| {
+                validate_writable(path)?;
+                Ok(ProgressFile::new(path.clone()))
+            })
+            .transpose()
+    }
+}
+
+/// Spaces out task launches so consecutive URLs start at least `interval` apart
+struct LaunchGate {
+    interval: Option<Duration>,
+    last_launch: Option<Instant>,
+}
+
+impl LaunchGate {
+    const fn new(interval: Option<Duration>) -> Self {
+        Self {
+            interval,
+            last_launch: None,
+        }
+    }
+
+    async fn wait(&mut self) {
+        if let (Some(interval), Some(last)) = (self.interval, self.last_launch) {
+            tokio::time::sleep_until(last + interval).await;
+        }
+        self.last_launch = Some(Instant::now());
+    }
+}
+
+/// Running total of completed file sizes against `--max-total-size`
+struct SizeBudget {
+    limit: u64,
+    used: AtomicU64,
+}
+
+impl SizeBudget {
+    const fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            used: AtomicU64::new(0),
+        }
+    }
+
+    fn record(&self, bytes: u64) {
+        self.used.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn used(&self) -> u64 {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.used() >= self.limit
+    }
+}
+
+/// Scratch file yt-dlp appends each final output to, unique per URL in this run
+fn output_paths_file(index: usize) -> PathBuf {
+    std::env::temp_dir().join(format!("ytrs-{}-{index}.paths", std::process::id()))
 }
 
+/// yt-dlp `--download-archive` copy of the JSON archive, shared by the whole run
+fn ytdlp_archive_file() -> PathBuf {
+    std::env::temp_dir().join(format!("ytrs-{}.archive", std::process::id()))
+}
+
+fn read_captured_outputs(path: &Path) -> Vec<CapturedOutput> {
+    parse_captured_outputs(&std::fs::read_to_string(path).unwrap_or_default())
+}
+
+/// Sums the on-disk sizes of the outputs yt-dlp reported, skipping any that vanished
+fn total_output_size(outputs: &[CapturedOutput]) -> u64 {
+    outputs
+        .iter()
+        .filter_map(|output| std::fs::metadata(&output.path).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Why a batch was stopped before every URL ran
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum AbortCause {
+    AuthFailure { url: String, reason: String },
+    TooManyFailures(usize),
+    OutOfTime(Duration),
+}
+
+impl AbortCause {
+    fn announce(&self) {
+        match self {
+            Self::AuthFailure { .. } => eprintln!(
+                "\n{} {}",
+                "Authentication failed.".error(),
+                "Stopping the batch - refresh your cookies and retry.".warning()
+            ),
+            Self::TooManyFailures(count) => eprintln!(
+                "\n{} {}",
+                format!("{count} downloads failed.").error(),
+                "Stopping the batch - something is likely wrong across the board.".warning()
+            ),
+            Self::OutOfTime(limit) => eprintln!(
+                "\n{} {}",
+                format!("Reached --max-runtime ({}s).", limit.as_secs()).error(),
+                "Stopping the batch - finished downloads are kept.".warning()
+            ),
+        }
+    }
+
+    fn into_error(self) -> YtrsError {
+        match self {
+            Self::AuthFailure { url, reason } => YtrsError::AuthAborted { url, reason },
+            Self::TooManyFailures(count) => YtrsError::TooManyFailures(count),
+            Self::OutOfTime(limit) => YtrsError::RuntimeExceeded(limit),
+        }
+    }
+}
+
+/// Trips once to stop a whole batch, remembering what caused it
+#[derive(Default)]
+struct AbortSwitch {
+    notify: Notify,
+    cause: std::sync::Mutex<Option<AbortCause>>,
+}
+
+impl AbortSwitch {
+    fn trip(&self, cause: AbortCause) {
+        let mut current = self.cause.lock().unwrap_or_else(PoisonError::into_inner);
+        if current.is_none() {
+            *current = Some(cause);
+            self.notify.notify_one();
+        }
+    }
+
+    async fn tripped(&self) {
+        self.notify.notified().await;
+    }
+
+    fn cause(&self) -> Option<AbortCause> {
+        self.cause
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+/// Trips `abort` once `limit` has passed; never finishes without a limit
+async fn out_of_time(limit: Option<Duration>, abort: &AbortSwitch) {
+    let Some(limit) = limit else {
+        return std::future::pending().await;
+    };
+    tokio::time::sleep(limit).await;
+    abort.trip(AbortCause::OutOfTime(limit));
+}
+
+/// Counts failures across tasks for `--max-failures`
+struct FailureBreaker {
+    limit: Option<NonZeroUsize>,
+    count: AtomicUsize,
+}
+
+impl FailureBreaker {
+    const fn new(limit: Option<NonZeroUsize>) -> Self {
+        Self {
+            limit,
+            count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Records one failure; returns the total if this one crossed the limit
+    fn record(&self) -> Option<usize> {
+        let count = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+        self.limit
+            .filter(|limit| count == limit.get())
+            .map(|_| count)
+    }
+}
+
+/// Per-URL status for library callers, in place of the terminal status lines
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DownloadEvent {
+    Started {
+        url: String,
+    },
+    Completed {
+        url: String,
+    },
+    /// Ran, or was passed over, without anything to download
+    Skipped {
+        url: String,
+        reason: String,
+    },
+    Failed {
+        url: String,
+        code: ErrorKind,
+        reason: String,
+    },
+}
+
+/// Called from the download tasks, possibly several at once
+pub type EventCallback = Arc<dyn Fn(DownloadEvent) + Send + Sync>;
+
 struct DownloadContext {
-    destination_path: Option<Arc<Path>>,
-    cookies_from: Option<Arc<str>>,
-    mode: DownloadMode,
+    options: DownloadOptions,
+    /// The yt-dlp executable to spawn
+    ytdlp: &'static str,
     apply_rate_limit: bool,
+    progress: Option<ProgressFile>,
+    budget: Option<SizeBudget>,
+    completed: Mutex<Vec<String>>,
+    /// Every file written, for `--perceptual-dedupe`
+    #[cfg(feature = "perceptual-dedupe")]
+    output_files: Mutex<Vec<PathBuf>>,
+    /// URLs that ran but had nothing to download, with the reason
+    filtered: Mutex<Vec<(String, &'static str)>>,
+    /// Files each completed URL wrote, for `--manifest`
+    manifest_outputs: Mutex<Vec<(String, Vec<CapturedOutput>)>>,
+    archive: Option<JsonArchive>,
+    ytdlp_archive: Option<PathBuf>,
+    /// Extra filename suffix for URLs renamed by `--on-collision index`
+    collision_suffixes: HashMap<String, String>,
+    /// How long each finished task ran, start to end
+    task_times: Mutex<Vec<Duration>>,
+    /// Set unless `--progress-style none`
+    bars: Option<MultiProgress>,
+    /// One permit per download allowed to run at once
+    download_slots: Arc<Semaphore>,
+    /// Set for `--auto-parallel`
+    auto_parallel: Option<Mutex<AutoParallel>>,
+    /// Download slots `--auto-parallel` gave up that are still held by running downloads
+    retiring_slots: AtomicUsize,
+    /// Gates deferred social media re-encodes, separately from the download slots
+    encode_slots: Option<Semaphore>,
+    /// Each download's share of `--total-rate`
+    per_download_rate: Option<u64>,
+    /// One cookie prompt on the terminal at a time
+    cookie_prompt: Mutex<()>,
+    on_event: Option<EventCallback>,
+    abort: AbortSwitch,
+    breaker: FailureBreaker,
 }
 
+impl DownloadContext {
+    /// The `--download-archive` file for yt-dlp: the one generated from the JSON archive,
+    /// or the user's own; none with `--force`, which downloads archived items again
+    fn download_archive(&self) -> Option<&Path> {
+        if self.options.force {
+            return None;
+        }
+        self.ytdlp_archive
+            .as_deref()
+            .or(self.options.archive.as_deref())
+    }
+
+    /// Feeds a finished download to `--auto-parallel` and applies its decision
+    async fn rescale(&self, bytes: u64, failed: bool) {
+        let Some(auto_parallel) = &self.auto_parallel else {
+            return;
+        };
+        let Some((scaling, limit)) = auto_parallel.lock().await.record(bytes, failed) else {
+            return;
+        };
+        match scaling {
+            Scaling::Up => self.download_slots.add_permits(1),
+            // The dispatch loop retires a slot before it starts the next download
+            Scaling::Down => {
+                self.retiring_slots.fetch_add(1, Ordering::Relaxed);
+            }
+            Scaling::Hold => return,
+        }
+        if self.options.prints_status() {
+            self.suspend_bars(|| println!("{} {limit}", "Parallel downloads:".dimmed()));
+        }
+    }
+
+    /// Prints without tearing up the progress bars, if any
+    fn suspend_bars<R>(&self, print: impl FnOnce() -> R) -> R {
+        match &self.bars {
+            Some(multi) => multi.suspend(print),
+            None => print(),
+        }
+    }
+
+    /// Asks which browser to take cookies from for `url`; `None` to give up on it
+    async fn ask_cookie_browser(&self, url: &str, kind: ErrorKind) -> Option<String> {
+        let _turn = self.cookie_prompt.lock().await;
+        let question = format!(
+            "{} {} - Retry with cookies from which browser? [skip]",
+            "Authentication failed:".warning(),
+            url.warning()
+        );
+        // Reading stdin blocks, so keep it off the runtime's worker
+        tokio::task::block_in_place(|| {
+            self.suspend_bars(|| cookie_retry_browser(&self.options, kind, || ask(&question)))
+        })
+    }
+
+    /// `--subs auto-best`: the one language to fetch for `url`, or `None` to skip subtitles
+    async fn auto_best_subs(&self, url: &str) -> Option<String> {
+        let mut cookie_args = Vec::new();
+        if let Some(browser) = &self.options.cookies_from {
+            cookie_args.extend(["--cookies-from-browser".to_string(), browser.clone()]);
+        }
+        if let Some(file) = &self.options.cookies_file {
+            cookie_args.extend(["--cookies".to_string(), file.display().to_string()]);
+        }
+        let available = probe_subtitle_langs(url, &cookie_args, self.options.auto_subs).await?;
+        let lang = pick_subtitle_lang(&available, std::env::var("LANG").ok().as_deref());
+        if lang.is_none() && self.options.prints_status() {
+            self.suspend_bars(|| {
+                println!(
+                    "{} {} - no subtitles in your language or English",
+                    "Note:".warning(),
+                    url
+                );
+            });
+        }
+        lang
+    }
+
+    /// Hands the event to the library callback; `false` when there is none and the
+    /// usual line should be printed instead
+    fn emit(&self, event: impl FnOnce() -> DownloadEvent) -> bool {
+        self.on_event
+            .as_ref()
+            .map(|on_event| on_event(event()))
+            .is_some()
+    }
+
+    fn report_status(&self, url: &str, status: ProgressStatus) {
+        if let Some(progress) = &self.progress {
+            progress.set_status(url, status);
+        }
+    }
+}
+
+const FILTERED_REASON: &str = "Longer than --max-duration";
+const ARCHIVED_REASON: &str = "Already in the JSON archive";
+const DOWNLOAD_ARCHIVE_REASON: &str = "Already in the download archive";
+const COLLISION_REASON: &str = "Same output file as an earlier URL";
+
 struct FailedDownload {
     url: String,
     reason: String,
+    detail: Option<String>,
 }
 
 async fn download_url_task(
+    index: usize,
     url: String,
     ctx: Arc<DownloadContext>,
     failed_downloads: Arc<Mutex<Vec<FailedDownload>>>,
+    download_slot: OwnedSemaphorePermit,
 ) {
-    println!("{} {}", "Starting:".cyan(), url.cyan());
+    let bar = ctx
+        .bars
+        .as_ref()
+        .and_then(|multi| new_bar(ctx.options.progress_style, multi, &url));
+    let started = ctx.emit(|| DownloadEvent::Started { url: url.clone() });
+    if !started && bar.is_none() && ctx.options.prints_status() {
+        println!("{} {}", "Starting:".info(), url.info());
+    }
+    ctx.report_status(&url, ProgressStatus::Downloading);
 
-    let args = YtDlpArgs {
-        destination_path: ctx.destination_path.as_deref(),
-        cookies_from: ctx.cookies_from.as_deref(),
-        mode: ctx.mode,
-        apply_rate_limit: ctx.apply_rate_limit,
+    let needs_outputs = ctx.budget.is_some()
+        || ctx.download_archive().is_some()
+        || ctx.options.staging.is_some()
+        || ctx.options.perceptual_dedupe
+        || ctx.options.verify
+        || ctx.options.manifest.is_some()
+        || ctx.auto_parallel.is_some()
+        || (matches!(ctx.options.mode, DownloadMode::SocialMedia(_))
+            && ctx.options.defers_encode());
+    let scratch = TaskScratch {
+        paths_file: needs_outputs.then(|| output_paths_file(index)),
+        stage_dir: ctx
+            .options
+            .staging
+            .as_deref()
+            .map(|base| staging_dir(base, index)),
     };
+    let paths_file = scratch.paths_file.as_deref();
+    // yt-dlp only treats the output as a directory if it already exists
+    if let Some(stage_dir) = &scratch.stage_dir
+        && let Err(e) = std::fs::create_dir_all(stage_dir)
+    {
+        let failure = RunFailure::other(format!(
+            "Could not create staging dir {}: {e}",
+            stage_dir.display()
+        ));
+        ctx.record_failure(url, failure, bar.as_ref(), &failed_downloads)
+            .await;
+        return;
+    }
 
-    let cmd_args = build_ytdlp_args(&url, &args);
-    let cmd_args_str: Vec<String> = cmd_args
-        .iter()
-        .map(std::string::ToString::to_string)
-        .collect();
-
-    let result = Command::new("yt-dlp")
-        .args(&cmd_args_str)
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::piped())
-        .spawn();
-
-    match result {
-        Ok(mut child) => {
-            let exit_status = child.wait().await;
+    let best_subs = match ctx.options.subs.as_deref() {
+        Some(AUTO_BEST) => ctx.auto_best_subs(&url).await,
+        _ => None,
+    };
+    // Captured outputs from this index on still need the deferred re-encode
+    let mut deferred_encode = None;
+    // Cookies the user named after an auth failure, kept for any later stages
+    let prompted_browser = std::cell::OnceCell::<String>::new();
+    let mut asked_for_cookies = false;
+    for (stage_index, stage) in download_plan(&ctx.options.mode, ctx.options.also_archive)
+        .into_iter()
+        .enumerate()
+    {
+        let first_stage = stage_index == 0;
+        if let DownloadMode::SocialMedia(preset) = &stage.mode
+            && ctx.options.defers_encode()
+        {
+            let from = paths_file.map_or(0, |path| read_captured_outputs(path).len());
+            deferred_encode = Some((from, preset.clone()));
+        }
+        let mut args = ctx.options.ytdlp_args(&stage, ctx.apply_rate_limit);
+        let suffix = ctx
+            .collision_suffixes
+            .get(&url)
+            .map(|index| format!("{index}{}", stage.filename_suffix.unwrap_or_default()));
+        if suffix.is_some() {
+            args.filename_suffix = suffix.as_deref();
+        }
+        args.capture_outputs_to = paths_file;
+        args.limit_rate = ctx.per_download_rate;
+        if let Some(browser) = prompted_browser.get() {
+            args.cookies_from = Some(browser);
+        }
+        if let Some(stage_dir) = &scratch.stage_dir {
+            args.destination_path = Some(stage_dir);
+        }
+        if args.subs == Some(AUTO_BEST) {
+            args.subs = best_subs.as_deref();
+        }
+        // Later stages re-fetch the same ids on purpose, so only the first one consults the archive
+        if first_stage {
+            args.download_archive = ctx.download_archive();
+        }
 
-            match exit_status {
-                Ok(status) if status.success() => {
-                    println!("{} {}", "Completed:".green(), url.green());
+        let sinks = OutputSinks {
+            progress: ctx.progress.as_ref(),
+            bar: bar.as_ref(),
+            quiet: ctx.options.quiet_on_success,
+            log_argv: ctx.options.verbose_args,
+        };
+        let mut result = run_ytdlp(ctx.ytdlp, &url, &args, sinks).await;
+        let mut attempt = 1;
+        loop {
+            let (note, backoff) = match &result {
+                Err(failure)
+                    if !args.relaxed_format
+                        && ctx.options.retries_with_lower_quality(failure.kind) =>
+                {
+                    args.relaxed_format = true;
+                    let note = format!(
+                        "{} {} - {}, retrying with any available quality",
+                        "Retrying:".warning(),
+                        url.warning(),
+                        failure.reason
+                    );
+                    (note, None)
+                }
+                Err(failure) if attempt <= ctx.options.retries && failure.kind.is_transient() => {
+                    attempt += 1;
+                    let note = format!(
+                        "{} {} - {}",
+                        format!("Retrying (attempt {attempt}/{}):", ctx.options.retries + 1)
+                            .warning(),
+                        url.warning(),
+                        failure.reason
+                    );
+                    (note, Some(retry_backoff(attempt - 1)))
                 }
-                Ok(status) => {
-                    let mut stderr_output = String::new();
-                    if let Some(mut stderr) = child.stderr.take() {
-                        let _ = stderr.read_to_string(&mut stderr_output).await;
-                    }
+                Err(failure)
+                    if !asked_for_cookies && ctx.options.prompts_for_cookies(failure.kind) =>
+                {
+                    asked_for_cookies = true;
+                    let Some(browser) = ctx.ask_cookie_browser(&url, failure.kind).await else {
+                        break;
+                    };
+                    let browser = prompted_browser.get_or_init(|| browser);
+                    args.cookies_from = Some(browser);
+                    let note = format!(
+                        "{} {} - with cookies from {browser}",
+                        "Retrying:".warning(),
+                        url.warning()
+                    );
+                    (note, None)
+                }
+                _ => break,
+            };
+            if ctx.options.prints_status() {
+                ctx.suspend_bars(|| println!("{note}"));
+            }
+            // A signal aborts the task, and this sleep with it
+            if let Some(backoff) = backoff {
+                tokio::time::sleep(backoff).await;
+            }
+            result = run_ytdlp(ctx.ytdlp, &url, &args, sinks).await;
+        }
 
-                    let reason = extract_error_reason(&stderr_output, status.code());
-                    eprintln!("{} {} - {}", "Failed:".red(), url.red(), reason.red());
+        let skip_reason = match result {
+            Ok(RunOutcome::FilteredOut) => Some(FILTERED_REASON),
+            Ok(RunOutcome::Downloaded)
+                if first_stage
+                    && ctx.download_archive().is_some()
+                    && paths_file.is_some_and(|path| read_captured_outputs(path).is_empty()) =>
+            {
+                Some(if ctx.archive.is_some() {
+                    ARCHIVED_REASON
+                } else {
+                    DOWNLOAD_ARCHIVE_REASON
+                })
+            }
+            Ok(RunOutcome::Downloaded) => continue,
+            Err(failure) => {
+                ctx.record_failure(url, failure, bar.as_ref(), &failed_downloads)
+                    .await;
+                return;
+            }
+        };
+
+        if let Some(reason) = skip_reason {
+            if let Some(bar) = &bar {
+                bar.finish_and_clear();
+            }
+            let skipped = ctx.emit(|| DownloadEvent::Skipped {
+                url: url.clone(),
+                reason: reason.to_string(),
+            });
+            if !skipped && ctx.options.prints_status() {
+                ctx.suspend_bars(|| {
+                    println!("{} {} - {}", "Skipped:".warning(), url.warning(), reason);
+                });
+            }
+            ctx.report_status(&url, ProgressStatus::Skipped);
+            ctx.filtered.lock().await.push((url, reason));
+            return;
+        }
+    }
 
-                    failed_downloads
-                        .lock()
+    let mut outputs = paths_file.map(read_captured_outputs).unwrap_or_default();
+    if let Some((from, preset)) = deferred_encode {
+        let encode = async {
+            for output in outputs.iter_mut().skip(from) {
+                if !ctx.options.force_reencode
+                    && probe_media(&output.path)
                         .await
-                        .push(FailedDownload { url, reason });
+                        .is_some_and(|probe| probe.fits_preset(&preset))
+                {
+                    // yt-dlp's remux already copied the streams into an .mp4
+                    continue;
                 }
+                output.path = reencode_download(
+                    &output.path,
+                    &preset,
+                    ctx.options.subs.is_some(),
+                    ctx.options.fit_size,
+                )
+                .await?;
+            }
+            Ok::<(), String>(())
+        };
+        let encoded = match &ctx.encode_slots {
+            Some(encode_slots) => encode_after_download(download_slot, encode_slots, encode).await,
+            None => encode.await,
+        };
+        if let Err(reason) = encoded {
+            let failure = RunFailure::other(format!("Re-encode failed: {reason}"));
+            ctx.record_failure(url, failure, bar.as_ref(), &failed_downloads)
+                .await;
+            return;
+        }
+    }
+    if ctx.options.verify {
+        for output in &outputs {
+            let probe = probe_media(&output.path).await;
+            if let Err(reason) = MediaProbe::check_playable(probe.as_ref()) {
+                let failure = RunFailure::other(format!(
+                    "Verification of {} failed: {reason}",
+                    output.path.display()
+                ));
+                ctx.record_failure(url, failure, bar.as_ref(), &failed_downloads)
+                    .await;
+                return;
+            }
+        }
+    }
+    if let (Some(stage_dir), Some(destination)) =
+        (&scratch.stage_dir, &ctx.options.destination_path)
+    {
+        for output in &mut outputs {
+            match move_to_destination(stage_dir, &output.path, destination) {
+                Ok(moved) => output.path = moved,
                 Err(e) => {
-                    let reason = format!("Process error: {e}");
-                    eprintln!("{} {} - {}", "Failed:".red(), url.red(), reason.red());
-
-                    failed_downloads
-                        .lock()
-                        .await
-                        .push(FailedDownload { url, reason });
+                    let failure = RunFailure::other(format!(
+                        "Could not move {} to {}: {e}",
+                        output.path.display(),
+                        destination.display()
+                    ));
+                    ctx.record_failure(url, failure, bar.as_ref(), &failed_downloads)
+                        .await;
+                    return;
                 }
             }
         }
-        Err(e) => {
-            let reason = format!("Failed to spawn yt-dlp: {e}");
-            eprintln!("{} {} - {}", "Failed:".red(), url.red(), reason.red());
+    }
+    if ctx.budget.is_some() || ctx.auto_parallel.is_some() {
+        let bytes = total_output_size(&outputs);
+        if let Some(budget) = &ctx.budget {
+            budget.record(bytes);
+        }
+        ctx.rescale(bytes, false).await;
+    }
+    #[cfg(feature = "perceptual-dedupe")]
+    if ctx.options.perceptual_dedupe {
+        let mut files = ctx.output_files.lock().await;
+        files.extend(outputs.iter().map(|output| output.path.clone()));
+    }
+    if ctx.options.manifest.is_some() {
+        ctx.manifest_outputs
+            .lock()
+            .await
+            .push((url.clone(), outputs.clone()));
+    }
+    if let Some(archive) = &ctx.archive {
+        let mode = ctx.options.mode.to_string();
+        for output in outputs {
+            if let Err(e) = archive.record(ArchiveEntry::new(&url, output, mode.clone())) {
+                ctx.suspend_bars(|| {
+                    eprintln!(
+                        "{} Could not update JSON archive: {e}",
+                        "Warning:".warning()
+                    );
+                });
+            }
+        }
+    }
 
-            failed_downloads
-                .lock()
-                .await
-                .push(FailedDownload { url, reason });
+    let completed = ctx.emit(|| DownloadEvent::Completed { url: url.clone() });
+    match &bar {
+        Some(bar) => bar.finish_with_message("done"),
+        None if completed || !ctx.options.prints_status() => {}
+        None => println!("{} {}", "Completed:".success(), url.success()),
+    }
+    ctx.report_status(&url, ProgressStatus::Completed);
+    ctx.completed.lock().await.push(url);
+}
+
+impl DownloadContext {
+    async fn record_failure(
+        &self,
+        url: String,
+        failure: RunFailure,
+        bar: Option<&ProgressBar>,
+        failed_downloads: &Mutex<Vec<FailedDownload>>,
+    ) {
+        if let Some(bar) = bar {
+            bar.abandon_with_message("failed");
+        }
+        let reported = self.emit(|| DownloadEvent::Failed {
+            url: url.clone(),
+            code: failure.kind,
+            reason: failure.reason.clone(),
+        });
+        // The summary at the end carries the same reason
+        if !reported && !self.options.fail_summary_only {
+            self.suspend_bars(|| {
+                eprint!(
+                    "{}",
+                    render_failure(&url, &failure, self.options.quiet_on_success)
+                );
+            });
+        }
+        self.report_status(&url, ProgressStatus::Failed);
+        self.rescale(0, true).await;
+        if self.options.aborts_batch(failure.kind) {
+            self.abort.trip(AbortCause::AuthFailure {
+                url: url.clone(),
+                reason: failure.reason.clone(),
+            });
+        }
+        if let Some(count) = self.breaker.record() {
+            self.abort.trip(AbortCause::TooManyFailures(count));
+        }
+
+        let detail = failure.detail();
+        failed_downloads.lock().await.push(FailedDownload {
+            url,
+            reason: failure.reason,
+            detail,
+        });
+    }
+}
+
+/// The browser to retry an auth failure with, if the user is asked and names one
+fn cookie_retry_browser(
+    options: &DownloadOptions,
+    kind: ErrorKind,
+    ask: impl FnOnce() -> std::io::Result<Option<String>>,
+) -> Option<String> {
+    if !options.prompts_for_cookies(kind) {
+        return None;
+    }
+    // A prompt that can't be read is the same as skipping it
+    ask().ok().flatten()
+}
+
+/// The failure line, followed by yt-dlp's full stderr when its output was held back
+fn render_failure(url: &str, failure: &RunFailure, quiet: bool) -> String {
+    let mut output = format!(
+        "{} {} - {}\n",
+        "Failed:".error(),
+        url.error(),
+        failure.reason.error()
+    );
+    if quiet {
+        for line in failure
+            .stderr
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+        {
+            output.push_str(&format!("  {}\n", line.dimmed()));
+        }
+    }
+    output
+}
+
+/// An even share of the total rate for each of the `active` concurrent downloads
+fn per_download_rate(total: u64, active: usize) -> u64 {
+    let active = u64::try_from(active.max(1)).unwrap_or(u64::MAX);
+    (total / active).max(1)
+}
+
+/// Wait before the given retry: 2s, 4s, 8s, ...
+fn retry_backoff(retry: usize) -> Duration {
+    let doublings = u32::try_from(retry.saturating_sub(1))
+        .unwrap_or(u32::MAX)
+        .min(6);
+    Duration::from_secs(RETRY_BACKOFF_SECONDS << doublings)
+}
+
+/// Frees the download slot before waiting on an encode slot, so queued downloads keep
+/// going while this one waits for ffmpeg
+async fn encode_after_download<F: Future>(
+    download_slot: OwnedSemaphorePermit,
+    encode_slots: &Semaphore,
+    encode: F,
+) -> F::Output {
+    drop(download_slot);
+    let _encode_slot = encode_slots.acquire().await;
+    encode.await
+}
+
+/// Per-URL temp files, cleaned up however the task ends
+struct TaskScratch {
+    paths_file: Option<PathBuf>,
+    stage_dir: Option<PathBuf>,
+}
+
+impl Drop for TaskScratch {
+    fn drop(&mut self) {
+        remove_paths_file(self.paths_file.as_deref());
+        if let Some(dir) = &self.stage_dir {
+            let _ = std::fs::remove_dir_all(dir);
         }
     }
 }
 
+fn remove_paths_file(path: Option<&Path>) {
+    if let Some(path) = path {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Downloads one URL as a batch of one, with the same validation and summary
+pub async fn download_single(url: String, options: DownloadOptions) -> Result<()> {
+    download_batch(vec![url], options).await
+}
+
+pub async fn download_batch(urls: Vec<String>, options: DownloadOptions) -> Result<()> {
+    download_batch_with_events(urls, options, None).await
+}
+
+/// [`download_batch`], reporting each URL's progress to `on_event` instead of printing
+/// status lines; the final summary is still printed
+pub async fn download_batch_with_events(
+    urls: Vec<String>,
+    options: DownloadOptions,
+    on_event: Option<EventCallback>,
+) -> Result<()> {
+    run_batch("yt-dlp", urls, options, on_event).await
+}
+
 #[allow(clippy::significant_drop_tightening)]
-pub async fn download_batch(
+async fn run_batch(
+    ytdlp: &'static str,
     urls: Vec<String>,
-    destination_path: Option<&Path>,
-    cookies_from: Option<&str>,
-    mode: DownloadMode,
-    parallel: NonZeroUsize,
+    options: DownloadOptions,
+    on_event: Option<EventCallback>,
 ) -> Result<()> {
     let original_count = urls.len();
-    let clean_urls = sanitize_and_deduplicate(urls);
+    let sanitized = sanitize_and_deduplicate(urls, options.dedupe);
+    for invalid in sanitized.invalid() {
+        eprintln!(
+            "{} {}",
+            "Warning: Skipping invalid URL:".warning(),
+            invalid.warning()
+        );
+    }
+    let clean_urls = sanitized.kept;
 
     if clean_urls.is_empty() {
         return Err(YtrsError::NoValidUrls);
     }
 
     let url_count = clean_urls.len();
+    if options.dry_run {
+        for command in dry_run_commands(&clean_urls, &options) {
+            println!("{command}");
+        }
+        return Ok(());
+    }
 
-    if url_count != original_count {
+    let chatty = options.prints_status();
+    if url_count != original_count && chatty {
         println!(
             "Processing {} valid URLs (filtered from {})",
-            url_count.to_string().cyan(),
-            original_count.to_string().cyan()
+            url_count.to_string().info(),
+            original_count.to_string().info()
         );
     }
 
     let apply_rate_limit = url_count > BATCH_SLEEP_THRESHOLD;
-    if apply_rate_limit {
+    if apply_rate_limit && chatty {
         println!(
             "{} Large batch detected (>{} URLs). Adding sleep intervals to prevent rate limiting.",
-            "Note:".yellow(),
+            "Note:".warning(),
             BATCH_SLEEP_THRESHOLD
         );
     }
 
+    for path in [&options.report, &options.failed_log, &options.archive]
+        .into_iter()
+        .flatten()
+    {
+        validate_writable(path)?;
+    }
+    let archive = match &options.json_archive {
+        Some(path) => {
+            validate_writable(path)?;
+            Some(JsonArchive::load(path)?)
+        }
+        None => None,
+    };
+    let ytdlp_archive = match &archive {
+        Some(archive) if !options.force => {
+            let path = ytdlp_archive_file();
+            std::fs::write(&path, archive.ytdlp_archive())?;
+            Some(path)
+        }
+        _ => None,
+    };
+    if options.staging.is_some()
+        && let Some(destination) = &options.destination_path
+    {
+        std::fs::create_dir_all(destination)?;
+    }
+    let planned_urls = clean_urls.clone();
+
+    let collisions = match (
+        file_destination_clash(&options, &clean_urls)?,
+        options.on_collision,
+    ) {
+        (Some(clash), _) => CollisionPlan::new(&[], &[clash], OnCollision::Index),
+        (None, Some(policy)) => check_collisions(&clean_urls, &options, policy).await,
+        (None, None) => CollisionPlan::default(),
+    };
+
+    let semaphore = Arc::new(Semaphore::new(options.parallel.get()));
     let ctx = Arc::new(DownloadContext {
-        destination_path: destination_path.map(Arc::from),
-        cookies_from: cookies_from.map(Arc::from),
-        mode,
+        download_slots: semaphore.clone(),
+        auto_parallel: options
+            .auto_parallel
+            .then(|| Mutex::new(AutoParallel::new(options.parallel.get(), AUTO_PARALLEL_MAX))),
+        retiring_slots: AtomicUsize::new(0),
+        progress: options.progress_tracker()?,
+        budget: options.max_total_size.map(SizeBudget::new),
+        completed: Mutex::new(Vec::new()),
+        #[cfg(feature = "perceptual-dedupe")]
+        output_files: Mutex::new(Vec::new()),
+        filtered: Mutex::new(Vec::new()),
+        manifest_outputs: Mutex::new(Vec::new()),
+        archive,
+        ytdlp_archive,
+        collision_suffixes: collisions.suffixes,
+        task_times: Mutex::new(Vec::new()),
+        bars: (options.progress_style != ProgressDisplay::None).then(MultiProgress::new),
+        encode_slots: options
+            .max_concurrent_ffmpeg
+            .map(|slots| Semaphore::new(slots.get())),
+        per_download_rate: options
+            .total_rate
+            .map(|total| per_download_rate(total, options.parallel.get().min(url_count))),
+        cookie_prompt: Mutex::new(()),
+        on_event,
+        ytdlp,
+        abort: AbortSwitch::default(),
+        breaker: FailureBreaker::new(options.max_failures),
+        options,
         apply_rate_limit,
     });
 
-    let semaphore = Arc::new(Semaphore::new(parallel.get()));
     let failed_downloads = Arc::new(Mutex::new(Vec::new()));
     let mut join_set = JoinSet::new();
 
@@ -195,75 +1466,1125 @@ pub async fn download_batch(
     let signals_handle = signals.handle();
     let mut signals_stream = signals.fuse();
 
+    let mut launch_gate = LaunchGate::new(ctx.options.cooldown);
+    let batch_started = Instant::now();
+
     let download_future = async {
-        for url in clean_urls {
+        let mut skipped = 0;
+        for (index, url) in clean_urls.into_iter().enumerate() {
+            if collisions.skip.contains(&url) {
+                let skipped = ctx.emit(|| DownloadEvent::Skipped {
+                    url: url.clone(),
+                    reason: COLLISION_REASON.to_string(),
+                });
+                if !skipped && chatty {
+                    println!(
+                        "{} {} - {}",
+                        "Skipped:".warning(),
+                        url.warning(),
+                        COLLISION_REASON
+                    );
+                }
+                ctx.filtered.lock().await.push((url, COLLISION_REASON));
+                continue;
+            }
+            while ctx.retiring_slots.load(Ordering::Relaxed) > 0 {
+                semaphore
+                    .acquire()
+                    .await
+                    .map_err(|_| YtrsError::SemaphoreClosed)?
+                    .forget();
+                ctx.retiring_slots.fetch_sub(1, Ordering::Relaxed);
+            }
             let permit = semaphore
                 .clone()
                 .acquire_owned()
                 .await
                 .map_err(|_| YtrsError::SemaphoreClosed)?;
+            if ctx.budget.as_ref().is_some_and(SizeBudget::is_exhausted) {
+                skipped = url_count - index;
+                break;
+            }
+            launch_gate.wait().await;
 
             let ctx_clone = ctx.clone();
             let failed_downloads_clone = failed_downloads.clone();
 
             join_set.spawn(async move {
-                download_url_task(url, ctx_clone, failed_downloads_clone).await;
-                drop(permit);
+                let started = Instant::now();
+                download_url_task(
+                    index,
+                    url,
+                    ctx_clone.clone(),
+                    failed_downloads_clone,
+                    permit,
+                )
+                .await;
+                ctx_clone.task_times.lock().await.push(started.elapsed());
             });
         }
 
         // Wait for all tasks to complete
         while join_set.join_next().await.is_some() {}
-        Ok::<(), YtrsError>(())
+        Ok::<usize, YtrsError>(skipped)
     };
 
     // Race between downloads and signals
+    let mut skipped = 0;
     tokio::select! {
-        result = download_future => result?,
+        result = download_future => skipped = result?,
         signal = signals_stream.next() => {
             if signal.is_some() {
                 eprintln!(
                     "\n{} {}",
-                    "Received termination signal.".yellow(),
-                    "Waiting for active downloads to complete...".yellow()
+                    "Received termination signal.".warning(),
+                    "Waiting for active downloads to complete...".warning()
                 );
                 join_set.shutdown().await;
             }
         }
+        () = out_of_time(ctx.options.max_runtime, &ctx.abort) => {
+            if let Some(cause) = ctx.abort.cause() {
+                cause.announce();
+            }
+            join_set.shutdown().await;
+        }
+        () = ctx.abort.tripped() => {
+            if let Some(cause) = ctx.abort.cause() {
+                cause.announce();
+            }
+            join_set.shutdown().await;
+        }
     }
 
     signals_handle.close();
+    remove_paths_file(ctx.ytdlp_archive.as_deref());
+
+    if skipped > 0
+        && chatty
+        && let Some(budget) = &ctx.budget
+    {
+        println!(
+            "{} Size budget reached ({} of {}). Skipped {} remaining URLs.",
+            "Note:".warning(),
+            format_bytes(budget.used()),
+            format_bytes(budget.limit),
+            skipped
+        );
+    }
 
     let failed = failed_downloads.lock().await;
-    if !failed.is_empty() {
-        println!("\n{}", "─".repeat(50));
-        println!("{}", "DOWNLOAD SUMMARY".bold());
-        println!("{}", "─".repeat(50));
 
+    let filtered = ctx.filtered.lock().await;
+    if !filtered.is_empty() && chatty {
+        println!(
+            "{} Skipped {} URLs without downloading them.",
+            "Note:".warning(),
+            filtered.len()
+        );
+    }
+
+    if ctx.options.report.is_some()
+        || ctx.options.manifest.is_some()
+        || ctx.options.webhook.is_some()
+    {
+        let completed = ctx.completed.lock().await;
+        let report = build_report(&planned_urls, &completed, &filtered, &failed);
+        if let Some(path) = &ctx.options.report
+            && let Err(e) = report.save(path)
+        {
+            eprintln!(
+                "{} Could not write report to {}: {e}",
+                "Warning:".warning(),
+                path.display()
+            );
+        }
+        if let Some(path) = &ctx.options.manifest
+            && let Err(e) = write_manifest(path, &report, &ctx.manifest_outputs.lock().await)
+        {
+            eprintln!(
+                "{} Could not write manifest to {}: {e}",
+                "Warning:".warning(),
+                path.display()
+            );
+        }
+        #[cfg(feature = "webhook")]
+        if let Some(url) = &ctx.options.webhook {
+            crate::webhook::send_webhook(url, &(&report).into()).await;
+        }
+    }
+
+    if ctx.options.json {
+        let summary = BatchSummary {
+            total: url_count,
+            succeeded: ctx.completed.lock().await.len(),
+            failed: failed.iter().map(|fail| fail.url.clone()).collect(),
+        };
+        println!("{}", serde_json::to_string(&summary)?);
+    } else if let Some(summary) = final_summary(
+        url_count - skipped - filtered.len(),
+        &failed,
+        !ctx.options.prints_status(),
+    ) {
+        print!("{summary}");
+    }
+
+    #[cfg(feature = "perceptual-dedupe")]
+    if ctx.options.perceptual_dedupe && chatty {
+        crate::phash::report_near_duplicates(&ctx.output_files.lock().await).await;
+    }
+
+    if ctx.options.concurrency_report {
+        let report = ConcurrencyReport::new(
+            &ctx.task_times.lock().await,
+            ctx.options.parallel.get(),
+            batch_started.elapsed(),
+        );
+        print!("{}", report.render());
+    }
+
+    if let Some(path) = &ctx.options.failed_log
+        && !failed.is_empty()
+    {
+        write_failed_log(path, &failed)?;
+    }
+
+    if let Some(cause) = ctx.abort.cause() {
+        return Err(cause.into_error());
+    }
+
+    let completed = ctx.completed.lock().await.len();
+    let accepted = ctx.options.accepts_partial(completed, failed.len());
+    batch_result(&failed, url_count, accepted)
+}
+
+/// The yt-dlp command line for every stage of every URL, as a batch would run them
+fn dry_run_commands(urls: &[String], options: &DownloadOptions) -> Vec<String> {
+    let apply_rate_limit = urls.len() > BATCH_SLEEP_THRESHOLD;
+    let plan = download_plan(&options.mode, options.also_archive);
+    let mut commands = Vec::new();
+    for url in urls {
+        for (stage_index, stage) in plan.iter().enumerate() {
+            let mut args = options.ytdlp_args(stage, apply_rate_limit);
+            if stage_index == 0 && !options.force {
+                args.download_archive = options.archive.as_deref();
+            }
+            commands.push(render_command("yt-dlp", &build_ytdlp_args(url, &args)));
+        }
+    }
+    commands
+}
+
+/// One URL per line, the format `ytrs retry` reads back
+fn write_failed_log(path: &Path, failed: &[FailedDownload]) -> Result<()> {
+    let contents: String = failed.iter().map(|f| format!("{}\n", f.url)).collect();
+    std::fs::write(path, contents).map_err(|source| YtrsError::FailedLogWrite {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// The batch's exit status once the summary is out; failures fail the run unless
+/// `--exit-zero-on-partial` or `--success-threshold` says the batch is good enough
+fn batch_result(failed: &[FailedDownload], url_count: usize, exit_zero: bool) -> Result<()> {
+    match failed {
+        [] => Ok(()),
+        _ if exit_zero => Ok(()),
+        [only] if url_count == 1 => Err(YtrsError::DownloadFailed {
+            url: only.url.clone(),
+            reason: only.reason.clone(),
+        }),
+        _ => Err(YtrsError::PartialFailure(failed.len())),
+    }
+}
+
+/// A file `-d` shared by several URLs is a clash between all of them; `None` if there
+/// is nothing to clash
+fn file_destination_clash(options: &DownloadOptions, urls: &[String]) -> Result<Option<Collision>> {
+    let Some(destination) = &options.destination_path else {
+        return Ok(None);
+    };
+    if urls.len() < 2 || !writes_to_file(destination) {
+        return Ok(None);
+    }
+    match options.on_file_dest_batch {
+        FileDestBatch::Error => Err(YtrsError::FileDestinationBatch(destination.clone())),
+        FileDestBatch::Index => Ok(Some(Collision {
+            filename: destination.display().to_string(),
+            urls: urls.to_vec(),
+        })),
+    }
+}
+
+/// Resolves every URL's output filenames, warns about clashes and plans around them
+async fn check_collisions(
+    urls: &[String],
+    options: &DownloadOptions,
+    policy: OnCollision,
+) -> CollisionPlan {
+    if options.prints_status() {
         println!(
-            "{} {}/{} downloads failed",
-            "Error:".red().bold(),
-            failed.len().to_string().red(),
-            url_count.to_string().white()
+            "{} output filenames for {} URLs...",
+            "Resolving".dimmed(),
+            urls.len().to_string().info()
         );
+    }
+    let plan = download_plan(&options.mode, options.also_archive);
+    let args = options.ytdlp_args(&plan[0], false);
+    let prefetched: Vec<(String, Vec<String>)> = futures::stream::iter(urls)
+        .map(|url| async { (url.clone(), prefetch_filenames(url, &args).await) })
+        .buffered(options.parallel.get())
+        .collect()
+        .await;
 
-        println!("\n{}", "Failed downloads:".red().bold());
-        for fail in failed.iter() {
-            println!("  {} {}", "•".red(), fail.url.red());
-            println!("    {} {}", "Reason:".dimmed(), fail.reason.dimmed());
+    let collisions = find_collisions(&prefetched);
+    for collision in &collisions {
+        eprintln!(
+            "{} {} URLs would write {}:",
+            "Warning:".warning(),
+            collision.urls.len(),
+            collision.filename.warning()
+        );
+        for url in &collision.urls {
+            eprintln!("  {url}");
         }
+    }
+    CollisionPlan::new(&prefetched, &collisions, policy)
+}
 
-        return Err(YtrsError::PartialFailure(failed.len()));
+/// Outcome of each planned URL in order; anything neither completed nor failed never ran
+fn build_report(
+    urls: &[String],
+    completed: &[String],
+    filtered: &[(String, &'static str)],
+    failed: &[FailedDownload],
+) -> DownloadReport {
+    let entries = urls
+        .iter()
+        .map(|url| {
+            let (status, reason) = if let Some(fail) = failed.iter().find(|f| &f.url == url) {
+                (ReportStatus::Failed, Some(fail.reason.clone()))
+            } else if let Some((_, reason)) = filtered.iter().find(|(u, _)| u == url) {
+                (ReportStatus::Skipped, Some((*reason).to_string()))
+            } else if completed.contains(url) {
+                (ReportStatus::Completed, None)
+            } else {
+                (ReportStatus::Skipped, None)
+            };
+            ReportEntry {
+                url: url.clone(),
+                status,
+                reason,
+            }
+        })
+        .collect();
+    DownloadReport { entries }
+}
+
+/// Ordered table of what a batch will do; slots are assigned round-robin, so they
+/// show the initial spread rather than which worker ends up taking a URL
+#[must_use]
+pub fn render_batch_plan(urls: &[String], options: &DownloadOptions) -> String {
+    let modes: Vec<String> = download_plan(&options.mode, options.also_archive)
+        .iter()
+        .map(|stage| stage.mode.to_string())
+        .collect();
+    let modes = modes.join(" + ");
+
+    let mut destination = options
+        .destination_path
+        .as_ref()
+        .map_or_else(|| ".".to_string(), |path| path.display().to_string());
+    if options.by_uploader {
+        destination.push_str("/<uploader>");
     }
 
-    println!("\n{}", "─".repeat(50));
-    println!("{}", "DOWNLOAD SUMMARY".bold());
-    println!("{}", "─".repeat(50));
-    println!(
-        "{} All {} downloads completed successfully.",
-        "Success:".green().bold(),
-        url_count
+    let parallel = options.parallel.get();
+    let mut plan = format!(
+        "{}\n",
+        format!(
+            "  {:>4}  {:<4}  {:<24}  {:<20}  URL",
+            "#", "Slot", "Mode", "Destination"
+        )
+        .dimmed()
     );
+    for (index, url) in urls.iter().enumerate() {
+        plan.push_str(&format!(
+            "  {:>4}  {:<4}  {modes:<24}  {destination:<20}  {url}\n",
+            index + 1,
+            index % parallel + 1,
+        ));
+    }
+    plan.push_str(&format!(
+        "{} URLs, up to {parallel} at a time\n",
+        urls.len()
+    ));
+    plan
+}
 
-    Ok(())
+/// The end-of-batch summary, or nothing for a clean run under `--fail-summary-only`
+fn final_summary(total: usize, failed: &[FailedDownload], failures_only: bool) -> Option<String> {
+    (!failures_only || !failed.is_empty()).then(|| render_summary(total, failed))
+}
+
+fn render_summary(total: usize, failed: &[FailedDownload]) -> String {
+    let rule = "─".repeat(50);
+    let mut summary = format!("\n{rule}\n{}\n{rule}\n", "DOWNLOAD SUMMARY".bold());
+
+    if failed.is_empty() {
+        let message = if total == 1 {
+            "Download completed successfully.".to_string()
+        } else {
+            format!("All {total} downloads completed successfully.")
+        };
+        summary.push_str(&format!("{} {message}\n", "Success:".success().bold()));
+        return summary;
+    }
+
+    summary.push_str(&format!(
+        "{} {}/{} downloads failed\n",
+        "Error:".error().bold(),
+        failed.len().to_string().error(),
+        total
+    ));
+
+    summary.push_str(&format!("\n{}\n", "Failed downloads:".error().bold()));
+    for fail in failed {
+        summary.push_str(&format!("  {} {}\n", "•".error(), fail.url.error()));
+        summary.push_str(&format!(
+            "    {} {}\n",
+            "Reason:".dimmed(),
+            fail.reason.dimmed()
+        ));
+        if let Some(detail) = &fail.detail {
+            for line in detail.lines() {
+                summary.push_str(&format!("      {}\n", line.dimmed()));
+            }
+        }
+    }
+
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::SocialMediaTarget;
+
+    /// Fails every download at spawn, the same way on every machine
+    const MISSING_YTDLP: &str = "ytrs-test-missing-yt-dlp";
+
+    #[test]
+    fn test_download_plan_single_stage() {
+        let plan = download_plan(&DownloadMode::Default, false);
+        assert_eq!(
+            plan,
+            [DownloadStage {
+                mode: DownloadMode::Default,
+                filename_suffix: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_download_plan_also_archive() {
+        let socm = DownloadMode::SocialMedia(SocialMediaTarget::Discord.preset());
+        let plan = download_plan(&socm, true);
+
+        assert_eq!(plan.len(), 2);
+        assert_eq!(plan[0].mode, DownloadMode::Default);
+        assert_eq!(plan[0].filename_suffix, None);
+        assert_eq!(plan[1].mode, socm);
+        assert_eq!(plan[1].filename_suffix, Some(SHARE_COPY_SUFFIX));
+    }
+
+    #[test]
+    fn test_download_plan_also_archive_args() {
+        let socm = DownloadMode::SocialMedia(SocialMediaTarget::WhatsApp.preset());
+        let plan = download_plan(&socm, true);
+        // Without it yt-dlp only remuxes and ytrs decides on the re-encode afterwards
+        let options = DownloadOptions {
+            force_reencode: true,
+            ..DownloadOptions::default()
+        };
+        let built: Vec<Vec<String>> = plan
+            .iter()
+            .map(|stage| {
+                let args = options.ytdlp_args(stage, false);
+                build_ytdlp_args("https://example.com", &args)
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect()
+            })
+            .collect();
+
+        assert!(!built[0].iter().any(|s| s.contains("libx264")));
+        assert!(built[1].iter().any(|s| s.contains("libx264")));
+        assert!(built[1].iter().any(|s| s.contains(SHARE_COPY_SUFFIX)));
+
+        let deferred = DownloadOptions::default();
+        let args = deferred.ytdlp_args(&plan[1], false);
+        assert!(
+            !build_ytdlp_args("https://example.com", &args)
+                .iter()
+                .any(|s| s.contains("libx264"))
+        );
+    }
+
+    #[test]
+    fn test_required_dependencies_follow_options() {
+        assert_eq!(
+            DownloadOptions::default().required_dependencies(),
+            REQUIRED_DEPENDENCIES
+        );
+        let native = DownloadOptions {
+            no_aria2c: true,
+            verify: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            native.required_dependencies(),
+            ["yt-dlp", "ffmpeg", "ffprobe"]
+        );
+    }
+
+    #[test]
+    fn test_download_plan_ignores_archive_outside_socm() {
+        assert_eq!(download_plan(&DownloadMode::AudioOnly, true).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_launch_gate_spaces_launches() {
+        let interval = Duration::from_millis(40);
+        let mut gate = LaunchGate::new(Some(interval));
+        let start = Instant::now();
+
+        gate.wait().await;
+        assert!(start.elapsed() < interval);
+
+        gate.wait().await;
+        gate.wait().await;
+        assert!(start.elapsed() >= interval * 2);
+    }
+
+    /// How many mock runners are in a stage right now, and the most there ever were
+    #[derive(Default)]
+    struct Gauge {
+        active: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    impl Gauge {
+        fn enter(&self) {
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(active, Ordering::SeqCst);
+        }
+
+        fn leave(&self) {
+            self.active.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_encodes_have_their_own_slots() {
+        let step = Duration::from_millis(50);
+        let downloads = Arc::new(Semaphore::new(3));
+        let encodes = Arc::new(Semaphore::new(1));
+        let downloading = Arc::new(Gauge::default());
+        let encoding = Arc::new(Gauge::default());
+        let start = Instant::now();
+
+        let mut tasks = JoinSet::new();
+        for _ in 0..6 {
+            let download_slot = downloads.clone().acquire_owned().await.unwrap();
+            let (encodes, downloading, encoding) =
+                (encodes.clone(), downloading.clone(), encoding.clone());
+            tasks.spawn(async move {
+                downloading.enter();
+                tokio::time::sleep(step).await;
+                downloading.leave();
+                let downloaded = start.elapsed();
+
+                encode_after_download(download_slot, &encodes, async {
+                    encoding.enter();
+                    tokio::time::sleep(step).await;
+                    encoding.leave();
+                })
+                .await;
+                downloaded
+            });
+        }
+        let finished = tasks.join_all().await;
+
+        assert_eq!(downloading.peak.load(Ordering::SeqCst), 3);
+        assert_eq!(encoding.peak.load(Ordering::SeqCst), 1);
+        // Two waves of downloads; holding download slots through the encode queue would
+        // push the second wave behind three encodes
+        assert!(finished.into_iter().max().unwrap() < step * 7 / 2);
+    }
+
+    #[test]
+    fn test_per_download_rate() {
+        let mib = 1024 * 1024;
+        assert_eq!(per_download_rate(4 * mib, 4), mib);
+        assert_eq!(per_download_rate(3 * mib, 2), 3 * mib / 2);
+        assert_eq!(per_download_rate(mib, 1), mib);
+        assert_eq!(per_download_rate(3, 8), 1);
+    }
+
+    #[test]
+    fn test_retry_backoff_doubles() {
+        assert_eq!(retry_backoff(1), Duration::from_secs(2));
+        assert_eq!(retry_backoff(2), Duration::from_secs(4));
+        assert_eq!(retry_backoff(3), Duration::from_secs(8));
+        assert_eq!(retry_backoff(50), Duration::from_secs(128));
+    }
+
+    #[tokio::test]
+    async fn test_events_replace_status_lines() {
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let on_event: EventCallback = Arc::new(move |event| sink.lock().unwrap().push(event));
+        let options = DownloadOptions {
+            parallel: NonZeroUsize::MIN,
+            ..DownloadOptions::default()
+        };
+        let urls = vec![
+            "https://first.invalid/video".to_string(),
+            "https://second.invalid/video".to_string(),
+        ];
+
+        let result = run_batch(MISSING_YTDLP, urls, options, Some(on_event)).await;
+        let events = events.lock().unwrap();
+
+        assert!(matches!(result, Err(YtrsError::PartialFailure(2))));
+        let order: Vec<(&str, &str)> = events
+            .iter()
+            .map(|event| match event {
+                DownloadEvent::Started { url } => ("started", url.as_str()),
+                DownloadEvent::Completed { url } => ("completed", url.as_str()),
+                DownloadEvent::Skipped { url, .. } => ("skipped", url.as_str()),
+                DownloadEvent::Failed { url, .. } => ("failed", url.as_str()),
+            })
+            .collect();
+        assert_eq!(
+            order,
+            [
+                ("started", "https://first.invalid/video"),
+                ("failed", "https://first.invalid/video"),
+                ("started", "https://second.invalid/video"),
+                ("failed", "https://second.invalid/video"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_spawns_nothing() {
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = events.clone();
+        let options = DownloadOptions {
+            dry_run: true,
+            ..DownloadOptions::default()
+        };
+
+        // A real run emits Started before spawning yt-dlp, then fails on .invalid
+        let result = download_batch_with_events(
+            vec!["https://first.invalid/video".to_string()],
+            options,
+            Some(Arc::new(move |event| seen.lock().unwrap().push(event))),
+        )
+        .await;
+
+        assert!(result.is_ok());
+        assert!(events.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_dry_run_commands() {
+        let options = DownloadOptions {
+            mode: DownloadMode::SocialMedia(SocialMediaTarget::Discord.preset()),
+            also_archive: true,
+            archive: Some(PathBuf::from("/srv/archive.txt")),
+            ..DownloadOptions::default()
+        };
+        let commands = dry_run_commands(&["https://a.example/1".to_string()], &options);
+
+        assert_eq!(commands.len(), 2);
+        assert!(commands.iter().all(|c| c.starts_with("yt-dlp ")));
+        assert!(commands.iter().all(|c| c.ends_with(" https://a.example/1")));
+        assert!(commands[0].contains("--download-archive /srv/archive.txt"));
+        assert!(!commands[1].contains("--download-archive"));
+
+        let forced = DownloadOptions {
+            force: true,
+            ..options
+        };
+        let commands = dry_run_commands(&["https://a.example/1".to_string()], &forced);
+        assert!(commands.iter().all(|c| !c.contains("--download-archive")));
+    }
+
+    #[test]
+    fn test_failed_log_lists_failed_urls() {
+        let dir = std::env::temp_dir().join(format!("ytrs-failed-log-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log_path = dir.join("failed.txt");
+        let urls = ["https://a.example/1", "https://a.example/2"];
+        let failed = urls.map(|url| FailedDownload {
+            url: url.to_string(),
+            reason: "Video unavailable".to_string(),
+            detail: None,
+        });
+
+        let written = write_failed_log(&log_path, &failed);
+        let logged = crate::url_validator::load_url_list(&log_path);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(written.is_ok());
+        assert_eq!(logged.unwrap(), urls);
+    }
+
+    #[test]
+    fn test_failed_log_write_error_is_distinct() {
+        let failed = [FailedDownload {
+            url: "https://a.example/1".to_string(),
+            reason: "Video unavailable".to_string(),
+            detail: None,
+        }];
+        let missing = std::env::temp_dir().join("ytrs-no-such-dir/failed.txt");
+
+        assert!(matches!(
+            write_failed_log(&missing, &failed),
+            Err(YtrsError::FailedLogWrite { path, .. }) if path == missing
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_max_runtime_keeps_a_partial_report() {
+        let dir = std::env::temp_dir().join(format!("ytrs-max-runtime-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let report_path = dir.join("report.json");
+        let options = DownloadOptions {
+            max_runtime: Some(Duration::from_millis(200)),
+            // Holds the second URL back well past the limit
+            cooldown: Some(Duration::from_secs(60)),
+            report: Some(report_path.clone()),
+            ..DownloadOptions::default()
+        };
+        let urls = vec![
+            "https://first.invalid/video".to_string(),
+            "https://second.invalid/video".to_string(),
+        ];
+
+        let result = run_batch(MISSING_YTDLP, urls, options, None).await;
+        let report = DownloadReport::load(&report_path);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(matches!(result, Err(YtrsError::RuntimeExceeded(_))));
+        let statuses: Vec<_> = report
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|entry| entry.status)
+            .collect();
+        assert_eq!(statuses, [ReportStatus::Failed, ReportStatus::Skipped]);
+    }
+
+    #[tokio::test]
+    async fn test_launch_gate_without_cooldown() {
+        let mut gate = LaunchGate::new(None);
+        let start = Instant::now();
+        for _ in 0..5 {
+            gate.wait().await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(40));
+    }
+
+    #[test]
+    fn test_size_budget_stops_at_limit() {
+        let budget = SizeBudget::new(100);
+        assert!(!budget.is_exhausted());
+
+        budget.record(60);
+        assert!(!budget.is_exhausted());
+
+        budget.record(40);
+        assert!(budget.is_exhausted());
+        assert_eq!(budget.used(), 100);
+    }
+
+    #[test]
+    fn test_total_output_size_from_reported_paths() {
+        let dir = std::env::temp_dir();
+        let first = dir.join(format!("ytrs-budget-{}-a.bin", std::process::id()));
+        let second = dir.join(format!("ytrs-budget-{}-b.bin", std::process::id()));
+        std::fs::write(&first, [0u8; 300]).unwrap();
+        std::fs::write(&second, [0u8; 200]).unwrap();
+
+        let captured = format!(
+            "Youtube\ta\tA\t{}\nYoutube\tb\tB\t{}\nYoutube\tc\tC\t/tmp/ytrs-budget-missing.bin\n",
+            first.display(),
+            second.display()
+        );
+        let total = total_output_size(&parse_captured_outputs(&captured));
+        let _ = std::fs::remove_file(&first);
+        let _ = std::fs::remove_file(&second);
+
+        assert_eq!(total, 500);
+    }
+
+    #[tokio::test]
+    async fn test_auth_failure_trips_abort() {
+        let options = DownloadOptions {
+            abort_on_auth_error: true,
+            ..DownloadOptions::default()
+        };
+        let switch = AbortSwitch::default();
+
+        let kind = classify_error("ERROR: [youtube] abc: Sign in to confirm you're not a bot");
+        if options.aborts_batch(kind) {
+            switch.trip(AbortCause::AuthFailure {
+                url: "https://a.example/1".to_string(),
+                reason: "Bot check - requires account cookies".to_string(),
+            });
+        }
+        // A later abort doesn't replace the original cause
+        switch.trip(AbortCause::TooManyFailures(3));
+
+        tokio::time::timeout(Duration::from_secs(1), switch.tripped())
+            .await
+            .expect("abort should have been signalled");
+        assert!(matches!(
+            switch.cause(),
+            Some(AbortCause::AuthFailure { ref url, .. }) if url == "https://a.example/1"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_failure_breaker_trips_at_limit() {
+        let breaker = Arc::new(FailureBreaker::new(NonZeroUsize::new(3)));
+        let switch = Arc::new(AbortSwitch::default());
+
+        // Mock runners that all fail, reporting concurrently
+        let mut tasks = JoinSet::new();
+        for _ in 0..5 {
+            let breaker = breaker.clone();
+            let switch = switch.clone();
+            tasks.spawn(async move {
+                if let Some(count) = breaker.record() {
+                    switch.trip(AbortCause::TooManyFailures(count));
+                }
+            });
+        }
+        while tasks.join_next().await.is_some() {}
+
+        tokio::time::timeout(Duration::from_secs(1), switch.tripped())
+            .await
+            .expect("breaker should have tripped");
+        assert_eq!(switch.cause(), Some(AbortCause::TooManyFailures(3)));
+        assert_eq!(FailureBreaker::new(None).record(), None);
+    }
+
+    #[test]
+    fn test_format_error_retries_with_relaxed_format() {
+        let options = DownloadOptions {
+            retry_lower_quality: true,
+            ..DownloadOptions::default()
+        };
+        let first_attempt = classify_error(
+            "ERROR: [youtube] abc: Requested format is not available. Use --list-formats for a list of available formats",
+        );
+        assert!(options.retries_with_lower_quality(first_attempt));
+        assert!(!options.retries_with_lower_quality(ErrorKind::Unavailable));
+        assert!(!DownloadOptions::default().retries_with_lower_quality(first_attempt));
+
+        let stage = &download_plan(&options.mode, false)[0];
+        let mut args = options.ytdlp_args(stage, false);
+        args.relaxed_format = true;
+        let retry = build_ytdlp_args("https://example.com", &args);
+        let pos = retry.iter().position(|s| s == "--format").unwrap();
+        assert_eq!(retry[pos + 1], "bv*+ba/b");
+    }
+
+    #[test]
+    fn test_only_auth_failures_abort_when_enabled() {
+        let enabled = DownloadOptions {
+            abort_on_auth_error: true,
+            ..DownloadOptions::default()
+        };
+        assert!(enabled.aborts_batch(ErrorKind::Auth));
+        assert!(!enabled.aborts_batch(ErrorKind::RateLimited));
+        assert!(!DownloadOptions::default().aborts_batch(ErrorKind::Auth));
+    }
+
+    #[test]
+    fn test_cookie_prompt_only_for_auth_without_cookies() {
+        let enabled = DownloadOptions {
+            prompt_for_cookies_on_auth_failure: true,
+            ..DownloadOptions::default()
+        };
+        let answer = || Ok(Some("firefox".to_string()));
+
+        assert_eq!(
+            cookie_retry_browser(&enabled, ErrorKind::Auth, answer).as_deref(),
+            Some("firefox")
+        );
+        assert_eq!(
+            cookie_retry_browser(&enabled, ErrorKind::Auth, || Ok(None)),
+            None
+        );
+        assert_eq!(
+            cookie_retry_browser(&enabled, ErrorKind::Auth, || Err(
+                std::io::ErrorKind::UnexpectedEof.into()
+            )),
+            None
+        );
+        assert_eq!(
+            cookie_retry_browser(&enabled, ErrorKind::Network, answer),
+            None
+        );
+
+        let with_cookies = DownloadOptions {
+            cookies_from: Some("chrome".to_string()),
+            ..enabled
+        };
+        assert_eq!(
+            cookie_retry_browser(&with_cookies, ErrorKind::Auth, answer),
+            None
+        );
+        assert_eq!(
+            cookie_retry_browser(&DownloadOptions::default(), ErrorKind::Auth, answer),
+            None
+        );
+    }
+
+    #[test]
+    fn test_quiet_failure_dumps_captured_stderr() {
+        let failure = RunFailure {
+            reason: "Unsupported URL".to_string(),
+            kind: ErrorKind::Unsupported,
+            stderr:
+                "WARNING: [generic] Falling back\nERROR: Unsupported URL: https://a.example/x\n"
+                    .to_string(),
+        };
+
+        let quiet = render_failure("https://a.example/x", &failure, true);
+        assert!(quiet.contains("https://a.example/x"));
+        assert!(quiet.contains("WARNING: [generic] Falling back"));
+        assert!(quiet.contains("ERROR: Unsupported URL: https://a.example/x"));
+
+        let loud = render_failure("https://a.example/x", &failure, false);
+        assert!(loud.contains("Unsupported URL"));
+        assert!(!loud.contains("WARNING"));
+    }
+
+    #[test]
+    fn test_build_report_statuses() {
+        let urls = [
+            "https://a.example/1".to_string(),
+            "https://a.example/2".to_string(),
+            "https://a.example/3".to_string(),
+        ];
+        let failed = [FailedDownload {
+            url: urls[1].clone(),
+            reason: "Private video".to_string(),
+            detail: None,
+        }];
+        let report = build_report(&urls, &urls[..1], &[], &failed);
+        let statuses: Vec<ReportStatus> = report.entries.iter().map(|e| e.status).collect();
+
+        assert_eq!(
+            statuses,
+            [
+                ReportStatus::Completed,
+                ReportStatus::Failed,
+                ReportStatus::Skipped
+            ]
+        );
+        assert_eq!(report.entries[1].reason.as_deref(), Some("Private video"));
+        assert_eq!(report.failed_urls(), ["https://a.example/2"]);
+    }
+
+    #[test]
+    fn test_filtered_items_are_not_failures() {
+        assert!(is_filter_skip(
+            "[download] Long Stream does not pass filter (duration<=3600), skipping .."
+        ));
+        assert!(!is_filter_skip("[download] 100% of 10.00MiB"));
+
+        let urls = ["https://a.example/long".to_string()];
+        let filtered = [(urls[0].clone(), FILTERED_REASON)];
+        let report = build_report(&urls, &[], &filtered, &[]);
+
+        assert_eq!(report.entries[0].status, ReportStatus::Skipped);
+        assert_eq!(
+            report.entries[0].reason.as_deref(),
+            Some("Longer than --max-duration")
+        );
+        assert!(report.failed_urls().is_empty());
+    }
+
+    #[test]
+    fn test_render_batch_plan() {
+        let options = DownloadOptions {
+            destination_path: Some(PathBuf::from("/media/clips")),
+            parallel: NonZeroUsize::new(2).unwrap(),
+            ..DownloadOptions::default()
+        };
+        let urls = [
+            "https://a.example/1".to_string(),
+            "https://a.example/2".to_string(),
+            "https://a.example/3".to_string(),
+        ];
+        let plan = render_batch_plan(&urls, &options);
+        let lines: Vec<&str> = plan.lines().collect();
+
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].contains("Default (Max Quality)"));
+        assert!(lines[1].contains("/media/clips"));
+        assert!(lines[1].ends_with("https://a.example/1"));
+        let slots: Vec<&str> = lines[1..4]
+            .iter()
+            .map(|line| line.split_whitespace().nth(1).unwrap())
+            .collect();
+        assert_eq!(slots, ["1", "2", "1"]);
+        assert_eq!(lines[4], "3 URLs, up to 2 at a time");
+    }
+
+    #[test]
+    fn test_file_destination_clash() {
+        let urls = ["https://a.example/1", "https://a.example/2"].map(String::from);
+        let file = DownloadOptions {
+            destination_path: Some(PathBuf::from("/nonexistent/clip.mp4")),
+            ..Default::default()
+        };
+        assert!(matches!(
+            file_destination_clash(&file, &urls),
+            Err(YtrsError::FileDestinationBatch(_))
+        ));
+        assert!(file_destination_clash(&file, &urls[..1]).unwrap().is_none());
+
+        let indexed = DownloadOptions {
+            on_file_dest_batch: FileDestBatch::Index,
+            ..file
+        };
+        let clash = file_destination_clash(&indexed, &urls).unwrap().unwrap();
+        let plan = CollisionPlan::new(&[], &[clash], OnCollision::Index);
+        assert_eq!(plan.suffixes.len(), 1);
+        let args = YtDlpArgs {
+            destination_path: indexed.destination_path.as_deref(),
+            filename_suffix: Some(&plan.suffixes[&urls[1]]),
+            ..Default::default()
+        };
+        let result = build_ytdlp_args(&urls[1], &args);
+        assert!(result.iter().any(|s| s == "/nonexistent/clip (2).mp4"));
+
+        let directory = DownloadOptions {
+            destination_path: Some(std::env::temp_dir()),
+            ..Default::default()
+        };
+        assert!(file_destination_clash(&directory, &urls).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_single_url_success_summary() {
+        let summary = render_summary(1, &[]);
+
+        assert!(summary.contains("DOWNLOAD SUMMARY"));
+        assert!(summary.contains("Download completed successfully."));
+        assert!(!summary.contains("failed"));
+    }
+
+    #[test]
+    fn test_single_url_failure_summary() {
+        let failed = [FailedDownload {
+            url: "https://example.com/v".to_string(),
+            reason: "yt-dlp exited with code 3".to_string(),
+            detail: Some("ERROR: something odd happened".to_string()),
+        }];
+        let summary = render_summary(1, &failed);
+
+        assert!(summary.contains("1/1 downloads failed"));
+        assert!(summary.contains("https://example.com/v"));
+        assert!(summary.contains("ERROR: something odd happened"));
+    }
+
+    #[test]
+    fn test_batch_success_summary() {
+        assert!(render_summary(3, &[]).contains("All 3 downloads completed successfully."));
+    }
+
+    #[test]
+    fn test_success_threshold() {
+        let at = |threshold| DownloadOptions {
+            success_threshold: Some(threshold),
+            ..DownloadOptions::default()
+        };
+
+        assert!(at(90).accepts_partial(9, 1));
+        assert!(!at(90).accepts_partial(8, 2));
+        assert!(at(50).accepts_partial(1, 1));
+        assert!(!at(50).accepts_partial(1, 2));
+        assert!(at(0).accepts_partial(0, 5));
+        assert!(!at(100).accepts_partial(99, 1));
+        assert!(at(100).accepts_partial(0, 0));
+        assert!(!DownloadOptions::default().accepts_partial(9, 1));
+        let exit_zero = DownloadOptions {
+            exit_zero_on_partial: true,
+            ..DownloadOptions::default()
+        };
+        assert!(exit_zero.accepts_partial(0, 3));
+    }
+
+    #[test]
+    fn test_exit_zero_on_partial() {
+        let failed = [FailedDownload {
+            url: "https://example.com/v".to_string(),
+            reason: "Video unavailable".to_string(),
+            detail: None,
+        }];
+
+        assert!(matches!(
+            batch_result(&failed, 3, false),
+            Err(YtrsError::PartialFailure(1))
+        ));
+        assert!(matches!(
+            batch_result(&failed, 1, false),
+            Err(YtrsError::DownloadFailed { .. })
+        ));
+        assert!(batch_result(&failed, 3, true).is_ok());
+        assert!(batch_result(&failed, 1, true).is_ok());
+        assert!(batch_result(&[], 3, false).is_ok());
+    }
+
+    #[test]
+    fn test_fail_summary_only() {
+        assert_eq!(final_summary(3, &[], true), None);
+        assert!(final_summary(3, &[], false).is_some());
+
+        let failed = [FailedDownload {
+            url: "https://example.com/v".to_string(),
+            reason: "Video unavailable".to_string(),
+            detail: None,
+        }];
+        let summary = final_summary(3, &failed, true).unwrap();
+        assert!(summary.contains("1/3 downloads failed"));
+        assert!(summary.contains("https://example.com/v"));
+    }
+
+    #[test]
+    fn test_quiet_and_fail_summary_only_silence_status() {
+        assert!(DownloadOptions::default().prints_status());
+        for options in [
+            DownloadOptions {
+                quiet: true,
+                ..Default::default()
+            },
+            DownloadOptions {
+                fail_summary_only: true,
+                ..Default::default()
+            },
+        ] {
+            assert!(!options.prints_status());
+        }
+    }
 }
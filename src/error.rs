@@ -1,7 +1,11 @@
 //! Error types with human-readable messages
 
+use std::path::PathBuf;
+
 use thiserror::Error;
 
+use crate::config::{COOKIE_BROWSERS, SPONSORBLOCK_CATEGORIES};
+
 #[derive(Debug, Error)]
 pub enum YtrsError {
     #[error("Dependency '{0}' is not installed or not found in PATH")]
@@ -10,80 +14,318 @@ pub enum YtrsError {
     #[error("Download failed for '{url}': {reason}")]
     DownloadFailed { url: String, reason: String },
 
+    #[error("{name} {found} is older than the required {required}; please update it")]
+    OutdatedDependency {
+        name: String,
+        found: String,
+        required: String,
+    },
+
     #[error("yt-dlp process error: {0}")]
     #[allow(dead_code)]
     ProcessError(String),
 
+    #[error("yt-dlp exited unsuccessfully{}", describe_failure(*.code, .stderr_tail.as_deref()))]
+    YtDlpFailed {
+        code: Option<i32>,
+        stderr_tail: Option<String>,
+    },
+
+    #[error("Stopped the batch after an authentication failure on '{url}': {reason}")]
+    AuthAborted { url: String, reason: String },
+
+    #[error("Stopped the batch after {0} failed downloads")]
+    TooManyFailures(usize),
+
+    #[error("Stopped the batch at the --max-runtime limit of {}s", .0.as_secs())]
+    RuntimeExceeded(std::time::Duration),
+
+    #[error("This yt-dlp build doesn't support {0}; try updating yt-dlp")]
+    UnsupportedByYtDlp(&'static str),
+
     #[error("No valid URLs provided")]
     NoValidUrls,
 
     #[error("{0} download(s) failed")]
     PartialFailure(usize),
 
+    #[error("{0} local re-encode(s) failed")]
+    ReencodeFailed(usize),
+
+    #[error("Invalid file pattern '{pattern}': {reason}")]
+    InvalidPattern { pattern: String, reason: String },
+
+    #[error("Invalid config file {}: {reason}", .path.display())]
+    InvalidConfig { path: PathBuf, reason: String },
+
+    #[error("Invalid --playlist-items '{0}', expected comma-separated N, N-M or N- like 1-3,7,10-")]
+    InvalidPlaylistSpec(String),
+
+    #[error("Unknown SponsorBlock category '{0}', expected one of: {categories}", categories = SPONSORBLOCK_CATEGORIES.join(", "))]
+    InvalidSponsorBlockCategory(String),
+
+    #[error("Unsupported cookie browser '{0}', expected one of: {browsers}", browsers = COOKIE_BROWSERS.join(", "))]
+    UnsupportedBrowser(String),
+
+    #[error(
+        "Both cookies_from and cookies_file are set; keep one in the config file or pass --cookies-from or --cookies-file"
+    )]
+    ConflictingCookies,
+
+    #[error(
+        "Unknown --socm target '{0}'; use a built-in target or a [socm_presets] name from the config file"
+    )]
+    UnknownSocmTarget(String),
+
     #[error("Invalid mode combination: {0}")]
     InvalidModeCombo(String),
 
     #[error("Semaphore closed unexpectedly")]
     SemaphoreClosed,
 
+    #[error("Cannot write to '{}': {reason}", path.display())]
+    DestinationNotWritable { path: PathBuf, reason: String },
+
+    #[error("Could not write failed URLs to '{}': {source}", path.display())]
+    FailedLogWrite {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error(
+        "Every URL in the batch would write to the file '{}'; pass a directory to -d or use --on-file-dest-batch index",
+        .0.display()
+    )]
+    FileDestinationBatch(PathBuf),
+
+    #[error("{0} only works with a single URL")]
+    SingleUrlOnly(&'static str),
+
+    #[error("Failed to parse yt-dlp output: {0}")]
+    InfoParse(#[from] serde_json::Error),
+
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 }
 
 pub type Result<T> = std::result::Result<T, YtrsError>;
 
+const STDERR_TAIL_LINES: usize = 3;
+
+fn describe_failure(code: Option<i32>, stderr_tail: Option<&str>) -> String {
+    let mut description = code.map(|c| format!(" (code {c})")).unwrap_or_default();
+    if let Some(tail) = stderr_tail {
+        description.push_str(":\n");
+        description.push_str(tail);
+    }
+    description
+}
+
+/// Last few non-empty stderr lines, which is where yt-dlp reports the actual error
+pub fn stderr_tail(stderr: &str) -> Option<String> {
+    let lines: Vec<&str> = stderr
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+
+    let start = lines.len().saturating_sub(STDERR_TAIL_LINES);
+    Some(lines[start..].join("\n"))
+}
+
+/// Broad cause of a failed download, used to decide how a batch should react
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Missing, expired or insufficient cookies
+    Auth,
+    RateLimited,
+    Unavailable,
+    /// The format selector matched nothing; a looser selector may still work
+    FormatUnavailable,
+    Network,
+    Unsupported,
+    /// yt-dlp couldn't solve YouTube's player signature; usually fixed by updating it
+    PlayerChallenge,
+    Other,
+}
+
+impl ErrorKind {
+    /// Whether running the same command again might succeed; auth, availability, format
+    /// and player challenge problems won't change between attempts
+    #[must_use]
+    pub const fn is_transient(self) -> bool {
+        matches!(self, Self::RateLimited | Self::Network | Self::Other)
+    }
+}
+
+const PLAYER_CHALLENGE_HINT: &str =
+    "YouTube player signature could not be solved - update yt-dlp with `yt-dlp -U` and retry";
+
+/// Known yt-dlp stderr signatures, checked in order
+const ERROR_PATTERNS: &[(&str, &str, ErrorKind)] = &[
+    (
+        "Video unavailable",
+        "Video is unavailable or private",
+        ErrorKind::Unavailable,
+    ),
+    ("Private video", "Video is private", ErrorKind::Unavailable),
+    (
+        "This video is private",
+        "Video is private",
+        ErrorKind::Unavailable,
+    ),
+    // Checked before the 403 or missing format these usually cause further down
+    (
+        "nsig extraction failed",
+        PLAYER_CHALLENGE_HINT,
+        ErrorKind::PlayerChallenge,
+    ),
+    (
+        "Signature extraction failed",
+        PLAYER_CHALLENGE_HINT,
+        ErrorKind::PlayerChallenge,
+    ),
+    (
+        "n challenge solving failed",
+        PLAYER_CHALLENGE_HINT,
+        ErrorKind::PlayerChallenge,
+    ),
+    (
+        "429",
+        "Rate limited by server - try again later",
+        ErrorKind::RateLimited,
+    ),
+    (
+        "Too Many Requests",
+        "Rate limited by server - try again later",
+        ErrorKind::RateLimited,
+    ),
+    (
+        "cookies are no longer valid",
+        "Cookies have expired - refresh them and retry",
+        ErrorKind::Auth,
+    ),
+    (
+        "not a bot",
+        "Bot check - requires account cookies",
+        ErrorKind::Auth,
+    ),
+    (
+        "403",
+        "Access forbidden - may require cookies",
+        ErrorKind::Auth,
+    ),
+    (
+        "Forbidden",
+        "Access forbidden - may require cookies",
+        ErrorKind::Auth,
+    ),
+    ("404", "Video not found", ErrorKind::Unavailable),
+    ("Not Found", "Video not found", ErrorKind::Unavailable),
+    (
+        "Sign in to confirm your age",
+        "Age-restricted - requires account cookies",
+        ErrorKind::Auth,
+    ),
+    (
+        "age-restricted",
+        "Age-restricted - requires account cookies",
+        ErrorKind::Auth,
+    ),
+    (
+        "members-only",
+        "Members-only content - requires membership cookies",
+        ErrorKind::Auth,
+    ),
+    (
+        "Join this channel",
+        "Members-only content - requires membership cookies",
+        ErrorKind::Auth,
+    ),
+    (
+        "no longer available",
+        "Video is no longer available",
+        ErrorKind::Unavailable,
+    ),
+    (
+        "has been removed",
+        "Video has been removed",
+        ErrorKind::Unavailable,
+    ),
+    (
+        "copyright",
+        "Video removed due to copyright claim",
+        ErrorKind::Unavailable,
+    ),
+    (
+        "blocked",
+        "Video is blocked in your region",
+        ErrorKind::Unavailable,
+    ),
+    (
+        "country",
+        "Video is not available in your country",
+        ErrorKind::Unavailable,
+    ),
+    (
+        "No video formats",
+        "No downloadable video formats found",
+        ErrorKind::Unavailable,
+    ),
+    (
+        "Requested format is not available",
+        "Requested format not available",
+        ErrorKind::FormatUnavailable,
+    ),
+    (
+        "is not a valid URL",
+        "Invalid URL format",
+        ErrorKind::Unsupported,
+    ),
+    (
+        "Unsupported URL",
+        "Website not supported by yt-dlp",
+        ErrorKind::Unsupported,
+    ),
+    (
+        "Unable to extract",
+        "Failed to extract video information",
+        ErrorKind::Other,
+    ),
+    (
+        "Connection refused",
+        "Connection refused by server",
+        ErrorKind::Network,
+    ),
+    ("timed out", "Connection timed out", ErrorKind::Network),
+    (
+        "Name or service not known",
+        "DNS resolution failed",
+        ErrorKind::Network,
+    ),
+];
+
+/// Human-readable message for a known yt-dlp stderr signature
+pub fn match_error_pattern(stderr: &str) -> Option<&'static str> {
+    ERROR_PATTERNS
+        .iter()
+        .find(|(pattern, _, _)| stderr.contains(pattern))
+        .map(|(_, message, _)| *message)
+}
+
+pub fn classify_error(stderr: &str) -> ErrorKind {
+    ERROR_PATTERNS
+        .iter()
+        .find(|(pattern, _, _)| stderr.contains(pattern))
+        .map_or(ErrorKind::Other, |(_, _, kind)| *kind)
+}
+
 pub fn extract_error_reason(stderr: &str, exit_code: Option<i32>) -> String {
-    let patterns = [
-        ("Video unavailable", "Video is unavailable or private"),
-        ("Private video", "Video is private"),
-        ("This video is private", "Video is private"),
-        ("429", "Rate limited by server - try again later"),
-        (
-            "Too Many Requests",
-            "Rate limited by server - try again later",
-        ),
-        ("403", "Access forbidden - may require cookies"),
-        ("Forbidden", "Access forbidden - may require cookies"),
-        ("404", "Video not found"),
-        ("Not Found", "Video not found"),
-        (
-            "Sign in to confirm your age",
-            "Age-restricted - requires account cookies",
-        ),
-        (
-            "age-restricted",
-            "Age-restricted - requires account cookies",
-        ),
-        (
-            "members-only",
-            "Members-only content - requires membership cookies",
-        ),
-        (
-            "Join this channel",
-            "Members-only content - requires membership cookies",
-        ),
-        ("no longer available", "Video is no longer available"),
-        ("has been removed", "Video has been removed"),
-        ("copyright", "Video removed due to copyright claim"),
-        ("blocked", "Video is blocked in your region"),
-        ("country", "Video is not available in your country"),
-        ("No video formats", "No downloadable video formats found"),
-        (
-            "Requested format not available",
-            "Requested format not available",
-        ),
-        ("is not a valid URL", "Invalid URL format"),
-        ("Unsupported URL", "Website not supported by yt-dlp"),
-        ("Unable to extract", "Failed to extract video information"),
-        ("Connection refused", "Connection refused by server"),
-        ("timed out", "Connection timed out"),
-        ("Name or service not known", "DNS resolution failed"),
-    ];
-
-    for (pattern, message) in patterns {
-        if stderr.contains(pattern) {
-            return message.to_string();
-        }
+    if let Some(message) = match_error_pattern(stderr) {
+        return message.to_string();
     }
 
     match exit_code {
@@ -152,6 +394,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_transient_kinds() {
+        assert!(classify_error("ERROR: HTTP Error 429: Too Many Requests").is_transient());
+        assert!(ErrorKind::Other.is_transient());
+        assert!(!classify_error("ERROR: Private video").is_transient());
+        assert!(!ErrorKind::Auth.is_transient());
+        assert!(!ErrorKind::FormatUnavailable.is_transient());
+        assert!(!ErrorKind::PlayerChallenge.is_transient());
+    }
+
+    #[test]
+    fn test_classify_error() {
+        assert_eq!(
+            classify_error("ERROR: Sign in to confirm your age"),
+            ErrorKind::Auth
+        );
+        assert_eq!(
+            classify_error("ERROR: [youtube] abc: Sign in to confirm you're not a bot"),
+            ErrorKind::Auth
+        );
+        assert_eq!(
+            classify_error("ERROR: HTTP Error 429: Too Many Requests"),
+            ErrorKind::RateLimited
+        );
+        assert_eq!(
+            classify_error(
+                "ERROR: [youtube] abc: Requested format is not available. Use --list-formats for a list of available formats"
+            ),
+            ErrorKind::FormatUnavailable
+        );
+        assert_eq!(classify_error("something new"), ErrorKind::Other);
+    }
+
+    #[test]
+    fn test_player_challenge_wins_over_its_symptoms() {
+        let stderr = "WARNING: [youtube] abc: nsig extraction failed: Some formats may be missing\n\
+                      ERROR: unable to download video data: HTTP Error 403: Forbidden";
+        assert_eq!(classify_error(stderr), ErrorKind::PlayerChallenge);
+        assert_eq!(
+            extract_error_reason(stderr, Some(1)),
+            "YouTube player signature could not be solved - update yt-dlp with `yt-dlp -U` and retry"
+        );
+        assert_eq!(
+            classify_error(
+                "WARNING: [youtube] [jsc] abc: n challenge solving failed: Some formats may be missing"
+            ),
+            ErrorKind::PlayerChallenge
+        );
+    }
+
     #[test]
     fn test_error_display() {
         let err = YtrsError::DownloadFailed {
@@ -163,4 +455,28 @@ mod tests {
             "Download failed for 'https://example.com': Video is private"
         );
     }
+
+    #[test]
+    fn test_ytdlp_failed_includes_stderr_tail() {
+        let stderr = "[youtube] abc: Downloading webpage\n[youtube] abc: Downloading player\n\
+                      WARNING: something\n\nERROR: boom\n";
+        let err = YtrsError::YtDlpFailed {
+            code: Some(1),
+            stderr_tail: stderr_tail(stderr),
+        };
+        let message = err.to_string();
+
+        assert!(message.starts_with("yt-dlp exited unsuccessfully (code 1):"));
+        assert!(message.ends_with("Downloading player\nWARNING: something\nERROR: boom"));
+        assert!(!message.contains("Downloading webpage"));
+    }
+
+    #[test]
+    fn test_ytdlp_failed_without_tail() {
+        let err = YtrsError::YtDlpFailed {
+            code: None,
+            stderr_tail: stderr_tail("  \n"),
+        };
+        assert_eq!(err.to_string(), "yt-dlp exited unsuccessfully");
+    }
 }
@@ -0,0 +1,86 @@
+//! `~` and `$VAR` expansion for paths that never went through a shell, like a quoted
+//! `-d '~/Videos'` or a config file value
+
+/// Expands a leading `~` and every `$VAR` or `${VAR}`; unset variables stay as written
+#[must_use]
+pub fn expand_path(input: &str) -> String {
+    expand_with(input, |name| std::env::var(name).ok())
+}
+
+fn expand_with(input: &str, var: impl Fn(&str) -> Option<String>) -> String {
+    // What a shell already expanded has nothing left to replace
+    if !input.starts_with('~') && !input.contains('$') {
+        return input.to_string();
+    }
+
+    let (mut expanded, mut rest) = match (input.strip_prefix('~'), var("HOME")) {
+        (Some(after), Some(home)) if after.is_empty() || after.starts_with('/') => (home, after),
+        _ => (String::new(), input),
+    };
+    while let Some(dollar) = rest.find('$') {
+        expanded.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+        let (name, len) = match after.strip_prefix('{') {
+            Some(braced) => braced
+                .find('}')
+                .map_or(("", 0), |end| (&braced[..end], end + 2)),
+            None => {
+                let end = after
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+        let written = &rest[dollar..=dollar + len];
+        match (!name.is_empty()).then(|| var(name)).flatten() {
+            Some(value) => expanded.push_str(&value),
+            None => expanded.push_str(written),
+        }
+        rest = &rest[dollar + 1 + len..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(input: &str) -> String {
+        expand_with(input, |name| match name {
+            "HOME" => Some("/home/me".to_string()),
+            "MEDIA" => Some("/srv/media".to_string()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_tilde() {
+        assert_eq!(expand("~"), "/home/me");
+        assert_eq!(expand("~/sub"), "/home/me/sub");
+        // Another user's home isn't looked up
+        assert_eq!(expand("~bob/sub"), "~bob/sub");
+        assert_eq!(expand("/videos/~/sub"), "/videos/~/sub");
+    }
+
+    #[test]
+    fn test_env_vars() {
+        assert_eq!(expand("$HOME/sub"), "/home/me/sub");
+        assert_eq!(expand("${HOME}/sub"), "/home/me/sub");
+        assert_eq!(expand("$MEDIA/${HOME}x"), "/srv/media//home/mex");
+        assert_eq!(
+            expand("$MEDIA/%(uploader)s/%(title)s.%(ext)s"),
+            "/srv/media/%(uploader)s/%(title)s.%(ext)s"
+        );
+    }
+
+    #[test]
+    fn test_unexpandable_left_alone() {
+        assert_eq!(expand("/already/expanded"), "/already/expanded");
+        assert_eq!(expand("$UNSET/sub"), "$UNSET/sub");
+        assert_eq!(expand("${UNSET}/sub"), "${UNSET}/sub");
+        assert_eq!(expand("price$"), "price$");
+        assert_eq!(expand("a$/b"), "a$/b");
+        assert_eq!(expand("${HOME"), "${HOME");
+    }
+}
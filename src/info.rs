@@ -0,0 +1,363 @@
+//! Informational yt-dlp invocations that print and exit without downloading
+
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use colored::Colorize;
+
+use crate::args_builder::{YtDlpArgs, build_ytdlp_args};
+use crate::error::{Result, YtrsError, stderr_tail};
+use crate::size::format_bytes;
+use crate::theme::Themed;
+use crate::video_info::{FormatRow, VideoInfo};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InfoCommand {
+    DumpUserAgent,
+    ListThumbnails(String),
+    ListImpersonateTargets,
+    ListFormats {
+        url: String,
+        cookies_from: Option<String>,
+        cookies_file: Option<PathBuf>,
+        /// Fetch the formats as JSON and print ytrs's own table instead of yt-dlp's
+        as_table: bool,
+    },
+}
+
+impl InfoCommand {
+    #[must_use]
+    pub fn args(&self) -> Vec<Cow<'_, str>> {
+        match self {
+            Self::DumpUserAgent => vec![Cow::Borrowed("--dump-user-agent")],
+            Self::ListThumbnails(url) => ["--list-thumbnails", "--skip-download", url]
+                .map(Cow::Borrowed)
+                .to_vec(),
+            Self::ListImpersonateTargets => vec![Cow::Borrowed("--list-impersonate-targets")],
+            Self::ListFormats {
+                url,
+                cookies_from,
+                cookies_file,
+                as_table,
+            } => {
+                let mut args: Vec<Cow<'_, str>> = if *as_table {
+                    vec![Cow::Borrowed("--dump-json"), Cow::Borrowed("--no-warnings")]
+                } else {
+                    vec![Cow::Borrowed("--list-formats")]
+                };
+                if let Some(cookies) = cookies_from {
+                    args.extend([
+                        Cow::Borrowed("--cookies-from-browser"),
+                        Cow::Borrowed(cookies),
+                    ]);
+                }
+                if let Some(file) = cookies_file {
+                    args.extend([Cow::Borrowed("--cookies"), file.to_string_lossy()]);
+                }
+                args.push(Cow::Borrowed(url));
+                args
+            }
+        }
+    }
+
+    const fn flag(&self) -> &'static str {
+        match self {
+            Self::DumpUserAgent => "--dump-user-agent",
+            Self::ListThumbnails(_) => "--list-thumbnails",
+            Self::ListImpersonateTargets => "--list-impersonate-targets",
+            Self::ListFormats {
+                as_table: false, ..
+            } => "--list-formats",
+            Self::ListFormats { as_table: true, .. } => "--list-formats-json",
+        }
+    }
+
+    /// The URL a per-URL command covers, for telling batch output apart
+    fn url(&self) -> Option<&str> {
+        match self {
+            Self::ListThumbnails(url) | Self::ListFormats { url, .. } => Some(url),
+            Self::DumpUserAgent | Self::ListImpersonateTargets => None,
+        }
+    }
+}
+
+/// Older yt-dlp builds reject options they predate with optparse's "no such option"
+fn is_unknown_option(stderr: &str) -> bool {
+    stderr.contains("no such option")
+}
+
+fn run_info(command: &InfoCommand) -> Result<()> {
+    let as_table = matches!(command, InfoCommand::ListFormats { as_table: true, .. });
+    let output = Command::new("yt-dlp")
+        .args(command.args().iter().map(AsRef::<str>::as_ref))
+        .stdin(Stdio::null())
+        .stdout(if as_table {
+            Stdio::piped()
+        } else {
+            Stdio::inherit()
+        })
+        .stderr(Stdio::piped())
+        .output()?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        if is_unknown_option(&stderr) {
+            return Err(YtrsError::UnsupportedByYtDlp(command.flag()));
+        }
+        return Err(YtrsError::YtDlpFailed {
+            code: output.status.code(),
+            stderr_tail: stderr_tail(&stderr),
+        });
+    }
+
+    eprint!("{stderr}");
+    // Playlists print one JSON object per entry
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        print!("{}", render_format_table(&VideoInfo::from_json(line)?));
+    }
+    Ok(())
+}
+
+/// Runs each command in turn, with a header per URL when there are several
+pub fn run_info_commands(commands: &[InfoCommand]) -> Result<()> {
+    for command in commands {
+        if commands.len() > 1
+            && let Some(url) = command.url()
+        {
+            println!("{} {}", "==>".dimmed(), url.info());
+        }
+        run_info(command)?;
+    }
+    Ok(())
+}
+
+/// Simulates the download with the real format args and reports what yt-dlp picked
+pub fn preview_format(url: &str, args: &YtDlpArgs<'_>) -> Result<VideoInfo> {
+    let mut cmd_args = build_ytdlp_args(url, args);
+    cmd_args.insert(cmd_args.len() - 1, Cow::Borrowed("--dump-json"));
+
+    let output = Command::new("yt-dlp")
+        .args(cmd_args.iter().map(AsRef::<str>::as_ref))
+        .stdin(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(YtrsError::YtDlpFailed {
+            code: output.status.code(),
+            stderr_tail: stderr_tail(&String::from_utf8_lossy(&output.stderr)),
+        });
+    }
+
+    // Playlists emit one JSON object per line; the first entry is representative
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    VideoInfo::from_json(stdout.lines().next().unwrap_or_default())
+}
+
+pub fn render_format_preview(info: &VideoInfo) -> String {
+    let mut preview = format!(
+        "{} {}\n",
+        "Selected format for".dimmed(),
+        info.title.as_deref().unwrap_or("Unknown").info()
+    );
+    for format in info.selected_formats() {
+        preview.push_str(&format!(
+            "  {:<8} {:<11} {:<14} {:<5} {}\n",
+            format.format_id.as_deref().unwrap_or("?"),
+            format.resolution(),
+            format.codec(),
+            format.ext.as_deref().unwrap_or("?"),
+            format
+                .size_bytes()
+                .map_or_else(|| "size unknown".to_string(), format_bytes),
+        ));
+    }
+    preview
+}
+
+/// `--list-formats-json`: every format the URL offers, one aligned row each
+pub fn render_format_table(info: &VideoInfo) -> String {
+    let rows = info.format_rows();
+    let header = FormatRow {
+        id: "ID".to_string(),
+        ext: "EXT".to_string(),
+        resolution: "RESOLUTION".to_string(),
+        fps: "FPS".to_string(),
+        vcodec: "VCODEC".to_string(),
+        acodec: "ACODEC".to_string(),
+        filesize: "FILESIZE".to_string(),
+    };
+    let width = |cell: fn(&FormatRow) -> &str| {
+        rows.iter()
+            .chain([&header])
+            .map(|row| cell(row).chars().count())
+            .max()
+            .unwrap_or_default()
+    };
+    let widths = [
+        width(|row| &row.id),
+        width(|row| &row.ext),
+        width(|row| &row.resolution),
+        width(|row| &row.fps),
+        width(|row| &row.vcodec),
+        width(|row| &row.acodec),
+    ];
+    let line = |row: &FormatRow| {
+        format!(
+            "{:<w0$}  {:<w1$}  {:<w2$}  {:>w3$}  {:<w4$}  {:<w5$}  {}",
+            row.id,
+            row.ext,
+            row.resolution,
+            row.fps,
+            row.vcodec,
+            row.acodec,
+            row.filesize,
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+            w4 = widths[4],
+            w5 = widths[5],
+        )
+    };
+
+    let mut table = format!(
+        "{} {}\n",
+        "Formats for".dimmed(),
+        info.title.as_deref().unwrap_or("Unknown").info()
+    );
+    table.push_str(&format!("{}\n", line(&header).dimmed()));
+    for row in &rows {
+        table.push_str(&line(row));
+        table.push('\n');
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_format_preview() {
+        let info = VideoInfo::from_json(
+            r#"{"title": "Clip", "requested_formats": [
+                {"format_id": "248", "ext": "webm", "width": 1920, "height": 1080,
+                 "vcodec": "vp9", "acodec": "none", "filesize": 1048576},
+                {"format_id": "251", "ext": "webm", "vcodec": "none", "acodec": "opus"}
+            ]}"#,
+        )
+        .unwrap();
+        let preview = render_format_preview(&info);
+        let lines: Vec<&str> = preview.lines().collect();
+
+        assert!(lines[0].contains("Clip"));
+        assert!(lines[1].contains("248") && lines[1].contains("1920x1080"));
+        assert!(lines[1].contains("vp9") && lines[1].ends_with("1.0 MiB"));
+        assert!(lines[2].contains("audio only") && lines[2].ends_with("size unknown"));
+    }
+
+    #[test]
+    fn test_dump_user_agent_args() {
+        assert_eq!(InfoCommand::DumpUserAgent.args(), ["--dump-user-agent"]);
+    }
+
+    #[test]
+    fn test_list_impersonate_targets_args() {
+        assert_eq!(
+            InfoCommand::ListImpersonateTargets.args(),
+            ["--list-impersonate-targets"]
+        );
+    }
+
+    #[test]
+    fn test_unknown_option_detected() {
+        assert!(is_unknown_option(
+            "yt-dlp: error: no such option: --list-impersonate-targets"
+        ));
+        assert!(!is_unknown_option("ERROR: Unsupported URL"));
+    }
+
+    #[test]
+    fn test_list_formats_args() {
+        let command = InfoCommand::ListFormats {
+            url: "https://example.com/v".to_string(),
+            cookies_from: Some("firefox".to_string()),
+            cookies_file: None,
+            as_table: false,
+        };
+        assert_eq!(
+            command.args(),
+            [
+                "--list-formats",
+                "--cookies-from-browser",
+                "firefox",
+                "https://example.com/v"
+            ]
+        );
+
+        let command = InfoCommand::ListFormats {
+            url: "https://example.com/v".to_string(),
+            cookies_from: None,
+            cookies_file: Some(PathBuf::from("/srv/cookies.txt")),
+            as_table: true,
+        };
+        assert_eq!(
+            command.args(),
+            [
+                "--dump-json",
+                "--no-warnings",
+                "--cookies",
+                "/srv/cookies.txt",
+                "https://example.com/v"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_table() {
+        let command = InfoCommand::ListFormats {
+            url: "https://example.com/v".to_string(),
+            cookies_from: None,
+            cookies_file: None,
+            as_table: true,
+        };
+        assert_eq!(
+            command.args(),
+            ["--dump-json", "--no-warnings", "https://example.com/v"]
+        );
+
+        let info = VideoInfo::from_json(
+            r#"{"title": "Clip", "formats": [
+                {"format_id": "251", "ext": "webm", "vcodec": "none", "acodec": "opus"},
+                {"format_id": "303", "ext": "webm", "width": 1920, "height": 1080,
+                 "fps": 60, "vcodec": "vp9", "acodec": "none", "filesize": 1048576}
+            ]}"#,
+        )
+        .unwrap();
+        let table = render_format_table(&info);
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].contains("Clip"));
+        assert!(lines[1].contains("ID") && lines[1].contains("FILESIZE"));
+        assert!(lines[2].starts_with("251  webm  audio only") && lines[2].ends_with(" -"));
+        assert!(lines[3].starts_with("303  webm  1920x1080 ") && lines[3].ends_with("1.0 MiB"));
+        // Columns line up whatever the cell widths
+        assert_eq!(lines[2].find("none"), lines[3].find("vp9"));
+    }
+
+    #[test]
+    fn test_list_thumbnails_args() {
+        let command = InfoCommand::ListThumbnails("https://example.com/v".to_string());
+        assert_eq!(
+            command.args(),
+            [
+                "--list-thumbnails",
+                "--skip-download",
+                "https://example.com/v"
+            ]
+        );
+    }
+}
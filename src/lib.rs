@@ -0,0 +1,50 @@
+//! ytrs - High-performance yt-dlp wrapper with social media optimization
+//!
+//! The `ytrs` binary is a thin shell over this crate. Front-ends can build a
+//! [`DownloadOptions`] themselves and call [`download_single`] or [`download_batch`]
+//! from a tokio runtime, or use [`build_ytdlp_args`] to run yt-dlp on their own.
+//! Both download functions print per-URL status and the final summary to the
+//! terminal, as the CLI does; [`download_batch_with_events`] sends the per-URL
+//! status to a callback instead.
+
+pub mod archive;
+pub mod args_builder;
+mod autoscale;
+mod bars;
+pub mod cli;
+mod collision;
+pub mod config;
+pub mod config_file;
+pub mod cookies;
+pub mod dependencies;
+pub mod downloader;
+pub mod error;
+mod expand;
+pub mod info;
+pub mod local_media;
+pub mod manifest;
+pub mod mode;
+#[cfg(feature = "net-check")]
+pub mod netcheck;
+#[cfg(feature = "perceptual-dedupe")]
+mod phash;
+pub mod progress;
+pub mod prompt;
+pub mod report;
+pub mod schema;
+pub mod size;
+mod staging;
+mod subtitles;
+pub mod theme;
+mod timing;
+pub mod url_validator;
+mod video_info;
+pub mod webhook;
+
+pub use args_builder::{YtDlpArgs, build_ytdlp_args};
+pub use downloader::{
+    DownloadEvent, DownloadOptions, EventCallback, download_batch, download_batch_with_events,
+    download_single,
+};
+pub use error::{Result, YtrsError};
+pub use mode::DownloadMode;
@@ -0,0 +1,738 @@
+//! Re-encoding local files with the social media presets, bypassing yt-dlp
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use serde::Deserialize;
+use tokio::process::Command as AsyncCommand;
+
+use crate::args_builder::render_command;
+use crate::config::{FIT_SIZE_HEADROOM, SHARE_COPY_SUFFIX};
+use crate::error::{Result, YtrsError, stderr_tail};
+use crate::mode::SocialMediaPreset;
+use crate::theme::Themed;
+use crate::url_validator::validate_url;
+
+const GLOB_CHARS: &[char] = &['*', '?', '['];
+
+/// Anything without a URL scheme that starts like a path, is a glob like `Videos/*.mkv`,
+/// or exists on disk; a schemeless URL like `youtube.com/watch?v=abc` is left to be
+/// skipped as an invalid URL
+#[must_use]
+pub fn is_local_input(input: &str) -> bool {
+    let input = input.trim();
+    if input.is_empty() || validate_url(input) || input.contains("://") {
+        return false;
+    }
+    let first_segment = input.split('/').next().unwrap_or_default();
+    input.starts_with(['/', '~', '.'])
+        || (input.contains(GLOB_CHARS) && !is_host_like(first_segment))
+        || Path::new(input).exists()
+}
+
+/// `youtube.com` or `www.example.co.uk`: dotted labels ending in a letters-only TLD
+fn is_host_like(segment: &str) -> bool {
+    let Some((_, tld)) = segment.rsplit_once('.') else {
+        return false;
+    };
+    tld.len() >= 2
+        && tld.chars().all(|c| c.is_ascii_alphabetic())
+        && segment
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+}
+
+/// `~/rest` under `$HOME`; anything else is taken as is
+pub fn expand_home(input: &str) -> PathBuf {
+    match (input.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(input),
+    }
+}
+
+/// Expands `~` and glob patterns into the matching files, in a stable order
+pub fn expand_local_input(input: &str) -> Result<Vec<PathBuf>> {
+    let path = expand_home(input.trim());
+    let pattern = path.to_string_lossy();
+    if !pattern.contains(GLOB_CHARS) {
+        return Ok(if path.is_file() {
+            vec![path]
+        } else {
+            Vec::new()
+        });
+    }
+
+    let matches = glob::glob(&pattern).map_err(|e| YtrsError::InvalidPattern {
+        pattern: input.to_string(),
+        reason: e.to_string(),
+    })?;
+    let mut files: Vec<PathBuf> = matches
+        .filter_map(std::result::Result::ok)
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// `<stem> [share].mp4` next to the source, or inside `destination` when it is a directory
+#[must_use]
+pub fn local_output_path(input: &Path, destination: Option<&Path>) -> PathBuf {
+    let stem = input
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let dir = destination
+        .filter(|dest| dest.is_dir())
+        .or_else(|| input.parent())
+        .unwrap_or_else(|| Path::new("."));
+    dir.join(format!("{stem}{SHARE_COPY_SUFFIX}.mp4"))
+}
+
+/// ffmpeg arguments for the same encode the yt-dlp postprocessor would run; with
+/// `keep_subs`, embedded text subtitles are carried over instead of dropped
+#[must_use]
+pub fn reencode_args(
+    input: &Path,
+    output: &Path,
+    preset: &SocialMediaPreset,
+    force: bool,
+    keep_subs: bool,
+) -> Vec<String> {
+    let postprocessor_args = preset.postprocessor_args();
+    let encode = postprocessor_args
+        .strip_prefix("ffmpeg:")
+        .unwrap_or(&postprocessor_args);
+
+    let mut args = input_args(input, preset, force, keep_subs);
+    args.extend(encode.split_whitespace().map(String::from));
+    args.push(output.to_string_lossy().into_owned());
+    args
+}
+
+/// Both runs of a two-pass encode at `video_kbps`: the first only analyses the video
+/// into a log next to `output`, the second uses it to land on the bitrate
+#[must_use]
+pub fn two_pass_args(
+    input: &Path,
+    output: &Path,
+    preset: &SocialMediaPreset,
+    force: bool,
+    keep_subs: bool,
+    video_kbps: u32,
+) -> [Vec<String>; 2] {
+    let passlog = pass_log_prefix(output).to_string_lossy().into_owned();
+    let video = |pass: &str| {
+        [
+            "-c:v",
+            &preset.video_codec,
+            "-preset",
+            &preset.preset,
+            "-b:v",
+            &format!("{video_kbps}k"),
+            "-pass",
+            pass,
+            "-passlogfile",
+            &passlog,
+        ]
+        .map(String::from)
+    };
+
+    let mut analyse = input_args(input, preset, true, false);
+    analyse.extend(video("1"));
+    analyse.extend(["-an", "-f", "null", "-"].map(String::from));
+
+    let mut encode = input_args(input, preset, force, keep_subs);
+    encode.extend(video("2"));
+    encode.extend(
+        [
+            "-c:a",
+            &preset.audio_codec,
+            "-b:a",
+            &preset.audio_bitrate,
+            "-movflags",
+            "+faststart",
+        ]
+        .map(String::from),
+    );
+    encode.push(output.to_string_lossy().into_owned());
+    [analyse, encode]
+}
+
+/// Everything before the encoder settings: the input, stream mapping and height cap
+fn input_args(
+    input: &Path,
+    preset: &SocialMediaPreset,
+    force: bool,
+    keep_subs: bool,
+) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "-hide_banner",
+        "-nostdin",
+        if force { "-y" } else { "-n" },
+        "-i",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+    args.push(input.to_string_lossy().into_owned());
+    if keep_subs {
+        // ffmpeg's default stream selection picks no subtitles at all
+        args.extend(
+            [
+                "-map", "0:v:0", "-map", "0:a?", "-map", "0:s?", "-c:s", "mov_text",
+            ]
+            .map(String::from),
+        );
+    }
+    // Local files aren't pre-filtered by height the way yt-dlp's format selection is
+    args.push("-vf".to_string());
+    args.push(format!("scale=-2:'min(ih,{})'", preset.max_height));
+    args
+}
+
+/// ffmpeg appends `-0.log` (and `.mbtree` for x264) to this
+fn pass_log_prefix(output: &Path) -> PathBuf {
+    output.with_extension("passlog")
+}
+
+/// `128k` or `1M` as kbit/s, the way ffmpeg reads `-b:a`
+fn bitrate_kbps(bitrate: &str) -> Option<f64> {
+    let bitrate = bitrate.trim();
+    let (number, scale) = match bitrate.char_indices().last()? {
+        (at, 'k' | 'K') => (&bitrate[..at], 1.0),
+        (at, 'M') => (&bitrate[..at], 1000.0),
+        _ => (bitrate, 0.001),
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .map(|value| value * scale)
+        .filter(|kbps| *kbps >= 0.0)
+}
+
+/// Video bitrate in kbit/s that fits `duration_secs` of video plus the preset's audio
+/// under its size cap, with some headroom for the container; `None` if nothing fits
+#[must_use]
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn target_video_kbps(preset: &SocialMediaPreset, duration_secs: f64) -> Option<u32> {
+    if !duration_secs.is_finite() || duration_secs <= 0.0 {
+        return None;
+    }
+    let budget_kbits = f64::from(preset.max_size_mb) * 1024.0 * 1024.0 * 8.0 / 1000.0;
+    let total_kbps = budget_kbits * FIT_SIZE_HEADROOM / duration_secs;
+    let video_kbps = total_kbps - bitrate_kbps(&preset.audio_bitrate)?;
+    (video_kbps >= 1.0).then(|| video_kbps.min(f64::from(u32::MAX)) as u32)
+}
+
+/// The ffmpeg runs for one CRF encode, or with `fit_size` for a two-pass libx264 encode
+/// sized from the duration; other codecs and files ffprobe can't time stay on CRF
+async fn encode_passes(
+    input: &Path,
+    output: &Path,
+    preset: &SocialMediaPreset,
+    force: bool,
+    keep_subs: bool,
+    fit_size: bool,
+) -> Vec<Vec<String>> {
+    let video_kbps = if fit_size && preset.video_codec == "libx264" {
+        probe_duration(input)
+            .await
+            .and_then(|duration| target_video_kbps(preset, duration))
+    } else {
+        None
+    };
+    match video_kbps {
+        Some(kbps) => two_pass_args(input, output, preset, force, keep_subs, kbps).to_vec(),
+        None => vec![reencode_args(input, output, preset, force, keep_subs)],
+    }
+}
+
+async fn encode(
+    input: &Path,
+    output: &Path,
+    preset: &SocialMediaPreset,
+    force: bool,
+    keep_subs: bool,
+    fit_size: bool,
+) -> std::result::Result<(), String> {
+    let passes = encode_passes(input, output, preset, force, keep_subs, fit_size).await;
+    let two_pass = passes.len() > 1;
+
+    let mut result = Ok(());
+    for args in passes {
+        result = run_ffmpeg(&args).await;
+        if result.is_err() {
+            break;
+        }
+    }
+    if two_pass {
+        let prefix = pass_log_prefix(output).into_os_string();
+        for suffix in ["-0.log", "-0.log.mbtree"] {
+            let mut log = prefix.clone();
+            log.push(suffix);
+            let _ = std::fs::remove_file(log);
+        }
+    }
+    result
+}
+
+async fn run_ffmpeg(args: &[String]) -> std::result::Result<(), String> {
+    let result = AsyncCommand::new("ffmpeg")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| format!("Failed to spawn ffmpeg: {e}"))?;
+    if result.status.success() {
+        Ok(())
+    } else {
+        Err(stderr_tail(&String::from_utf8_lossy(&result.stderr))
+            .unwrap_or_else(|| "ffmpeg failed".to_string()))
+    }
+}
+
+/// Re-encodes each file in turn, reporting progress like a download batch
+pub async fn reencode_local_files(
+    files: &[PathBuf],
+    preset: &SocialMediaPreset,
+    destination: Option<&Path>,
+    force: bool,
+    fit_size: bool,
+) -> Result<()> {
+    let mut failed = 0;
+    for file in files {
+        let name = file.display().to_string();
+        println!("{} {}", "Re-encoding:".info(), name.info());
+
+        let output = local_output_path(file, destination);
+        match encode(file, &output, preset, force, false, fit_size).await {
+            Ok(()) => println!(
+                "{} {}",
+                "Completed:".success(),
+                output.display().to_string().success()
+            ),
+            Err(reason) => {
+                failed += 1;
+                eprintln!("{} {} - {}", "Failed:".error(), name.error(), reason);
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(YtrsError::ReencodeFailed(failed));
+    }
+    Ok(())
+}
+
+/// The ffmpeg command lines [`reencode_local_files`] would run, for `--dry-run`
+pub async fn local_dry_run_commands(
+    files: &[PathBuf],
+    preset: &SocialMediaPreset,
+    destination: Option<&Path>,
+    force: bool,
+    fit_size: bool,
+) -> Vec<String> {
+    let mut commands = Vec::new();
+    for file in files {
+        let output = local_output_path(file, destination);
+        for args in encode_passes(file, &output, preset, force, false, fit_size).await {
+            let args: Vec<Cow<'_, str>> =
+                args.iter().map(|arg| Cow::Borrowed(arg.as_str())).collect();
+            commands.push(render_command("ffmpeg", &args));
+        }
+    }
+    commands
+}
+
+/// The streams of a downloaded file that decide whether a socm re-encode is needed
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MediaProbe {
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub height: Option<u32>,
+    pub size_bytes: u64,
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    height: Option<u32>,
+}
+
+impl MediaProbe {
+    /// Reads `ffprobe -of json` stream output; the first video and audio streams count
+    #[must_use]
+    pub fn from_ffprobe_json(json: &str, size_bytes: u64) -> Option<Self> {
+        let output: ProbeOutput = serde_json::from_str(json).ok()?;
+        let stream = |kind: &str| {
+            output
+                .streams
+                .iter()
+                .find(|stream| stream.codec_type.as_deref() == Some(kind))
+        };
+        let video = stream("video");
+        Some(Self {
+            video_codec: video.and_then(|stream| stream.codec_name.clone()),
+            audio_codec: stream("audio").and_then(|stream| stream.codec_name.clone()),
+            height: video.and_then(|stream| stream.height),
+            size_bytes,
+        })
+    }
+
+    /// `--verify`: ffprobe read the file and found something to play
+    pub fn check_playable(probe: Option<&Self>) -> std::result::Result<(), String> {
+        match probe {
+            None => Err("ffprobe could not read the file".to_string()),
+            Some(probe) if probe.size_bytes == 0 => Err("the file is empty".to_string()),
+            Some(probe) if probe.video_codec.is_none() && probe.audio_codec.is_none() => {
+                Err("ffprobe found no audio or video streams".to_string())
+            }
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Already in the preset's codecs within its height and size caps, so a remux is enough
+    #[must_use]
+    pub fn fits_preset(&self, preset: &SocialMediaPreset) -> bool {
+        let max_bytes = u64::from(preset.max_size_mb) * 1024 * 1024;
+        let audio_codec = encoded_codec(&preset.audio_codec);
+        self.video_codec.as_deref() == Some(encoded_codec(&preset.video_codec))
+            && self
+                .audio_codec
+                .as_deref()
+                .is_none_or(|codec| codec == audio_codec)
+            && self
+                .height
+                .is_some_and(|height| height <= preset.max_height)
+            && self.size_bytes <= max_bytes
+    }
+}
+
+/// The codec name ffprobe reports for what an ffmpeg encoder writes, e.g. `h264` for
+/// `libx264`; native encoders like `aac` are already named after their codec
+fn encoded_codec(encoder: &str) -> &str {
+    match encoder {
+        "libx264" | "libopenh264" => "h264",
+        "libx265" => "hevc",
+        "libvpx" => "vp8",
+        "libvpx-vp9" => "vp9",
+        "libaom-av1" | "libsvtav1" | "librav1e" => "av1",
+        "libopus" => "opus",
+        "libvorbis" => "vorbis",
+        "libmp3lame" => "mp3",
+        "libfdk_aac" => "aac",
+        // Hardware encoders are named after the codec, e.g. h264_nvenc or hevc_vaapi
+        other => other.split_once('_').map_or(other, |(codec, _)| codec),
+    }
+}
+
+/// Container duration in seconds; `None` when ffprobe is missing or can't tell
+pub async fn probe_duration(path: &Path) -> Option<f64> {
+    let output = AsyncCommand::new("ffprobe")
+        .args(["-v", "error", "-show_entries", "format=duration"])
+        .args(["-of", "default=noprint_wrappers=1:nokey=1"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && *secs > 0.0)
+}
+
+/// `None` when ffprobe is missing or can't read the file, which means re-encoding
+pub async fn probe_media(path: &Path) -> Option<MediaProbe> {
+    let output = AsyncCommand::new("ffprobe")
+        .args(["-v", "error", "-show_entries"])
+        .arg("stream=codec_type,codec_name,height")
+        .args(["-of", "json"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let size_bytes = std::fs::metadata(path).ok()?.len();
+    MediaProbe::from_ffprobe_json(&String::from_utf8_lossy(&output.stdout), size_bytes)
+}
+
+/// Re-encodes a remuxed download in place for `--max-concurrent-ffmpeg`, returning the
+/// finished `.mp4`; the reason is ffmpeg's last error line
+pub async fn reencode_download(
+    input: &Path,
+    preset: &SocialMediaPreset,
+    keep_subs: bool,
+    fit_size: bool,
+) -> std::result::Result<PathBuf, String> {
+    let output = input.with_extension("mp4");
+    let partial = input.with_extension("encoding.mp4");
+    if let Err(reason) = encode(input, &partial, preset, true, keep_subs, fit_size).await {
+        let _ = std::fs::remove_file(&partial);
+        return Err(reason);
+    }
+    std::fs::rename(&partial, &output)
+        .map_err(|e| format!("Could not replace {}: {e}", output.display()))?;
+    if input != output {
+        let _ = std::fs::remove_file(input);
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::SocialMediaTarget;
+
+    #[test]
+    fn test_url_vs_path_classification() {
+        assert!(!is_local_input("https://www.youtube.com/watch?v=abc"));
+        assert!(!is_local_input("ftp://example.com/clip.mkv"));
+        assert!(!is_local_input("not-a-url"));
+        assert!(is_local_input("~/Videos/*.mkv"));
+        assert!(is_local_input("./clip.mkv"));
+        assert!(is_local_input("/srv/media/clip.mkv"));
+        assert!(is_local_input("./clips/*.mp4"));
+        // A URL typed without its scheme is still a URL, even with glob characters
+        assert!(!is_local_input("youtube.com/watch?v=abc"));
+        assert!(!is_local_input("www.youtube.com/watch?v=abc"));
+        assert!(is_local_input("Videos/*.mkv"));
+        assert!(is_local_input("clip?.mkv"));
+        assert!(is_local_input("*.mp4"));
+    }
+
+    #[test]
+    fn test_glob_expansion() {
+        let dir = std::env::temp_dir().join(format!("ytrs-glob-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["b.mkv", "a.mkv", "notes.txt"] {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+
+        let pattern = format!("{}/*.mkv", dir.display());
+        let files = expand_local_input(&pattern).unwrap();
+        let single = expand_local_input(&dir.join("notes.txt").to_string_lossy()).unwrap();
+        let none = expand_local_input(&format!("{}/*.webm", dir.display())).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(files, [dir.join("a.mkv"), dir.join("b.mkv")]);
+        assert_eq!(single, [dir.join("notes.txt")]);
+        assert!(none.is_empty());
+        assert!(expand_local_input("/tmp/[oops").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_local_dry_run_commands() {
+        let files = [
+            PathBuf::from("/videos/a.mkv"),
+            PathBuf::from("/videos/b c.mkv"),
+        ];
+        let discord = SocialMediaTarget::Discord.preset();
+
+        let commands = local_dry_run_commands(&files, &discord, None, false, false).await;
+
+        assert_eq!(commands.len(), 2);
+        assert!(commands[0].starts_with("ffmpeg -hide_banner -nostdin -n -i /videos/a.mkv "));
+        assert!(commands[0].ends_with(" '/videos/a [share].mp4'"));
+        assert!(commands[1].contains(" -i '/videos/b c.mkv' "));
+    }
+
+    #[test]
+    fn test_remux_only_when_source_fits_preset() {
+        const H264_1080: &str = r#"{"streams": [
+            {"codec_type": "video", "codec_name": "h264", "height": 1080},
+            {"codec_type": "audio", "codec_name": "aac"}
+        ]}"#;
+        const VP9_1080: &str = r#"{"streams": [
+            {"codec_type": "video", "codec_name": "vp9", "height": 1080},
+            {"codec_type": "audio", "codec_name": "opus"}
+        ]}"#;
+        let discord = SocialMediaTarget::Discord.preset();
+        let small = 10 * 1024 * 1024;
+
+        let compatible = MediaProbe::from_ffprobe_json(H264_1080, small).unwrap();
+        assert_eq!(compatible.video_codec.as_deref(), Some("h264"));
+        assert_eq!(compatible.height, Some(1080));
+        assert!(compatible.fits_preset(&discord));
+
+        let vp9 = MediaProbe::from_ffprobe_json(VP9_1080, small).unwrap();
+        assert!(!vp9.fits_preset(&discord));
+        // Right codecs, but too tall for Instagram or too big for Discord
+        assert!(!compatible.fits_preset(&SocialMediaTarget::Instagram.preset()));
+        let too_big = MediaProbe::from_ffprobe_json(H264_1080, 30 * 1024 * 1024).unwrap();
+        assert!(!too_big.fits_preset(&discord));
+
+        assert_eq!(MediaProbe::from_ffprobe_json("not json", small), None);
+        let no_video = MediaProbe::from_ffprobe_json(r#"{"streams": []}"#, small).unwrap();
+        assert!(!no_video.fits_preset(&discord));
+    }
+
+    #[test]
+    fn test_fits_preset_follows_preset_codecs() {
+        const HEVC_OPUS: &str = r#"{"streams": [
+            {"codec_type": "video", "codec_name": "hevc", "height": 720},
+            {"codec_type": "audio", "codec_name": "opus"}
+        ]}"#;
+        let hevc = SocialMediaPreset {
+            video_codec: "libx265".to_string(),
+            audio_codec: "libopus".to_string(),
+            ..SocialMediaTarget::Discord.preset()
+        };
+        let small = 10 * 1024 * 1024;
+
+        let probe = MediaProbe::from_ffprobe_json(HEVC_OPUS, small).unwrap();
+        assert!(probe.fits_preset(&hevc));
+        assert!(!probe.fits_preset(&SocialMediaTarget::Discord.preset()));
+
+        let h264 = MediaProbe {
+            video_codec: Some("h264".to_string()),
+            audio_codec: Some("aac".to_string()),
+            height: Some(720),
+            size_bytes: small,
+        };
+        assert!(!h264.fits_preset(&hevc));
+        let nvenc = SocialMediaPreset {
+            video_codec: "h264_nvenc".to_string(),
+            ..SocialMediaTarget::Discord.preset()
+        };
+        assert!(h264.fits_preset(&nvenc));
+    }
+
+    #[test]
+    fn test_reencode_args_use_socm_preset() {
+        let args = reencode_args(
+            Path::new("/in/clip.mkv"),
+            Path::new("/out/clip [share].mp4"),
+            &SocialMediaTarget::Discord.preset(),
+            false,
+            false,
+        );
+
+        assert_eq!(args[2], "-n");
+        assert!(!args.contains(&"-map".to_string()));
+        assert_eq!(args[4], "/in/clip.mkv");
+        assert!(args.contains(&"scale=-2:'min(ih,1080)'".to_string()));
+        assert!(args.windows(2).any(|w| w == ["-c:v", "libx264"]));
+        assert_eq!(args.last().unwrap(), "/out/clip [share].mp4");
+        assert_eq!(
+            local_output_path(Path::new("/in/clip.mkv"), None),
+            PathBuf::from("/in/clip [share].mp4")
+        );
+
+        let with_subs = reencode_args(
+            Path::new("/in/clip.webm"),
+            Path::new("/in/clip.encoding.mp4"),
+            &SocialMediaTarget::Discord.preset(),
+            true,
+            true,
+        );
+        assert!(with_subs.windows(2).any(|w| w == ["-map", "0:s?"]));
+        assert!(with_subs.windows(2).any(|w| w == ["-c:s", "mov_text"]));
+    }
+
+    #[test]
+    fn test_target_video_bitrate() {
+        let whatsapp = SocialMediaTarget::WhatsApp.preset();
+        // 16 MiB is 134217.728 kbit; 97% of it over 60s is 2169.85 kbps, less 128k audio
+        assert_eq!(target_video_kbps(&whatsapp, 60.0), Some(2041));
+        // Twice as long, half the total bitrate
+        assert_eq!(target_video_kbps(&whatsapp, 120.0), Some(956));
+
+        let discord = SocialMediaTarget::Discord.preset();
+        assert_eq!(target_video_kbps(&discord, 30.0), Some(6620));
+
+        // The audio alone would overrun the cap
+        assert_eq!(target_video_kbps(&whatsapp, 3600.0), None);
+        assert_eq!(target_video_kbps(&whatsapp, 0.0), None);
+        assert_eq!(target_video_kbps(&whatsapp, f64::NAN), None);
+        let odd_bitrate = SocialMediaPreset {
+            audio_bitrate: "loud".to_string(),
+            ..whatsapp
+        };
+        assert_eq!(target_video_kbps(&odd_bitrate, 60.0), None);
+    }
+
+    #[test]
+    fn test_bitrate_units() {
+        assert_eq!(bitrate_kbps("128k"), Some(128.0));
+        assert_eq!(bitrate_kbps("1M"), Some(1000.0));
+        assert_eq!(bitrate_kbps("96000"), Some(96.0));
+        assert_eq!(bitrate_kbps("fast"), None);
+        assert_eq!(bitrate_kbps(""), None);
+    }
+
+    #[test]
+    fn test_two_pass_args() {
+        let [analyse, encode] = two_pass_args(
+            Path::new("/in/clip.webm"),
+            Path::new("/in/clip.encoding.mp4"),
+            &SocialMediaTarget::WhatsApp.preset(),
+            true,
+            true,
+            2041,
+        );
+        let has = |args: &[String], pair: [&str; 2]| args.windows(2).any(|w| w == pair);
+
+        for (args, pass) in [(&analyse, "1"), (&encode, "2")] {
+            assert!(has(args, ["-b:v", "2041k"]));
+            assert!(has(args, ["-pass", pass]));
+            assert!(has(args, ["-passlogfile", "/in/clip.encoding.passlog"]));
+            assert!(!args.contains(&"-crf".to_string()));
+        }
+        assert!(has(&analyse, ["-f", "null"]));
+        assert!(analyse.contains(&"-an".to_string()));
+        assert!(!analyse.contains(&"-map".to_string()));
+        assert!(has(&encode, ["-b:a", "128k"]));
+        assert!(has(&encode, ["-map", "0:s?"]));
+        assert_eq!(encode.last().unwrap(), "/in/clip.encoding.mp4");
+    }
+
+    #[test]
+    fn test_verify_decision() {
+        let probe = |json: &str, size_bytes| MediaProbe::from_ffprobe_json(json, size_bytes);
+        let video = probe(
+            r#"{"streams": [{"codec_type": "video", "codec_name": "vp9", "height": 720}]}"#,
+            1024,
+        );
+        let audio = probe(
+            r#"{"streams": [{"codec_type": "audio", "codec_name": "opus"}]}"#,
+            1024,
+        );
+        assert!(MediaProbe::check_playable(video.as_ref()).is_ok());
+        assert!(MediaProbe::check_playable(audio.as_ref()).is_ok());
+
+        // ffprobe exited non-zero, e.g. on a truncated merge with no moov atom
+        assert!(MediaProbe::check_playable(None).is_err());
+        let no_streams = probe(r#"{"streams": []}"#, 1024);
+        assert!(MediaProbe::check_playable(no_streams.as_ref()).is_err());
+        let data_only = probe(
+            r#"{"streams": [{"codec_type": "data", "codec_name": "bin_data"}]}"#,
+            1024,
+        );
+        assert!(MediaProbe::check_playable(data_only.as_ref()).is_err());
+        let empty = probe(
+            r#"{"streams": [{"codec_type": "audio", "codec_name": "opus"}]}"#,
+            0,
+        );
+        assert!(MediaProbe::check_playable(empty.as_ref()).is_err());
+    }
+}
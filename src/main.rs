@@ -1,63 +1,262 @@
-//! ytrs - High-performance yt-dlp wrapper with social media optimization
-
-mod args_builder;
-mod cli;
-mod config;
-mod dependencies;
-mod downloader;
-mod error;
-mod mode;
-mod url_validator;
-
-use clap::Parser;
+//! Command-line shell over the ytrs library
+
+use std::path::PathBuf;
+
+use clap::{CommandFactory, FromArgMatches};
 use colored::Colorize;
+use tokio::runtime::Runtime;
+
+use ytrs::args_builder::thumbnail_incompatible_container;
+use ytrs::cli::{Cli, Command};
+use ytrs::config::{MIN_DEPENDENCY_VERSIONS, OPTIONAL_DEPENDENCIES, REQUIRED_DEPENDENCIES};
+use ytrs::config_file::load_config;
+use ytrs::cookies::{export_cookies, list_browser_profiles};
+use ytrs::dependencies::{
+    build_dependency_report, check_dependencies, check_versions, installed_version,
+    resolve_dependency,
+};
+use ytrs::downloader::{DownloadOptions, download_batch, download_plan, render_batch_plan};
+use ytrs::info::{preview_format, render_format_preview, run_info_commands};
+use ytrs::local_media::{
+    expand_local_input, is_local_input, local_dry_run_commands, reencode_local_files,
+};
+use ytrs::mode::{DownloadMode, SocialMediaPreset};
+use ytrs::prompt::confirm;
+use ytrs::report::DownloadReport;
+use ytrs::schema::output_schema;
+use ytrs::theme::Themed;
+use ytrs::url_validator::{load_url_list, render_sanitize_report, sanitize_and_deduplicate};
+use ytrs::{Result, YtrsError, theme};
+
+fn run(mut cli: Cli) -> Result<()> {
+    theme::install(cli.theme);
+
+    if let Some(Command::ExportCookies { from, to }) = &cli.command {
+        check_dependencies(&["yt-dlp"])?;
+        return export_cookies(from, to);
+    }
 
-use crate::cli::Cli;
-use crate::config::REQUIRED_DEPENDENCIES;
-use crate::dependencies::check_dependencies;
-use crate::downloader::{download_batch, download_single};
-use crate::error::{Result, YtrsError};
-use crate::url_validator::validate_url;
+    if let Some(Command::Schema) = &cli.command {
+        println!("{}", serde_json::to_string_pretty(&output_schema())?);
+        return Ok(());
+    }
 
-fn run(cli: Cli) -> Result<()> {
-    check_dependencies(REQUIRED_DEPENDENCIES)?;
+    if cli.check_deps {
+        print!(
+            "{}",
+            build_dependency_report(
+                REQUIRED_DEPENDENCIES,
+                OPTIONAL_DEPENDENCIES,
+                resolve_dependency
+            )
+        );
+        check_dependencies(REQUIRED_DEPENDENCIES)?;
+        return check_versions(MIN_DEPENDENCY_VERSIONS, installed_version);
+    }
 
-    let mode = cli.download_mode()?;
+    if let Some(browser) = cli.list_browser_profiles {
+        list_browser_profiles(browser);
+        return Ok(());
+    }
 
-    println!("{} {}", "Mode:".dimmed(), mode.to_string().cyan());
+    let info_commands = cli.info_commands()?;
+    if !info_commands.is_empty() {
+        check_dependencies(&["yt-dlp"])?;
+        return run_info_commands(&info_commands);
+    }
 
-    let destination = cli.destination.as_deref();
-    let cookies = cli.cookies_from.as_deref();
+    cli.apply_sync()?;
+    let options = cli.download_options()?;
 
-    if cli.urls.len() == 1 {
-        let url = cli.urls[0].trim();
-        if !validate_url(url) {
-            return Err(YtrsError::NoValidUrls);
+    if cli.print_config {
+        println!("{}", serde_json::to_string_pretty(&options)?);
+        return Ok(());
+    }
+
+    let urls = match &cli.command {
+        Some(Command::Retry { failed_log }) => {
+            let urls = load_url_list(failed_log)?;
+            if urls.is_empty() {
+                eprintln!(
+                    "{} {} contains no URLs, nothing to retry",
+                    "Warning:".warning(),
+                    failed_log.display()
+                );
+                return Ok(());
+            }
+            urls
+        }
+        Some(Command::RetryReport { report }) => {
+            let urls = DownloadReport::load(report)?.failed_urls();
+            if urls.is_empty() {
+                eprintln!(
+                    "{} {} has no failed URLs, nothing to retry",
+                    "Warning:".warning(),
+                    report.display()
+                );
+                return Ok(());
+            }
+            urls
         }
+        _ => {
+            let mut urls = std::mem::take(&mut cli.urls);
+            if let Some(batch_file) = &cli.batch_file {
+                urls.extend(load_url_list(batch_file)?);
+            }
+            urls
+        }
+    };
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    let (local_inputs, urls): (Vec<String>, Vec<String>) =
+        urls.into_iter().partition(|input| is_local_input(input));
 
-        tokio::runtime::Builder::new_multi_thread()
-            .enable_all()
-            .build()?
-            .block_on(download_single(url, destination, cookies, mode))
+    if cli.sanitize_report {
+        println!(
+            "{}",
+            render_sanitize_report(&sanitize_and_deduplicate(urls, options.dedupe))
+        );
+        return Ok(());
+    }
+
+    if cli.plan {
+        let cleaned = sanitize_and_deduplicate(urls.clone(), options.dedupe).kept;
+        print!("{}", render_batch_plan(&cleaned, &options));
+        if !cli.yes {
+            return Ok(());
+        }
+    }
+
+    let local_files = if local_inputs.is_empty() {
+        None
+    } else {
+        let DownloadMode::SocialMedia(preset) = &options.mode else {
+            return Err(YtrsError::InvalidModeCombo(
+                "Local files can only be re-encoded with --socm".to_string(),
+            ));
+        };
+        let mut files = Vec::new();
+        for input in &local_inputs {
+            let matched = expand_local_input(input)?;
+            if matched.is_empty() {
+                eprintln!("{} No files match {input}", "Warning:".warning());
+            }
+            files.extend(matched);
+        }
+        Some((files, preset))
+    };
+    let reencode_local = || match &local_files {
+        Some((files, preset)) => run_local_reencodes(&runtime, files, preset, &options),
+        None => Ok(()),
+    };
+    if urls.is_empty() && local_files.is_some() {
+        return reencode_local();
+    }
+
+    // A dry run only prints commands, so the tools don't have to be installed yet
+    if !options.dry_run {
+        check_dependencies(&options.required_dependencies())?;
+        check_versions(MIN_DEPENDENCY_VERSIONS, installed_version)?;
+    }
+
+    if options.prints_status() && !options.dry_run {
+        println!("{} {}", "Mode:".dimmed(), options.mode.to_string().info());
+        if cli.verbose
+            && let DownloadMode::SocialMedia(preset) = &options.mode
+        {
+            println!("{} {}", "Preset:".dimmed(), preset);
+        }
+    }
+    if let Some(ext) = &options.force_ext {
+        eprintln!(
+            "{} --force-ext {ext} re-encodes anything not already {ext}, which is slow and lossy",
+            "Warning:".warning()
+        );
+    }
+    if options.embed_thumbnail
+        && let Some(container) =
+            thumbnail_incompatible_container(&options.mode, options.force_ext.as_deref())
+    {
+        eprintln!(
+            "{} --embed-thumbnail can't write into {container}, so those downloads will fail to embed it; --force-ext mkv avoids that",
+            "Warning:".warning()
+        );
+    }
+
+    if cli.preview {
+        let [url] = urls.as_slice() else {
+            return Err(YtrsError::SingleUrlOnly("--preview"));
+        };
+        let plan = download_plan(&options.mode, options.also_archive);
+        let final_stage = &plan[plan.len() - 1];
+        let args = options.ytdlp_args(final_stage, false);
+        print!(
+            "{}",
+            render_format_preview(&preview_format(url.trim(), &args)?)
+        );
+        if !cli.yes && !confirm("Proceed with download?")? {
+            return Ok(());
+        }
+    }
+
+    #[cfg(feature = "net-check")]
+    let urls = if cli.net_check {
+        runtime.block_on(ytrs::netcheck::net_check(urls, cli.skip_unreachable))
     } else {
-        tokio::runtime::Builder::new_multi_thread()
-            .enable_all()
-            .build()?
-            .block_on(download_batch(
-                cli.urls,
-                destination,
-                cookies,
-                mode,
-                cli.parallel,
-            ))
+        urls
+    };
+    // Past every prompt, so declining one leaves local files untouched too
+    let local_result = reencode_local();
+    // A single URL is just a batch of one, sharing validation and the summary
+    runtime.block_on(download_batch(urls, options))?;
+    local_result
+}
+
+/// Re-encodes local files with the socm preset, or under `--dry-run` prints the ffmpeg
+/// commands instead
+fn run_local_reencodes(
+    runtime: &Runtime,
+    files: &[PathBuf],
+    preset: &SocialMediaPreset,
+    options: &DownloadOptions,
+) -> Result<()> {
+    let destination = options.destination_path.as_deref();
+    if options.dry_run {
+        let commands = runtime.block_on(local_dry_run_commands(
+            files,
+            preset,
+            destination,
+            options.force,
+            options.fit_size,
+        ));
+        for command in commands {
+            println!("{command}");
+        }
+        return Ok(());
     }
+    check_dependencies(&["ffmpeg"])?;
+    runtime.block_on(reencode_local_files(
+        files,
+        preset,
+        destination,
+        options.force,
+        options.fit_size,
+    ))
 }
 
 fn main() {
-    let cli = Cli::parse();
+    // Parsed by hand so the config file can tell flags that were typed from defaults
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
-    if let Err(e) = run(cli) {
-        eprintln!("{} {}", "Error:".red().bold(), e);
+    let result = load_config().and_then(|config| {
+        config.apply(&mut cli, &matches);
+        run(cli)
+    });
+    if let Err(e) = result {
+        eprintln!("{} {}", "Error:".error().bold(), e);
         std::process::exit(1);
     }
 }
@@ -0,0 +1,163 @@
+//! `--manifest`: a CSV of the batch for spreadsheets, one row per downloaded file
+
+use std::borrow::Cow;
+use std::path::Path;
+
+use crate::archive::CapturedOutput;
+use crate::error::Result;
+use crate::report::{DownloadReport, ReportStatus};
+
+const HEADER: &str = "url,title,id,path,status,size";
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ManifestRow<'a> {
+    pub url: &'a str,
+    pub title: &'a str,
+    pub id: &'a str,
+    pub path: Cow<'a, str>,
+    pub status: ReportStatus,
+    pub size: Option<u64>,
+}
+
+impl ManifestRow<'_> {
+    #[must_use]
+    pub fn to_csv(&self) -> String {
+        let status = match self.status {
+            ReportStatus::Completed => "completed",
+            ReportStatus::Failed => "failed",
+            ReportStatus::Skipped => "skipped",
+        };
+        let size = self.size.map(|size| size.to_string()).unwrap_or_default();
+        [
+            self.url,
+            self.title,
+            self.id,
+            self.path.as_ref(),
+            status,
+            size.as_str(),
+        ]
+        .map(csv_field)
+        .join(",")
+    }
+}
+
+/// Quotes a field holding a comma, quote or line break, doubling any quotes (RFC 4180)
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Report entries in order, expanded to one row per captured file; URLs without any
+/// captured file get a single row with only the URL and status
+#[must_use]
+pub fn manifest_rows<'a>(
+    report: &'a DownloadReport,
+    outputs: &'a [(String, Vec<CapturedOutput>)],
+) -> Vec<ManifestRow<'a>> {
+    let mut rows = Vec::new();
+    for entry in &report.entries {
+        let files = outputs
+            .iter()
+            .find(|(url, _)| *url == entry.url)
+            .map_or(&[][..], |(_, files)| files.as_slice());
+        if files.is_empty() {
+            rows.push(ManifestRow {
+                url: &entry.url,
+                title: "",
+                id: "",
+                path: Cow::Borrowed(""),
+                status: entry.status,
+                size: None,
+            });
+        }
+        rows.extend(files.iter().map(|file| ManifestRow {
+            url: &entry.url,
+            title: &file.title,
+            id: &file.id,
+            path: file.path.to_string_lossy(),
+            status: entry.status,
+            size: std::fs::metadata(&file.path).ok().map(|m| m.len()),
+        }));
+    }
+    rows
+}
+
+#[must_use]
+pub fn render_manifest(rows: &[ManifestRow]) -> String {
+    let mut csv = format!("{HEADER}\n");
+    for row in rows {
+        csv.push_str(&row.to_csv());
+        csv.push('\n');
+    }
+    csv
+}
+
+pub fn write_manifest(
+    path: &Path,
+    report: &DownloadReport,
+    outputs: &[(String, Vec<CapturedOutput>)],
+) -> Result<()> {
+    std::fs::write(path, render_manifest(&manifest_rows(report, outputs)))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::report::ReportEntry;
+
+    #[test]
+    fn test_row_quotes_fields() {
+        let row = ManifestRow {
+            url: "https://a.example/1",
+            title: r#"Cats, dogs and "birds""#,
+            id: "abc",
+            path: Cow::Borrowed("/videos/cats.webm"),
+            status: ReportStatus::Completed,
+            size: Some(1024),
+        };
+
+        assert_eq!(
+            row.to_csv(),
+            r#"https://a.example/1,"Cats, dogs and ""birds""",abc,/videos/cats.webm,completed,1024"#
+        );
+    }
+
+    #[test]
+    fn test_rows_follow_report() {
+        let entry = |url: &str, status| ReportEntry {
+            url: url.to_string(),
+            status,
+            reason: None,
+        };
+        let report = DownloadReport {
+            entries: vec![
+                entry("https://a.example/list", ReportStatus::Completed),
+                entry("https://a.example/2", ReportStatus::Failed),
+            ],
+        };
+        let file = |id: &str| CapturedOutput {
+            extractor: "Youtube".to_string(),
+            id: id.to_string(),
+            title: format!("Video {id}"),
+            path: PathBuf::from(format!("/nonexistent/{id}.webm")),
+        };
+        let outputs = [(
+            "https://a.example/list".to_string(),
+            vec![file("a"), file("b")],
+        )];
+
+        assert_eq!(
+            render_manifest(&manifest_rows(&report, &outputs)),
+            "url,title,id,path,status,size\n\
+             https://a.example/list,Video a,a,/nonexistent/a.webm,completed,\n\
+             https://a.example/list,Video b,b,/nonexistent/b.webm,completed,\n\
+             https://a.example/2,,,,failed,\n"
+        );
+    }
+}
@@ -1,12 +1,23 @@
 //! Download modes and social media presets
 
-use crate::cli::SocialMediaTarget;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{AudioCodec, QualityPreset, SocialMediaTarget, VideoCodec};
+use crate::config::{
+    DEFAULT_MAX_HEIGHT, FORMAT_BALANCED, FORMAT_SMALL, FORMAT_SORT_AV1, FORMAT_SORT_BALANCED,
+    FORMAT_SORT_DEFAULT, FORMAT_SORT_H264, FORMAT_SORT_SMALL, MAX_CRF,
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum DownloadMode {
     #[default]
     Default,
-    SocialMedia(SocialMediaTarget),
+    SocialMedia(SocialMediaPreset),
     AudioOnly,
     VideoOnly,
 }
@@ -15,117 +26,343 @@ impl std::fmt::Display for DownloadMode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Default => write!(f, "Default (Max Quality)"),
-            Self::SocialMedia(target) => write!(f, "Social Media ({target})"),
+            Self::SocialMedia(preset) => write!(f, "Social Media ({})", preset.name),
             Self::AudioOnly => write!(f, "Audio Only"),
             Self::VideoOnly => write!(f, "Video Only"),
         }
     }
 }
 
-#[derive(Clone, Debug)]
+/// Re-encode settings for `--socm`; also the shape of a `[socm_presets.<name>]`
+/// table in the config file, where everything but the size and height caps is optional
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct SocialMediaPreset {
-    #[allow(dead_code)]
+    /// The target's display name, or the table name for config-file presets
+    #[serde(skip_deserializing)]
+    pub name: String,
     pub max_size_mb: u32,
     pub max_height: u32,
-    pub video_codec: &'static str,
-    pub audio_codec: &'static str,
-    pub audio_bitrate: &'static str,
+    #[serde(default = "default_video_codec")]
+    pub video_codec: String,
+    #[serde(default = "default_audio_codec")]
+    pub audio_codec: String,
+    #[serde(default = "default_audio_bitrate")]
+    pub audio_bitrate: String,
+    #[serde(default = "default_crf")]
     pub crf: u8,
-    pub preset: &'static str,
+    #[serde(default = "default_encoder_preset")]
+    pub preset: String,
 }
 
-impl SocialMediaTarget {
-    #[must_use]
-    pub const fn preset(self) -> SocialMediaPreset {
-        match self {
-            Self::WhatsApp => SocialMediaPreset {
-                max_size_mb: 16,
-                max_height: 1080,
-                video_codec: "libx264",
-                audio_codec: "aac",
-                audio_bitrate: "128k",
-                crf: 23,
-                preset: "medium",
-            },
-            Self::Discord | Self::Messenger => SocialMediaPreset {
-                max_size_mb: 25,
-                max_height: 1080,
-                video_codec: "libx264",
-                audio_codec: "aac",
-                audio_bitrate: "160k",
-                crf: 20,
-                preset: "medium",
-            },
-            Self::Instagram => SocialMediaPreset {
-                max_size_mb: 15,
-                max_height: 720,
-                video_codec: "libx264",
-                audio_codec: "aac",
-                audio_bitrate: "128k",
-                crf: 23,
-                preset: "medium",
-            },
-            Self::Signal => SocialMediaPreset {
-                max_size_mb: 100,
-                max_height: 1080,
-                video_codec: "libx264",
-                audio_codec: "aac",
-                audio_bitrate: "192k",
-                crf: 18,
-                preset: "slow",
-            },
-            Self::Telegram => SocialMediaPreset {
-                max_size_mb: 2000,
-                max_height: 2160,
-                video_codec: "libx264",
-                audio_codec: "aac",
-                audio_bitrate: "192k",
-                crf: 18,
-                preset: "slow",
-            },
+fn default_video_codec() -> String {
+    "libx264".to_string()
+}
+
+fn default_audio_codec() -> String {
+    "aac".to_string()
+}
+
+fn default_audio_bitrate() -> String {
+    "128k".to_string()
+}
+
+const fn default_crf() -> u8 {
+    23
+}
+
+fn default_encoder_preset() -> String {
+    "medium".to_string()
+}
+
+/// Presets from the config file, keyed by the name `--socm` takes
+pub type UserPresets = BTreeMap<String, SocialMediaPreset>;
+
+/// Resolves a `--socm` name; a config-file preset wins over a built-in target of the same name
+#[must_use]
+pub fn resolve_preset(name: &str, user_presets: &UserPresets) -> Option<SocialMediaPreset> {
+    user_presets.get(name).cloned().or_else(|| {
+        SocialMediaTarget::from_str(name, true)
+            .ok()
+            .map(SocialMediaTarget::preset)
+    })
+}
+
+/// One-line summary for `--verbose`
+impl std::fmt::Display for SocialMediaPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "size cap {} MB, up to {}p, {} crf {} preset {}, {} {}",
+            self.max_size_mb,
+            self.max_height,
+            self.video_codec,
+            self.crf,
+            self.preset,
+            self.audio_codec,
+            self.audio_bitrate
+        )
+    }
+}
+
+impl SocialMediaPreset {
+    /// Checks a config-file preset before anything is handed to ffmpeg
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.video_codec.trim().is_empty() || self.audio_codec.trim().is_empty() {
+            return Err(format!("socm preset '{}' has an empty codec", self.name));
+        }
+        if self.crf > MAX_CRF {
+            return Err(format!(
+                "socm preset '{}' has crf {}, expected 0-{MAX_CRF}",
+                self.name, self.crf
+            ));
+        }
+        if self.max_size_mb == 0 || self.max_height == 0 {
+            return Err(format!(
+                "socm preset '{}' needs a max_size_mb and max_height above 0",
+                self.name
+            ));
         }
+        Ok(())
     }
 
+    /// Capped selection first, then any best format so sources without a
+    /// small enough rendition never hard-fail; `format_sort`'s `res:` limit
+    /// keeps the fallback on the nearest resolution to the cap
     #[must_use]
-    pub fn format_selector(self) -> String {
-        let preset = self.preset();
+    pub fn format_selector(&self) -> String {
         format!(
-            "bv*[height<={}]+ba/b[height<={}]",
-            preset.max_height, preset.max_height
+            "bv*[height<={}]+ba/b[height<={}]/bv*+ba/b",
+            self.max_height, self.max_height
         )
     }
 
     #[must_use]
-    pub fn format_sort(self) -> String {
-        let preset = self.preset();
-        format!("res:{},vcodec:avc,acodec:aac,size", preset.max_height)
+    pub fn format_sort(&self) -> String {
+        format!("res:{},vcodec:avc,acodec:aac,size", self.max_height)
     }
 
     #[must_use]
-    pub fn postprocessor_args(self) -> String {
-        let preset = self.preset();
+    pub fn postprocessor_args(&self) -> String {
         format!(
             "ffmpeg:-c:v {} -preset {} -crf {} -c:a {} -b:a {} -movflags +faststart",
-            preset.video_codec, preset.preset, preset.crf, preset.audio_codec, preset.audio_bitrate,
+            self.video_codec, self.preset, self.crf, self.audio_codec, self.audio_bitrate,
         )
     }
 }
 
+impl SocialMediaTarget {
+    #[must_use]
+    pub fn preset(self) -> SocialMediaPreset {
+        let (max_size_mb, max_height, audio_bitrate, crf, preset) = match self {
+            Self::WhatsApp => (16, 1080, "128k", 23, "medium"),
+            Self::Discord | Self::Messenger => (25, 1080, "160k", 20, "medium"),
+            Self::Instagram => (15, 720, "128k", 23, "medium"),
+            Self::Signal => (100, 1080, "192k", 18, "slow"),
+            Self::Telegram => (2000, 2160, "192k", 18, "slow"),
+        };
+        SocialMediaPreset {
+            name: self.to_string(),
+            max_size_mb,
+            max_height,
+            video_codec: default_video_codec(),
+            audio_codec: default_audio_codec(),
+            audio_bitrate: audio_bitrate.to_string(),
+            crf,
+            preset: preset.to_string(),
+        }
+    }
+}
+
+impl QualityPreset {
+    /// `max_height` replaces the archive preset's 2160p cap; the other presets keep theirs
+    #[must_use]
+    pub fn format_selector(self, max_height: Option<u32>) -> Cow<'static, str> {
+        match self {
+            Self::Archive => {
+                let height = max_height.unwrap_or(DEFAULT_MAX_HEIGHT);
+                Cow::Owned(format!("bv*[height<={height}]+ba/b[height<={height}]"))
+            }
+            Self::Balanced => Cow::Borrowed(FORMAT_BALANCED),
+            Self::Small => Cow::Borrowed(FORMAT_SMALL),
+        }
+    }
+
+    /// `codec` picks the archive preset's codec order; the other presets keep theirs
+    #[must_use]
+    pub const fn format_sort(self, codec: VideoCodec) -> &'static str {
+        match self {
+            Self::Archive => codec.format_sort(),
+            Self::Balanced => FORMAT_SORT_BALANCED,
+            Self::Small => FORMAT_SORT_SMALL,
+        }
+    }
+}
+
+impl VideoCodec {
+    #[must_use]
+    pub const fn format_sort(self) -> &'static str {
+        match self {
+            Self::Vp9 => FORMAT_SORT_DEFAULT,
+            Self::Av1 => FORMAT_SORT_AV1,
+            Self::H264 => FORMAT_SORT_H264,
+        }
+    }
+}
+
+impl AudioCodec {
+    #[must_use]
+    pub const fn encoder(self) -> &'static str {
+        match self {
+            Self::Aac => "aac",
+            Self::Opus => "libopus",
+            Self::Mp3 => "libmp3lame",
+        }
+    }
+
+    /// Args for the `FFmpegCopyStream` postprocessor, which runs on every download
+    /// unlike the merger or remuxer
+    #[must_use]
+    pub fn postprocessor_args(self) -> String {
+        format!("CopyStream:-c:v copy -c:a {}", self.encoder())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_quality_preset_format_sort() {
+        assert_eq!(
+            QualityPreset::Archive.format_sort(VideoCodec::Vp9),
+            "res,fps,vcodec:vp9.2,vcodec:vp9,vcodec:av01,vcodec:hev1,vcodec:avc,hdr:12,acodec:opus,acodec:flac,acodec:aac,acodec:mp3,size"
+        );
+        assert_eq!(
+            QualityPreset::Balanced.format_sort(VideoCodec::Av1),
+            "res:1080,vcodec:avc,acodec:aac,fps,size"
+        );
+        assert_eq!(
+            QualityPreset::Small.format_sort(VideoCodec::Vp9),
+            "res:480,+size,+br"
+        );
+
+        assert_eq!(
+            QualityPreset::Archive.format_selector(None),
+            "bv*[height<=2160]+ba/b[height<=2160]"
+        );
+        assert_eq!(
+            QualityPreset::Archive.format_selector(Some(1080)),
+            "bv*[height<=1080]+ba/b[height<=1080]"
+        );
+        assert!(
+            QualityPreset::Balanced
+                .format_selector(None)
+                .starts_with("bv*[height<=1080]")
+        );
+        assert!(
+            QualityPreset::Small
+                .format_selector(None)
+                .ends_with("/wv*+wa/w")
+        );
+    }
+
+    #[test]
+    fn test_codec_format_sort() {
+        let position = |codec: VideoCodec, vcodec: &str| codec.format_sort().find(vcodec).unwrap();
+
+        assert!(position(VideoCodec::Av1, "vcodec:av01") < position(VideoCodec::Av1, "vcodec:vp9"));
+        assert!(position(VideoCodec::Vp9, "vcodec:vp9") < position(VideoCodec::Vp9, "vcodec:av01"));
+        assert!(
+            VideoCodec::H264
+                .format_sort()
+                .starts_with("res,fps,vcodec:avc,")
+        );
+        assert_eq!(
+            QualityPreset::Archive.format_sort(VideoCodec::Av1),
+            FORMAT_SORT_AV1
+        );
+    }
+
     #[test]
     fn test_download_mode_display() {
         assert_eq!(DownloadMode::Default.to_string(), "Default (Max Quality)");
         assert_eq!(DownloadMode::AudioOnly.to_string(), "Audio Only");
         assert_eq!(DownloadMode::VideoOnly.to_string(), "Video Only");
         assert_eq!(
-            DownloadMode::SocialMedia(SocialMediaTarget::Discord).to_string(),
+            DownloadMode::SocialMedia(SocialMediaTarget::Discord.preset()).to_string(),
             "Social Media (Discord)"
         );
     }
 
+    #[test]
+    fn test_preset_summary() {
+        assert_eq!(
+            SocialMediaTarget::Discord.preset().to_string(),
+            "size cap 25 MB, up to 1080p, libx264 crf 20 preset medium, aac 160k"
+        );
+        assert_eq!(
+            SocialMediaTarget::Instagram.preset().to_string(),
+            "size cap 15 MB, up to 720p, libx264 crf 23 preset medium, aac 128k"
+        );
+    }
+
+    #[test]
+    fn test_user_presets_win_over_builtins() {
+        let slow_discord = SocialMediaPreset {
+            crf: 28,
+            ..SocialMediaTarget::Discord.preset()
+        };
+        let user = UserPresets::from([("discord".to_string(), slow_discord.clone())]);
+
+        assert_eq!(resolve_preset("discord", &user), Some(slow_discord));
+        // Aliases still reach the built-in target
+        assert_eq!(
+            resolve_preset("dc", &user),
+            Some(SocialMediaTarget::Discord.preset())
+        );
+        assert_eq!(
+            resolve_preset("ig", &UserPresets::new()),
+            Some(SocialMediaTarget::Instagram.preset())
+        );
+        assert_eq!(resolve_preset("mastodon", &user), None);
+    }
+
+    #[test]
+    fn test_preset_validation() {
+        let valid = SocialMediaTarget::Discord.preset();
+        assert!(valid.validate().is_ok());
+        assert!(
+            SocialMediaPreset {
+                crf: 0,
+                ..valid.clone()
+            }
+            .validate()
+            .is_ok()
+        );
+
+        for invalid in [
+            SocialMediaPreset {
+                crf: 52,
+                ..valid.clone()
+            },
+            SocialMediaPreset {
+                video_codec: " ".to_string(),
+                ..valid.clone()
+            },
+            SocialMediaPreset {
+                audio_codec: String::new(),
+                ..valid.clone()
+            },
+            SocialMediaPreset {
+                max_height: 0,
+                ..valid.clone()
+            },
+        ] {
+            assert!(invalid.validate().is_err(), "{invalid:?}");
+        }
+    }
+
     #[test]
     fn test_whatsapp_preset() {
         let preset = SocialMediaTarget::WhatsApp.preset();
@@ -159,19 +396,37 @@ mod tests {
 
     #[test]
     fn test_format_selector() {
-        let selector = SocialMediaTarget::Instagram.format_selector();
-        assert_eq!(selector, "bv*[height<=720]+ba/b[height<=720]");
+        let selector = SocialMediaTarget::Instagram.preset().format_selector();
+        assert_eq!(selector, "bv*[height<=720]+ba/b[height<=720]/bv*+ba/b");
+    }
+
+    #[test]
+    fn test_format_selector_has_best_fallback() {
+        for target in [
+            SocialMediaTarget::WhatsApp,
+            SocialMediaTarget::Instagram,
+            SocialMediaTarget::Telegram,
+        ] {
+            let preset = target.preset();
+            let selector = preset.format_selector();
+            let capped = format!("[height<={}]", preset.max_height);
+            let last = selector.rsplit('/').next().unwrap();
+
+            assert!(selector.starts_with(&format!("bv*{capped}")));
+            assert_eq!(last, "b");
+            assert!(selector.ends_with("/bv*+ba/b"));
+        }
     }
 
     #[test]
     fn test_format_sort() {
-        let sort = SocialMediaTarget::Discord.format_sort();
+        let sort = SocialMediaTarget::Discord.preset().format_sort();
         assert_eq!(sort, "res:1080,vcodec:avc,acodec:aac,size");
     }
 
     #[test]
     fn test_postprocessor_args() {
-        let args = SocialMediaTarget::WhatsApp.postprocessor_args();
+        let args = SocialMediaTarget::WhatsApp.preset().postprocessor_args();
         assert!(args.contains("-crf 23"));
         assert!(args.contains("-b:a 128k"));
         assert!(args.contains("+faststart"));
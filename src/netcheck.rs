@@ -0,0 +1,145 @@
+//! `--net-check`: a TCP connect to each host before the batch starts, so a dead
+//! network or a blocked site shows up once instead of as a wall of failures
+
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+
+use colored::Colorize;
+use tokio::net::TcpStream;
+use url::Url;
+
+use crate::config::{NET_CHECK_PORT, NET_CHECK_TIMEOUT_SECONDS};
+use crate::theme::Themed;
+
+/// Each host once, in the order its first URL appears; unparseable URLs are left
+/// for the batch to report
+#[must_use]
+pub fn unique_hosts(urls: &[String]) -> Vec<String> {
+    let mut hosts: Vec<String> = Vec::new();
+    for host in urls.iter().filter_map(|url| url_host(url)) {
+        if !hosts.contains(&host) {
+            hosts.push(host);
+        }
+    }
+    hosts
+}
+
+fn url_host(url: &str) -> Option<String> {
+    Url::parse(url.trim()).ok()?.host_str().map(str::to_string)
+}
+
+/// Tries every host at once and returns the ones that failed or timed out, with why
+pub async fn unreachable_hosts<F, Fut>(
+    hosts: &[String],
+    timeout: Duration,
+    connect: F,
+) -> Vec<(String, String)>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = io::Result<()>>,
+{
+    let checks = hosts.iter().map(|host| {
+        let attempt = tokio::time::timeout(timeout, connect(host.clone()));
+        async move {
+            let reason = match attempt.await {
+                Ok(Ok(())) => return None,
+                Ok(Err(e)) => e.to_string(),
+                Err(_) => format!("no answer within {}s", timeout.as_secs_f64()),
+            };
+            Some((host.clone(), reason))
+        }
+    });
+    futures::future::join_all(checks)
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+async fn tcp_connect(host: String) -> io::Result<()> {
+    TcpStream::connect((host.as_str(), NET_CHECK_PORT))
+        .await
+        .map(drop)
+}
+
+/// Checks every host and reports the unreachable ones; with `skip_unreachable` their
+/// URLs are dropped from the batch, otherwise all URLs are returned
+pub async fn net_check(urls: Vec<String>, skip_unreachable: bool) -> Vec<String> {
+    let hosts = unique_hosts(&urls);
+    let unreachable = unreachable_hosts(
+        &hosts,
+        Duration::from_secs(NET_CHECK_TIMEOUT_SECONDS),
+        tcp_connect,
+    )
+    .await;
+    if unreachable.is_empty() {
+        println!(
+            "{} all {} hosts reachable",
+            "Net check:".dimmed(),
+            hosts.len()
+        );
+        return urls;
+    }
+
+    eprintln!(
+        "{} {} of {} hosts unreachable:",
+        "Net check:".warning(),
+        unreachable.len(),
+        hosts.len()
+    );
+    for (host, reason) in &unreachable {
+        eprintln!("  {} - {reason}", host.error());
+    }
+    if !skip_unreachable {
+        return urls;
+    }
+
+    let (kept, skipped): (Vec<String>, Vec<String>) = urls.into_iter().partition(|url| {
+        url_host(url).is_none_or(|host| !unreachable.iter().any(|(down, _)| *down == host))
+    });
+    for url in &skipped {
+        println!(
+            "{} {} - Host unreachable",
+            "Skipped:".warning(),
+            url.warning()
+        );
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unique_hosts_in_order() {
+        let urls = [
+            "https://www.youtube.com/watch?v=a",
+            "https://vimeo.com/1",
+            " https://www.youtube.com/watch?v=b",
+            "not a url",
+        ]
+        .map(String::from);
+        assert_eq!(unique_hosts(&urls), ["www.youtube.com", "vimeo.com"]);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_hosts_from_mock_connector() {
+        let hosts = ["up.example", "refused.example", "silent.example"].map(String::from);
+        let connect = |host: String| async move {
+            match host.as_str() {
+                "up.example" => Ok(()),
+                "refused.example" => Err(io::Error::from(io::ErrorKind::ConnectionRefused)),
+                _ => std::future::pending().await,
+            }
+        };
+
+        let unreachable = unreachable_hosts(&hosts, Duration::from_millis(50), connect).await;
+
+        let names: Vec<&str> = unreachable.iter().map(|(host, _)| host.as_str()).collect();
+        assert_eq!(names, ["refused.example", "silent.example"]);
+        assert!(unreachable[0].1.contains("refused"));
+        assert!(unreachable[1].1.contains("no answer within 0.05s"));
+    }
+}
@@ -0,0 +1,165 @@
+//! Flags downloads that are the same video re-encoded or re-uploaded, by comparing
+//! difference hashes of frames sampled with ffmpeg
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use tokio::process::Command;
+
+use crate::theme::Themed;
+
+/// Frames sampled per video, one every [`SAMPLE_INTERVAL_SECS`]
+const SAMPLE_FRAMES: usize = 16;
+const SAMPLE_INTERVAL_SECS: u32 = 5;
+/// Mean differing bits per 64-bit frame hash at or under which two videos count as the same
+pub const NEAR_DUPLICATE_BITS: f64 = 10.0;
+
+/// dHash input: 9x8 grayscale, one byte per pixel
+const FRAME_WIDTH: usize = 9;
+const FRAME_HEIGHT: usize = 8;
+const FRAME_BYTES: usize = FRAME_WIDTH * FRAME_HEIGHT;
+
+/// One 64-bit difference hash per sampled frame, in playback order
+pub type VideoHash = Vec<u64>;
+
+/// Each bit says whether a pixel is darker than its right neighbour
+#[must_use]
+fn dhash(frame: &[u8]) -> u64 {
+    let mut hash = 0;
+    for row in frame.chunks_exact(FRAME_WIDTH) {
+        for pair in row.windows(2) {
+            hash = (hash << 1) | u64::from(pair[0] < pair[1]);
+        }
+    }
+    hash
+}
+
+/// Mean Hamming distance over the frames both videos have; `None` if either has none
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn hash_distance(a: &[u64], b: &[u64]) -> Option<f64> {
+    let frames = a.len().min(b.len());
+    if frames == 0 {
+        return None;
+    }
+    let bits: u32 = a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum();
+    Some(f64::from(bits) / frames as f64)
+}
+
+/// Every pair of files within `threshold` of each other, with their distance
+#[must_use]
+pub fn find_near_duplicates(
+    hashes: &[(PathBuf, VideoHash)],
+    threshold: f64,
+) -> Vec<(PathBuf, PathBuf, f64)> {
+    let mut pairs = Vec::new();
+    for (index, (path, hash)) in hashes.iter().enumerate() {
+        for (other_path, other_hash) in &hashes[index + 1..] {
+            if let Some(distance) = hash_distance(hash, other_hash)
+                && distance <= threshold
+            {
+                pairs.push((path.clone(), other_path.clone(), distance));
+            }
+        }
+    }
+    pairs
+}
+
+/// Samples frames with ffmpeg; `None` for files it can't decode, like audio-only downloads
+pub async fn video_hash(path: &Path) -> Option<VideoHash> {
+    let filter =
+        format!("fps=1/{SAMPLE_INTERVAL_SECS},scale={FRAME_WIDTH}:{FRAME_HEIGHT},format=gray");
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-nostdin", "-i"])
+        .arg(path)
+        .args(["-vf", &filter, "-frames:v", &SAMPLE_FRAMES.to_string()])
+        .args(["-f", "rawvideo", "-"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let hash: VideoHash = output.stdout.chunks_exact(FRAME_BYTES).map(dhash).collect();
+    (!hash.is_empty()).then_some(hash)
+}
+
+/// Hashes the completed files and prints near-duplicate pairs; nothing is deleted
+pub async fn report_near_duplicates(paths: &[PathBuf]) {
+    let mut hashes = Vec::new();
+    for path in paths {
+        if let Some(hash) = video_hash(path).await {
+            hashes.push((path.clone(), hash));
+        }
+    }
+
+    let pairs = find_near_duplicates(&hashes, NEAR_DUPLICATE_BITS);
+    if pairs.is_empty() {
+        return;
+    }
+    println!(
+        "{} {} near-duplicate pairs:",
+        "Note:".warning(),
+        pairs.len()
+    );
+    for (a, b, distance) in pairs {
+        println!("  {}", a.display());
+        println!("  {} (distance {distance:.1})", b.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dhash_gradient() {
+        let rising: Vec<u8> = (0..FRAME_HEIGHT)
+            .flat_map(|_| (0..FRAME_WIDTH).map(|x| u8::try_from(x * 10).unwrap()))
+            .collect();
+        assert_eq!(dhash(&rising), u64::MAX);
+        assert_eq!(dhash(&[128; FRAME_BYTES]), 0);
+    }
+
+    #[test]
+    fn test_distance_threshold() {
+        let original = vec![0x0f0f_0f0f_0f0f_0f0f, 0xffff_0000_ffff_0000];
+        // 8 flipped bits in each frame, as a re-encode might leave
+        let reencoded = vec![0x0f0f_0f0f_0f0f_0ff0, 0xffff_0000_ffff_00ff];
+        let unrelated = vec![!original[0], !original[1]];
+
+        assert_eq!(hash_distance(&original, &reencoded), Some(8.0));
+        assert_eq!(hash_distance(&original, &unrelated), Some(64.0));
+        let hashes = [
+            (PathBuf::from("original.webm"), original.clone()),
+            (PathBuf::from("reencoded.mp4"), reencoded.clone()),
+        ];
+        assert_eq!(find_near_duplicates(&hashes, NEAR_DUPLICATE_BITS).len(), 1);
+        assert!(find_near_duplicates(&hashes, 7.0).is_empty());
+        assert_eq!(hash_distance(&original, &[]), None);
+        // A longer upload is compared over the frames both have
+        let extended = [reencoded.clone(), vec![0]].concat();
+        assert_eq!(hash_distance(&original, &extended), Some(8.0));
+    }
+
+    #[test]
+    fn test_find_near_duplicates() {
+        let hashes = vec![
+            (PathBuf::from("a.webm"), vec![0u64; 4]),
+            (PathBuf::from("b.webm"), vec![u64::MAX; 4]),
+            (PathBuf::from("a (reupload).mp4"), vec![0b111; 4]),
+        ];
+        let pairs = find_near_duplicates(&hashes, NEAR_DUPLICATE_BITS);
+
+        assert_eq!(
+            pairs,
+            [(
+                PathBuf::from("a.webm"),
+                PathBuf::from("a (reupload).mp4"),
+                3.0
+            )]
+        );
+    }
+}
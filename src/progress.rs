@@ -0,0 +1,230 @@
+//! yt-dlp progress parsing and headless progress snapshots
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::config::PROGRESS_WRITE_INTERVAL_MS;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ProgressUpdate {
+    pub percent: f64,
+    pub speed: Option<String>,
+    pub eta: Option<String>,
+}
+
+/// Parses yt-dlp's `[download]  42.3% of ~10.00MiB at 1.23MiB/s ETA 00:05` lines
+pub fn parse_progress_line(line: &str) -> Option<ProgressUpdate> {
+    let rest = line.trim_start().strip_prefix("[download]")?;
+    let (percent, rest) = rest.split_once('%')?;
+    let percent: f64 = percent.trim().parse().ok()?;
+
+    let tokens: Vec<&str> = rest.split_whitespace().collect();
+    let value_after = |marker: &str| {
+        tokens
+            .iter()
+            .position(|t| *t == marker)
+            .and_then(|i| tokens.get(i + 1))
+            .filter(|value| !value.starts_with("Unknown"))
+            .map(ToString::to_string)
+    };
+
+    Some(ProgressUpdate {
+        percent,
+        speed: value_after("at"),
+        eta: value_after("ETA"),
+    })
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressStatus {
+    #[default]
+    Downloading,
+    Completed,
+    Failed,
+    /// Rejected by a filter such as --max-duration, which isn't a failure
+    Skipped,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct UrlProgress {
+    pub status: ProgressStatus,
+    pub percent: f64,
+    pub speed: Option<String>,
+    pub eta: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProgressSnapshot<'a> {
+    pub updated_at: u64,
+    pub downloads: &'a BTreeMap<String, UrlProgress>,
+}
+
+struct ProgressState {
+    downloads: BTreeMap<String, UrlProgress>,
+    last_write: Option<Instant>,
+}
+
+/// JSON progress file that other processes can poll while a run is in flight
+pub struct ProgressFile {
+    path: PathBuf,
+    state: Mutex<ProgressState>,
+}
+
+impl ProgressFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            state: Mutex::new(ProgressState {
+                downloads: BTreeMap::new(),
+                last_write: None,
+            }),
+        }
+    }
+
+    pub fn update(&self, url: &str, update: ProgressUpdate) {
+        self.modify(url, false, |entry| {
+            entry.percent = update.percent;
+            entry.speed = update.speed;
+            entry.eta = update.eta;
+        });
+    }
+
+    /// Status changes are always flushed, unlike throttled percentage updates
+    pub fn set_status(&self, url: &str, status: ProgressStatus) {
+        self.modify(url, true, |entry| {
+            entry.status = status;
+            if status == ProgressStatus::Completed {
+                entry.percent = 100.0;
+                entry.eta = None;
+            }
+        });
+    }
+
+    fn modify(&self, url: &str, force: bool, apply: impl FnOnce(&mut UrlProgress)) {
+        let json = {
+            let mut state = self
+                .state
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            apply(state.downloads.entry(url.to_string()).or_default());
+
+            let due = state.last_write.is_none_or(|last| {
+                last.elapsed() >= Duration::from_millis(PROGRESS_WRITE_INTERVAL_MS)
+            });
+            if !force && !due {
+                return;
+            }
+            state.last_write = Some(Instant::now());
+            render_snapshot(&state.downloads)
+        };
+
+        // Best effort: the path was validated up front and a missed tick is harmless
+        let _ = write_atomic(&self.path, &json);
+    }
+}
+
+pub fn render_snapshot(downloads: &BTreeMap<String, UrlProgress>) -> String {
+    let updated_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    serde_json::to_string_pretty(&ProgressSnapshot {
+        updated_at,
+        downloads,
+    })
+    .unwrap_or_default()
+}
+
+/// Write to a sibling temp file and rename so readers never see a partial snapshot
+pub fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let tmp = path.with_file_name(format!(".{file_name}.tmp"));
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_progress_line() {
+        let update = parse_progress_line(
+            "[download]  42.3% of ~  10.00MiB at    1.23MiB/s ETA 00:05 (frag 3/10)",
+        )
+        .unwrap();
+        assert!((update.percent - 42.3).abs() < f64::EPSILON);
+        assert_eq!(update.speed.as_deref(), Some("1.23MiB/s"));
+        assert_eq!(update.eta.as_deref(), Some("00:05"));
+    }
+
+    #[test]
+    fn test_parse_progress_unknown_values() {
+        let update =
+            parse_progress_line("[download]   0.0% of 5.00MiB at Unknown B/s ETA Unknown").unwrap();
+        assert_eq!(update.speed, None);
+        assert_eq!(update.eta, None);
+    }
+
+    #[test]
+    fn test_parse_non_progress_lines() {
+        assert!(parse_progress_line("[download] Destination: video.webm").is_none());
+        assert!(parse_progress_line("[youtube] abc: Downloading webpage").is_none());
+    }
+
+    #[test]
+    fn test_snapshot_serialization() {
+        let mut downloads = BTreeMap::new();
+        downloads.insert(
+            "https://example.com/a".to_string(),
+            UrlProgress {
+                status: ProgressStatus::Downloading,
+                percent: 42.5,
+                speed: Some("1.23MiB/s".to_string()),
+                eta: Some("00:05".to_string()),
+            },
+        );
+        downloads.insert(
+            "https://example.com/b".to_string(),
+            UrlProgress {
+                status: ProgressStatus::Failed,
+                ..Default::default()
+            },
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&render_snapshot(&downloads)).unwrap();
+        let a = &json["downloads"]["https://example.com/a"];
+
+        assert!(json["updated_at"].as_u64().unwrap() > 0);
+        assert_eq!(a["status"], "downloading");
+        assert_eq!(a["percent"], 42.5);
+        assert_eq!(a["speed"], "1.23MiB/s");
+        assert_eq!(a["eta"], "00:05");
+        assert_eq!(
+            json["downloads"]["https://example.com/b"]["status"],
+            "failed"
+        );
+    }
+
+    #[test]
+    fn test_progress_file_writes_atomically() {
+        let path =
+            std::env::temp_dir().join(format!("ytrs-test-progress-{}.json", std::process::id()));
+        let progress = ProgressFile::new(path.clone());
+        progress.set_status("https://example.com", ProgressStatus::Completed);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(json["downloads"]["https://example.com"]["percent"], 100.0);
+    }
+}
@@ -0,0 +1,60 @@
+//! Interactive terminal prompts
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// Asks a yes/no question on stderr; anything but `y`/`yes` (or a non-TTY stdin) is a no
+pub fn confirm(question: &str) -> io::Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Ok(false);
+    }
+
+    eprint!("{question} [y/N] ");
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(is_yes(&answer))
+}
+
+/// Asks for a free-form answer on stderr; an empty line, `skip`, or a non-TTY stdin is `None`
+pub fn ask(question: &str) -> io::Result<Option<String>> {
+    if !io::stdin().is_terminal() {
+        return Ok(None);
+    }
+
+    eprint!("{question} ");
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(non_skip(&answer))
+}
+
+fn non_skip(answer: &str) -> Option<String> {
+    let answer = answer.trim();
+    (!answer.is_empty() && !answer.eq_ignore_ascii_case("skip")).then(|| answer.to_string())
+}
+
+fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_yes() {
+        assert!(is_yes("y\n"));
+        assert!(is_yes(" YES "));
+        assert!(!is_yes("\n"));
+        assert!(!is_yes("nope"));
+    }
+
+    #[test]
+    fn test_non_skip() {
+        assert_eq!(non_skip(" firefox\n").as_deref(), Some("firefox"));
+        assert_eq!(non_skip("\n"), None);
+        assert_eq!(non_skip("Skip\n"), None);
+    }
+}
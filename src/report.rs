@@ -0,0 +1,116 @@
+//! Machine-readable summary of a finished batch
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportStatus {
+    Completed,
+    Failed,
+    /// Never started, e.g. after an interrupt or once a budget was reached
+    Skipped,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportEntry {
+    pub url: String,
+    pub status: ReportStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// `--json` result of a batch, printed to stdout in place of the summary
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadReport {
+    pub entries: Vec<ReportEntry>,
+}
+
+impl DownloadReport {
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn failed_urls(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.status == ReportStatus::Failed)
+            .map(|entry| entry.url.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_urls_from_report() {
+        let report: DownloadReport = serde_json::from_str(
+            r#"{"entries": [
+                {"url": "https://a.example/1", "status": "completed"},
+                {"url": "https://a.example/2", "status": "failed", "reason": "Video unavailable"},
+                {"url": "https://a.example/3", "status": "skipped"},
+                {"url": "https://a.example/4", "status": "failed"}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            report.failed_urls(),
+            ["https://a.example/2", "https://a.example/4"]
+        );
+    }
+
+    #[test]
+    fn test_batch_summary_shape() {
+        let summary = BatchSummary {
+            total: 3,
+            succeeded: 1,
+            failed: vec!["https://a.example/2".to_string()],
+        };
+
+        assert_eq!(
+            serde_json::to_value(&summary).unwrap(),
+            serde_json::json!({
+                "total": 3,
+                "succeeded": 1,
+                "failed": ["https://a.example/2"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_report_round_trip() {
+        let report = DownloadReport {
+            entries: vec![ReportEntry {
+                url: "https://a.example/1".to_string(),
+                status: ReportStatus::Failed,
+                reason: Some("Private video".to_string()),
+            }],
+        };
+        let json = serde_json::to_string(&report).unwrap();
+
+        assert!(json.contains(r#""status":"failed""#));
+        assert_eq!(
+            serde_json::from_str::<DownloadReport>(&json).unwrap(),
+            report
+        );
+    }
+}
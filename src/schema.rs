@@ -0,0 +1,188 @@
+//! JSON Schema for the output other tools read: the `--report` file, the
+//! `--progress-file` snapshots and the `--json` summary
+
+use serde_json::{Value, json};
+
+/// Status names as serialized, kept in step with `ReportStatus` and `ProgressStatus`
+const REPORT_STATUSES: &[&str] = &["completed", "failed", "skipped"];
+const PROGRESS_STATUSES: &[&str] = &["downloading", "completed", "failed", "skipped"];
+
+/// One schema with every format under `$defs`, so each can be validated by reference
+#[must_use]
+pub fn output_schema() -> Value {
+    let nullable_string = json!({ "type": ["string", "null"] });
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "ytrs output formats",
+        "$defs": {
+            "DownloadReport": {
+                "description": "Written by --report when a batch ends",
+                "type": "object",
+                "required": ["entries"],
+                "properties": {
+                    "entries": {
+                        "type": "array",
+                        "items": { "$ref": "#/$defs/ReportEntry" }
+                    }
+                },
+                "additionalProperties": false
+            },
+            "ReportEntry": {
+                "type": "object",
+                "required": ["url", "status"],
+                "properties": {
+                    "url": { "type": "string" },
+                    "status": { "enum": REPORT_STATUSES },
+                    "reason": { "type": "string" }
+                },
+                "additionalProperties": false
+            },
+            "ProgressSnapshot": {
+                "description": "Rewritten at --progress-file while a batch runs",
+                "type": "object",
+                "required": ["updated_at", "downloads"],
+                "properties": {
+                    "updated_at": {
+                        "description": "Unix time in seconds",
+                        "type": "integer",
+                        "minimum": 0
+                    },
+                    "downloads": {
+                        "description": "Keyed by URL",
+                        "type": "object",
+                        "additionalProperties": { "$ref": "#/$defs/UrlProgress" }
+                    }
+                },
+                "additionalProperties": false
+            },
+            "BatchSummary": {
+                "description": "Printed to stdout by --json when a batch ends",
+                "type": "object",
+                "required": ["total", "succeeded", "failed"],
+                "properties": {
+                    "total": { "type": "integer", "minimum": 0 },
+                    "succeeded": { "type": "integer", "minimum": 0 },
+                    "failed": {
+                        "description": "URLs that failed",
+                        "type": "array",
+                        "items": { "type": "string" }
+                    }
+                },
+                "additionalProperties": false
+            },
+            "UrlProgress": {
+                "type": "object",
+                "required": ["status", "percent", "speed", "eta"],
+                "properties": {
+                    "status": { "enum": PROGRESS_STATUSES },
+                    "percent": { "type": "number", "minimum": 0, "maximum": 100 },
+                    "speed": nullable_string,
+                    "eta": nullable_string
+                },
+                "additionalProperties": false
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::{ProgressStatus, UrlProgress};
+    use crate::report::{BatchSummary, DownloadReport, ReportEntry, ReportStatus};
+
+    #[test]
+    fn test_schema_is_json_with_every_status() {
+        let printed = serde_json::to_string_pretty(&output_schema()).unwrap();
+        let schema: Value = serde_json::from_str(&printed).unwrap();
+        let defs = &schema["$defs"];
+
+        for name in [
+            "DownloadReport",
+            "ReportEntry",
+            "ProgressSnapshot",
+            "UrlProgress",
+            "BatchSummary",
+        ] {
+            assert!(defs[name].is_object(), "missing {name}");
+        }
+        for status in [
+            ReportStatus::Completed,
+            ReportStatus::Failed,
+            ReportStatus::Skipped,
+        ] {
+            let name = serde_json::to_value(status).unwrap();
+            assert!(
+                defs["ReportEntry"]["properties"]["status"]["enum"]
+                    .as_array()
+                    .unwrap()
+                    .contains(&name)
+            );
+        }
+        for status in [
+            ProgressStatus::Downloading,
+            ProgressStatus::Completed,
+            ProgressStatus::Failed,
+            ProgressStatus::Skipped,
+        ] {
+            let name = serde_json::to_value(status).unwrap();
+            assert!(
+                defs["UrlProgress"]["properties"]["status"]["enum"]
+                    .as_array()
+                    .unwrap()
+                    .contains(&name)
+            );
+        }
+    }
+
+    #[test]
+    fn test_schema_matches_serialized_fields() {
+        let schema = output_schema();
+        let report = serde_json::to_value(DownloadReport {
+            entries: vec![ReportEntry {
+                url: "https://a.example/1".to_string(),
+                status: ReportStatus::Failed,
+                reason: Some("Video unavailable".to_string()),
+            }],
+        })
+        .unwrap();
+        let progress = serde_json::to_value(UrlProgress::default()).unwrap();
+
+        let fields = |def: &str| schema["$defs"][def]["properties"].clone();
+        for key in report["entries"][0].as_object().unwrap().keys() {
+            assert!(fields("ReportEntry").get(key).is_some(), "{key}");
+        }
+        for key in progress.as_object().unwrap().keys() {
+            assert!(fields("UrlProgress").get(key).is_some(), "{key}");
+        }
+    }
+
+    #[test]
+    fn test_schema_covers_batch_summary() {
+        let schema = output_schema();
+        let summary = serde_json::to_value(BatchSummary {
+            total: 2,
+            succeeded: 1,
+            failed: vec!["https://a.example/2".to_string()],
+        })
+        .unwrap();
+
+        let def = &schema["$defs"]["BatchSummary"];
+        let mut keys: Vec<&str> = summary
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        let mut required: Vec<&str> = def["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(Value::as_str)
+            .collect();
+        keys.sort_unstable();
+        required.sort_unstable();
+        assert_eq!(keys, ["failed", "succeeded", "total"]);
+        assert_eq!(required, keys);
+    }
+}
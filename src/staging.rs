@@ -0,0 +1,104 @@
+//! Local staging for slow network destinations: download to a temp dir, then move
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// `--temp-dir` if given, otherwise the OS temp dir
+#[must_use]
+pub fn staging_base(temp_dir: Option<&Path>) -> PathBuf {
+    temp_dir.map_or_else(std::env::temp_dir, Path::to_path_buf)
+}
+
+/// Private per-URL directory, so concurrent downloads never share partial files
+#[must_use]
+pub fn staging_dir(base: &Path, index: usize) -> PathBuf {
+    base.join(format!("ytrs-stage-{}-{index}", std::process::id()))
+}
+
+/// Moves a staged file to the same relative path under `destination`, returning where it
+/// ended up; files yt-dlp wrote outside the staging dir are left alone
+pub fn move_to_destination(staged: &Path, file: &Path, destination: &Path) -> io::Result<PathBuf> {
+    let Ok(relative) = file.strip_prefix(staged) else {
+        return Ok(file.to_path_buf());
+    };
+    let target = destination.join(relative);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    move_file_with(file, &target, |from, to| std::fs::rename(from, to))?;
+    Ok(target)
+}
+
+/// A rename can't cross filesystems, which is the whole point of staging, so fall back
+/// to copy and delete
+fn move_file_with(
+    from: &Path,
+    to: &Path,
+    rename: impl FnOnce(&Path, &Path) -> io::Result<()>,
+) -> io::Result<()> {
+    match rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            std::fs::copy(from, to)?;
+            std::fs::remove_file(from)
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staging_path_selection() {
+        assert_eq!(staging_base(None), std::env::temp_dir());
+        assert_eq!(
+            staging_base(Some(Path::new("/scratch"))),
+            PathBuf::from("/scratch")
+        );
+
+        let first = staging_dir(Path::new("/scratch"), 0);
+        let second = staging_dir(Path::new("/scratch"), 1);
+        assert!(first.starts_with("/scratch"));
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_cross_device_move_falls_back_to_copy() {
+        let dir = std::env::temp_dir().join(format!("ytrs-stage-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let from = dir.join("staged.webm");
+        let to = dir.join("moved.webm");
+        std::fs::write(&from, "video").unwrap();
+
+        let result = move_file_with(&from, &to, |_, _| {
+            Err(io::Error::from(io::ErrorKind::CrossesDevices))
+        });
+        let moved = std::fs::read_to_string(&to);
+        let source_left = from.exists();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(result.is_ok());
+        assert_eq!(moved.unwrap(), "video");
+        assert!(!source_left);
+    }
+
+    #[test]
+    fn test_move_keeps_relative_layout() {
+        let dir = std::env::temp_dir().join(format!("ytrs-stage-layout-{}", std::process::id()));
+        let staged = dir.join("stage");
+        let destination = dir.join("nas");
+        std::fs::create_dir_all(staged.join("Uploader")).unwrap();
+        let file = staged.join("Uploader/clip.mp4");
+        std::fs::write(&file, "clip").unwrap();
+
+        let target = move_to_destination(&staged, &file, &destination).unwrap();
+        let exists = target.is_file();
+        let outside = move_to_destination(&staged, Path::new("/elsewhere/x.mp4"), &destination);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(target, destination.join("Uploader/clip.mp4"));
+        assert!(exists);
+        assert_eq!(outside.unwrap(), PathBuf::from("/elsewhere/x.mp4"));
+    }
+}
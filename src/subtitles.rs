@@ -0,0 +1,125 @@
+//! `--subs auto-best`: one subtitle language picked per video from what it offers
+
+use std::process::Stdio;
+
+use tokio::process::Command;
+
+use crate::video_info::VideoInfo;
+
+/// `--subs` value that asks for a language picked from the locale
+pub const AUTO_BEST: &str = "auto-best";
+const FALLBACK_LANG: &str = "en";
+
+/// `pt_BR.UTF-8` becomes `pt-BR`; `C` and `POSIX` name no language
+#[must_use]
+pub fn locale_language(locale: &str) -> Option<String> {
+    let tag = locale.split(['.', '@']).next()?.trim();
+    if tag.is_empty() || tag == "C" || tag == "POSIX" {
+        return None;
+    }
+    Some(tag.replace('_', "-"))
+}
+
+/// The locale's exact language, then any variant of its base language, then English
+#[must_use]
+pub fn pick_subtitle_lang(available: &[String], locale: Option<&str>) -> Option<String> {
+    let wanted = locale.and_then(locale_language);
+    let mut candidates: Vec<&str> = Vec::new();
+    if let Some(tag) = &wanted {
+        candidates.push(tag);
+        candidates.extend(tag.split('-').next());
+    }
+    candidates.push(FALLBACK_LANG);
+
+    candidates.into_iter().find_map(|want| {
+        let base = |lang: &str| lang.split('-').next().unwrap_or(lang).to_string();
+        available
+            .iter()
+            .find(|lang| lang.eq_ignore_ascii_case(want))
+            .or_else(|| {
+                available
+                    .iter()
+                    .find(|lang| base(lang).eq_ignore_ascii_case(want))
+            })
+            .cloned()
+    })
+}
+
+/// Languages the video offers, read from yt-dlp's info JSON; `None` if yt-dlp fails
+pub async fn probe_subtitle_langs(
+    url: &str,
+    cookie_args: &[String],
+    include_auto: bool,
+) -> Option<Vec<String>> {
+    let output = Command::new("yt-dlp")
+        .args(["--dump-json", "--no-warnings", "--playlist-items", "1"])
+        .args(cookie_args)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let info = VideoInfo::from_json(stdout.lines().next()?).ok()?;
+    Some(info.subtitle_langs(include_auto))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn langs(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_locale_language() {
+        assert_eq!(locale_language("pt_BR.UTF-8").as_deref(), Some("pt-BR"));
+        assert_eq!(locale_language("de_DE@euro").as_deref(), Some("de-DE"));
+        assert_eq!(locale_language("fr").as_deref(), Some("fr"));
+        assert_eq!(locale_language("C.UTF-8"), None);
+        assert_eq!(locale_language("POSIX"), None);
+        assert_eq!(locale_language(""), None);
+    }
+
+    #[test]
+    fn test_pick_subtitle_lang() {
+        let available = langs(&["de", "en-US", "pt", "pt-BR"]);
+
+        assert_eq!(
+            pick_subtitle_lang(&available, Some("pt_BR.UTF-8")).as_deref(),
+            Some("pt-BR")
+        );
+        // No exact regional match, so the base language wins over English
+        assert_eq!(
+            pick_subtitle_lang(&available, Some("pt_PT.UTF-8")).as_deref(),
+            Some("pt")
+        );
+        assert_eq!(
+            pick_subtitle_lang(&langs(&["de-AT", "en"]), Some("de_DE.UTF-8")).as_deref(),
+            Some("de-AT")
+        );
+        // Nothing in the locale's language, or no usable locale: English, any region
+        assert_eq!(
+            pick_subtitle_lang(&available, Some("ja_JP.UTF-8")).as_deref(),
+            Some("en-US")
+        );
+        assert_eq!(
+            pick_subtitle_lang(&available, Some("C")).as_deref(),
+            Some("en-US")
+        );
+        assert_eq!(
+            pick_subtitle_lang(&available, None).as_deref(),
+            Some("en-US")
+        );
+        assert_eq!(
+            pick_subtitle_lang(&langs(&["ko"]), Some("ja_JP.UTF-8")),
+            None
+        );
+        assert_eq!(pick_subtitle_lang(&[], Some("en_US.UTF-8")), None);
+    }
+}
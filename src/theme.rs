@@ -0,0 +1,134 @@
+//! Terminal color palette, selected once per run with `--theme`
+
+use std::sync::OnceLock;
+
+use colored::{Color, ColoredString, Colorize};
+
+use crate::cli::ThemeName;
+
+/// What a piece of output means; the theme decides how that looks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tone {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Theme {
+    info: Option<Color>,
+    success: Option<Color>,
+    warning: Option<Color>,
+    error: Option<Color>,
+}
+
+impl Theme {
+    const DARK: Self = Self {
+        info: Some(Color::Cyan),
+        success: Some(Color::Green),
+        warning: Some(Color::Yellow),
+        error: Some(Color::Red),
+    };
+
+    /// Yellow and cyan wash out on white backgrounds
+    const LIGHT: Self = Self {
+        info: Some(Color::Blue),
+        success: Some(Color::Green),
+        warning: Some(Color::Magenta),
+        error: Some(Color::Red),
+    };
+
+    const MONO: Self = Self {
+        info: None,
+        success: None,
+        warning: None,
+        error: None,
+    };
+
+    #[must_use]
+    pub const fn from_name(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Dark => Self::DARK,
+            ThemeName::Light => Self::LIGHT,
+            ThemeName::Mono => Self::MONO,
+        }
+    }
+
+    #[must_use]
+    pub const fn color(&self, tone: Tone) -> Option<Color> {
+        match tone {
+            Tone::Info => self.info,
+            Tone::Success => self.success,
+            Tone::Warning => self.warning,
+            Tone::Error => self.error,
+        }
+    }
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Selects the palette for the rest of the run; `mono` also drops bold and dim styling
+pub fn install(name: ThemeName) {
+    if name == ThemeName::Mono {
+        colored::control::set_override(false);
+    }
+    let _ = THEME.set(Theme::from_name(name));
+}
+
+fn current() -> Theme {
+    THEME.get().copied().unwrap_or(Theme::DARK)
+}
+
+/// Colors text by meaning instead of by a hardcoded color
+pub trait Themed {
+    fn tone(&self, tone: Tone) -> ColoredString;
+
+    fn info(&self) -> ColoredString {
+        self.tone(Tone::Info)
+    }
+
+    fn success(&self) -> ColoredString {
+        self.tone(Tone::Success)
+    }
+
+    fn warning(&self) -> ColoredString {
+        self.tone(Tone::Warning)
+    }
+
+    fn error(&self) -> ColoredString {
+        self.tone(Tone::Error)
+    }
+}
+
+impl<T: AsRef<str> + ?Sized> Themed for T {
+    fn tone(&self, tone: Tone) -> ColoredString {
+        let text = self.as_ref();
+        match current().color(tone) {
+            Some(color) => text.color(color),
+            None => text.normal(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_colors_per_tone() {
+        let dark = Theme::from_name(ThemeName::Dark);
+        assert_eq!(dark.color(Tone::Info), Some(Color::Cyan));
+        assert_eq!(dark.color(Tone::Warning), Some(Color::Yellow));
+
+        let light = Theme::from_name(ThemeName::Light);
+        assert_eq!(light.color(Tone::Info), Some(Color::Blue));
+        assert_eq!(light.color(Tone::Warning), Some(Color::Magenta));
+        assert_eq!(light.color(Tone::Error), Some(Color::Red));
+
+        let mono = Theme::from_name(ThemeName::Mono);
+        for tone in [Tone::Info, Tone::Success, Tone::Warning, Tone::Error] {
+            assert_eq!(mono.color(tone), None);
+        }
+    }
+}
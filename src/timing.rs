@@ -0,0 +1,136 @@
+//! How well a batch used its parallel slots, for tuning `-p`
+
+use std::time::Duration;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConcurrencyReport {
+    pub urls: usize,
+    pub parallel: usize,
+    pub wall_clock: Duration,
+    /// Sum of every task's own run time
+    pub busy: Duration,
+}
+
+impl ConcurrencyReport {
+    #[must_use]
+    pub fn new(task_times: &[Duration], parallel: usize, wall_clock: Duration) -> Self {
+        Self {
+            urls: task_times.len(),
+            parallel,
+            wall_clock,
+            busy: task_times.iter().sum(),
+        }
+    }
+
+    /// How many times faster than running the same tasks one after another
+    #[must_use]
+    pub fn speedup(&self) -> f64 {
+        if self.wall_clock.is_zero() {
+            return 1.0;
+        }
+        self.busy.as_secs_f64() / self.wall_clock.as_secs_f64()
+    }
+
+    /// Share of the usable slots that were busy; a batch smaller than `-p` can't fill them all
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn utilization(&self) -> f64 {
+        let slots = self.parallel.min(self.urls).max(1);
+        (self.speedup() / slots as f64).min(1.0)
+    }
+
+    #[must_use]
+    pub fn render(&self) -> String {
+        format!(
+            "Concurrency: {} URLs, -p {}\n  Wall clock:      {:.1}s\n  Download time:   {:.1}s\n  Speedup:         {:.2}x\n  Utilization:     {:.0}%\n",
+            self.urls,
+            self.parallel,
+            self.wall_clock.as_secs_f64(),
+            self.busy.as_secs_f64(),
+            self.speedup(),
+            self.utilization() * 100.0
+        )
+    }
+}
+
+/// Parses `90m`, `1h30m`, `2h`, `45s` or `1d`; a bare number is seconds
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim().to_ascii_lowercase();
+    if let Ok(seconds) = input.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+
+    let mut total: u64 = 0;
+    let mut digits = String::new();
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let unit = match c {
+            'd' => 86_400,
+            'h' => 3_600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(format!("unknown duration unit '{c}'")),
+        };
+        let value: u64 = digits
+            .parse()
+            .map_err(|_| format!("'{c}' needs a number before it"))?;
+        total = value
+            .checked_mul(unit)
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or("duration is too long")?;
+        digits.clear();
+    }
+    if !digits.is_empty() {
+        return Err(format!("'{digits}' needs a unit: d, h, m or s"));
+    }
+    if total == 0 {
+        return Err("duration must be greater than zero".to_string());
+    }
+    Ok(Duration::from_secs(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utilization_from_task_times() {
+        let tasks = [
+            Duration::from_secs(30),
+            Duration::from_secs(30),
+            Duration::from_secs(20),
+            Duration::from_secs(20),
+        ];
+        let report = ConcurrencyReport::new(&tasks, 2, Duration::from_secs(50));
+
+        assert_eq!(report.busy, Duration::from_secs(100));
+        assert!((report.speedup() - 2.0).abs() < f64::EPSILON);
+        assert!((report.utilization() - 1.0).abs() < f64::EPSILON);
+
+        let report = ConcurrencyReport::new(&tasks, 4, Duration::from_secs(50));
+        assert!((report.utilization() - 0.5).abs() < f64::EPSILON);
+        assert!(report.render().contains("Utilization:     50%"));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90m"), Ok(Duration::from_secs(5_400)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5_400)));
+        assert_eq!(parse_duration("2H"), Ok(Duration::from_secs(7_200)));
+        assert_eq!(parse_duration("1d12h"), Ok(Duration::from_secs(129_600)));
+        assert_eq!(parse_duration("45"), Ok(Duration::from_secs(45)));
+        assert!(parse_duration("1h30").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("2 weeks").is_err());
+        assert!(parse_duration("0m").is_err());
+    }
+
+    #[test]
+    fn test_small_batch_counts_only_usable_slots() {
+        let report = ConcurrencyReport::new(&[Duration::from_secs(10)], 8, Duration::from_secs(10));
+        assert!((report.utilization() - 1.0).abs() < f64::EPSILON);
+    }
+}
@@ -1,8 +1,12 @@
 use std::collections::HashSet;
+use std::io::Read;
+use std::path::Path;
 
-use colored::Colorize;
 use url::Url;
 
+use crate::error::Result;
+use crate::theme::Themed;
+
 pub fn validate_url(raw_url: &str) -> bool {
     let trimmed = raw_url.trim();
     if trimmed.is_empty() {
@@ -14,9 +18,77 @@ pub fn validate_url(raw_url: &str) -> bool {
         .unwrap_or(false)
 }
 
-pub fn sanitize_and_deduplicate(urls: Vec<String>) -> Vec<String> {
+/// `https://www.youtube.com/watch?v=<id>` for the youtu.be, /shorts/, /watch and
+/// m.youtube.com forms of a video; `None` for other hosts and for playlist links,
+/// which yt-dlp may expand into more than the one video
+#[must_use]
+pub fn canonicalize_url(raw_url: &str) -> Option<String> {
+    let parsed = Url::parse(raw_url.trim()).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https")
+        || parsed.query_pairs().any(|(key, _)| key == "list")
+    {
+        return None;
+    }
+
+    let id = match parsed.host_str()? {
+        "youtu.be" => parsed.path_segments()?.next()?.to_string(),
+        "youtube.com" | "www.youtube.com" | "m.youtube.com" => {
+            let mut segments = parsed.path_segments()?;
+            match segments.next()? {
+                "watch" => parsed
+                    .query_pairs()
+                    .find(|(key, _)| key == "v")
+                    .map(|(_, id)| id.into_owned())?,
+                "shorts" => segments.next()?.to_string(),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    (!id.is_empty()).then(|| format!("https://www.youtube.com/watch?v={id}"))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UrlStatus {
+    Kept,
+    Invalid,
+    Duplicate,
+}
+
+impl std::fmt::Display for UrlStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Kept => write!(f, "kept"),
+            Self::Invalid => write!(f, "invalid"),
+            Self::Duplicate => write!(f, "duplicate"),
+        }
+    }
+}
+
+/// Cleaned URL list plus the classification of every non-empty input
+#[derive(Debug, Default)]
+pub struct SanitizedUrls {
+    pub kept: Vec<String>,
+    pub classified: Vec<(String, UrlStatus)>,
+}
+
+impl SanitizedUrls {
+    pub fn invalid(&self) -> impl Iterator<Item = &str> {
+        self.classified
+            .iter()
+            .filter(|(_, status)| *status == UrlStatus::Invalid)
+            .map(|(url, _)| url.as_str())
+    }
+}
+
+/// With `dedupe` off, repeated URLs are kept so they download again; with it on, the
+/// different forms of one YouTube video count as repeats and the first one is kept
+pub fn sanitize_and_deduplicate(urls: Vec<String>, dedupe: bool) -> SanitizedUrls {
     let mut seen = HashSet::with_capacity(urls.len());
-    let mut result = Vec::with_capacity(urls.len());
+    let mut result = SanitizedUrls {
+        kept: Vec::with_capacity(urls.len()),
+        classified: Vec::with_capacity(urls.len()),
+    };
 
     for raw_url in urls {
         let trimmed = raw_url.trim();
@@ -24,23 +96,66 @@ pub fn sanitize_and_deduplicate(urls: Vec<String>) -> Vec<String> {
             continue;
         }
 
-        if !validate_url(trimmed) {
-            eprintln!(
-                "{} {}",
-                "Warning: Skipping invalid URL:".yellow(),
-                trimmed.yellow()
-            );
-            continue;
-        }
+        let status = if !validate_url(trimmed) {
+            UrlStatus::Invalid
+        } else if !dedupe
+            || seen.insert(canonicalize_url(trimmed).unwrap_or_else(|| trimmed.to_string()))
+        {
+            result.kept.push(trimmed.to_string());
+            UrlStatus::Kept
+        } else {
+            UrlStatus::Duplicate
+        };
 
-        if seen.insert(trimmed.to_string()) {
-            result.push(trimmed.to_string());
-        }
+        result.classified.push((trimmed.to_string(), status));
     }
 
     result
 }
 
+pub fn render_sanitize_report(sanitized: &SanitizedUrls) -> String {
+    let mut report = String::new();
+    for (url, status) in &sanitized.classified {
+        let label = format!("{status:<9}");
+        let label = match status {
+            UrlStatus::Kept => label.success(),
+            UrlStatus::Invalid => label.error(),
+            UrlStatus::Duplicate => label.warning(),
+        };
+        report.push_str(&format!("  {label} {url}\n"));
+    }
+    report.push_str(&format!(
+        "{} of {} URLs kept",
+        sanitized.kept.len(),
+        sanitized.classified.len()
+    ));
+    report
+}
+
+/// One URL per line; blank lines, `#` comment lines and ` # notes` after a URL are ignored
+pub fn parse_url_list(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(|line| line.split_once(" #").map_or(line, |(url, _)| url).trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Reads a URL list from a file, or from stdin when the path is `-`
+pub fn load_url_list(path: &Path) -> Result<Vec<String>> {
+    if path == Path::new("-") {
+        return read_url_list(std::io::stdin().lock());
+    }
+    Ok(parse_url_list(&std::fs::read_to_string(path)?))
+}
+
+fn read_url_list(mut reader: impl Read) -> Result<Vec<String>> {
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+    Ok(parse_url_list(&contents))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -63,9 +178,152 @@ mod tests {
             String::new(),
             "invalid".to_string(),
         ];
-        let result = sanitize_and_deduplicate(urls);
+        let result = sanitize_and_deduplicate(urls, true).kept;
         assert_eq!(result.len(), 2);
         assert_eq!(result[0], "https://example.com");
         assert_eq!(result[1], "https://test.com");
     }
+
+    #[test]
+    fn test_canonicalize_url() {
+        let canonical = Some("https://www.youtube.com/watch?v=abc123".to_string());
+        for url in [
+            "https://youtu.be/abc123",
+            "https://youtu.be/abc123?si=share&t=42",
+            "https://www.youtube.com/watch?v=abc123",
+            "http://youtube.com/watch?feature=share&v=abc123",
+            "https://m.youtube.com/watch?v=abc123",
+            "https://www.youtube.com/shorts/abc123",
+            " https://m.youtube.com/shorts/abc123/ ",
+        ] {
+            assert_eq!(canonicalize_url(url), canonical, "{url}");
+        }
+
+        assert_eq!(canonicalize_url("https://example.com/watch?v=abc123"), None);
+        assert_eq!(canonicalize_url("https://www.youtube.com/@channel"), None);
+        assert_eq!(canonicalize_url("https://www.youtube.com/watch"), None);
+        assert_eq!(canonicalize_url("https://youtu.be/"), None);
+        assert_eq!(
+            canonicalize_url("https://www.youtube.com/watch?v=abc123&list=PL1"),
+            None
+        );
+        assert_eq!(canonicalize_url("not-a-url"), None);
+    }
+
+    #[test]
+    fn test_dedupe_across_youtube_url_forms() {
+        let urls = vec![
+            "https://youtu.be/abc123".to_string(),
+            "https://www.youtube.com/watch?v=abc123".to_string(),
+            "https://m.youtube.com/shorts/abc123".to_string(),
+            "https://www.youtube.com/watch?v=other".to_string(),
+            "https://example.com/abc123".to_string(),
+        ];
+        let result = sanitize_and_deduplicate(urls.clone(), true);
+        assert_eq!(
+            result.kept,
+            [
+                "https://youtu.be/abc123",
+                "https://www.youtube.com/watch?v=other",
+                "https://example.com/abc123",
+            ]
+        );
+        assert_eq!(sanitize_and_deduplicate(urls, false).kept.len(), 5);
+    }
+
+    #[test]
+    fn test_no_dedupe_preserves_duplicates() {
+        let urls = vec![
+            "https://example.com".to_string(),
+            "https://example.com".to_string(),
+            "invalid".to_string(),
+        ];
+        let result = sanitize_and_deduplicate(urls, false);
+        assert_eq!(result.kept, ["https://example.com", "https://example.com"]);
+        assert!(
+            !result
+                .classified
+                .iter()
+                .any(|(_, status)| *status == UrlStatus::Duplicate)
+        );
+    }
+
+    #[test]
+    fn test_sanitize_classification() {
+        let urls = vec![
+            "https://example.com".to_string(),
+            "not-a-url".to_string(),
+            " https://example.com ".to_string(),
+            String::new(),
+        ];
+        let result = sanitize_and_deduplicate(urls, true);
+        assert_eq!(
+            result.classified,
+            [
+                ("https://example.com".to_string(), UrlStatus::Kept),
+                ("not-a-url".to_string(), UrlStatus::Invalid),
+                ("https://example.com".to_string(), UrlStatus::Duplicate),
+            ]
+        );
+        assert_eq!(result.invalid().collect::<Vec<_>>(), ["not-a-url"]);
+    }
+
+    #[test]
+    fn test_render_sanitize_report() {
+        let urls = vec![
+            "https://example.com".to_string(),
+            "invalid".to_string(),
+            "https://example.com".to_string(),
+        ];
+        let report = render_sanitize_report(&sanitize_and_deduplicate(urls, true));
+        let lines: Vec<&str> = report.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].contains("kept") && lines[0].ends_with("https://example.com"));
+        assert!(lines[1].contains("invalid") && lines[1].ends_with("invalid"));
+        assert!(lines[2].contains("duplicate"));
+        assert_eq!(lines[3], "1 of 3 URLs kept");
+    }
+
+    #[test]
+    fn test_parse_url_list() {
+        let contents = "https://example.com/a\n\n  https://example.com/b  \n# comment\n";
+        assert_eq!(
+            parse_url_list(contents),
+            ["https://example.com/a", "https://example.com/b"]
+        );
+    }
+
+    #[test]
+    fn test_load_url_list_from_failed_log() {
+        let path = std::env::temp_dir().join("ytrs-test-failed-log.txt");
+        std::fs::write(&path, "https://example.com/1\nhttps://example.com/2\n").unwrap();
+        let urls = load_url_list(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(urls, ["https://example.com/1", "https://example.com/2"]);
+    }
+
+    #[test]
+    fn test_parse_url_list_strips_comments() {
+        let contents = "# channels\nhttps://example.com/a # weekly\n\t# indented\nhttps://example.com/b#t=30\n";
+        assert_eq!(
+            parse_url_list(contents),
+            ["https://example.com/a", "https://example.com/b#t=30"]
+        );
+    }
+
+    #[test]
+    fn test_read_url_list_from_stdin_like_reader() {
+        let stdin = "https://example.com/1\r\n\r\n# skipped\r\nhttps://example.com/2\r\n";
+        assert_eq!(
+            read_url_list(stdin.as_bytes()).unwrap(),
+            ["https://example.com/1", "https://example.com/2"]
+        );
+    }
+
+    #[test]
+    fn test_parse_url_list_empty() {
+        assert!(parse_url_list("\n\n").is_empty());
+    }
 }
@@ -0,0 +1,234 @@
+//! Parsing of yt-dlp's `--dump-json` info output
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Deserialize;
+use serde::de::IgnoredAny;
+
+use crate::error::Result;
+use crate::size::format_bytes;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct FormatInfo {
+    pub format_id: Option<String>,
+    pub ext: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<f64>,
+    pub vcodec: Option<String>,
+    pub acodec: Option<String>,
+    pub filesize: Option<f64>,
+    pub filesize_approx: Option<f64>,
+}
+
+impl FormatInfo {
+    #[must_use]
+    pub fn resolution(&self) -> String {
+        match (self.width, self.height, self.vcodec.as_deref()) {
+            (_, _, Some("none")) => "audio only".to_string(),
+            (Some(w), Some(h), _) => format!("{w}x{h}"),
+            (None, Some(h), _) => format!("{h}p"),
+            _ => "unknown".to_string(),
+        }
+    }
+
+    /// Exact size when known, otherwise yt-dlp's bitrate-based estimate
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn size_bytes(&self) -> Option<u64> {
+        self.filesize
+            .or(self.filesize_approx)
+            .map(|size| size.max(0.0) as u64)
+    }
+
+    /// The codec that matters for this stream: video codec, or audio for audio-only formats
+    #[must_use]
+    pub fn codec(&self) -> &str {
+        match self.vcodec.as_deref() {
+            Some("none") | None => self.acodec.as_deref().unwrap_or("unknown"),
+            Some(vcodec) => vcodec,
+        }
+    }
+}
+
+/// One line of the `--list-formats-json` table, with every cell already formatted
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatRow {
+    pub id: String,
+    pub ext: String,
+    pub resolution: String,
+    pub fps: String,
+    pub vcodec: String,
+    pub acodec: String,
+    pub filesize: String,
+}
+
+impl From<&FormatInfo> for FormatRow {
+    fn from(format: &FormatInfo) -> Self {
+        let cell = |value: Option<&str>| value.unwrap_or("-").to_string();
+        Self {
+            id: cell(format.format_id.as_deref()),
+            ext: cell(format.ext.as_deref()),
+            resolution: format.resolution(),
+            fps: format
+                .fps
+                .filter(|fps| *fps > 0.0)
+                .map_or_else(|| "-".to_string(), |fps| format!("{fps:.0}")),
+            vcodec: cell(format.vcodec.as_deref()),
+            acodec: cell(format.acodec.as_deref()),
+            filesize: format
+                .size_bytes()
+                .map_or_else(|| "-".to_string(), format_bytes),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct VideoInfo {
+    pub title: Option<String>,
+    /// Output path yt-dlp resolved from the output template
+    pub filename: Option<String>,
+    pub requested_formats: Option<Vec<FormatInfo>>,
+    /// Everything the site offers, worst to best as yt-dlp sorts them
+    pub formats: Option<Vec<FormatInfo>>,
+    /// Uploaded subtitle tracks, keyed by language code
+    #[serde(default)]
+    pub subtitles: BTreeMap<String, IgnoredAny>,
+    /// Auto-generated captions, keyed by language code
+    #[serde(default)]
+    pub automatic_captions: BTreeMap<String, IgnoredAny>,
+    #[serde(flatten)]
+    pub format: FormatInfo,
+}
+
+impl VideoInfo {
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Formats yt-dlp will download: the merged pair, or the single combined format
+    #[must_use]
+    pub fn selected_formats(&self) -> Vec<&FormatInfo> {
+        match &self.requested_formats {
+            Some(formats) if !formats.is_empty() => formats.iter().collect(),
+            _ => vec![&self.format],
+        }
+    }
+
+    /// Subtitle languages on offer, with auto-generated captions only if asked for
+    #[must_use]
+    pub fn subtitle_langs(&self, include_auto: bool) -> Vec<String> {
+        let auto = include_auto.then_some(&self.automatic_captions);
+        let langs: BTreeSet<&String> = self
+            .subtitles
+            .keys()
+            .chain(auto.into_iter().flat_map(BTreeMap::keys))
+            .collect();
+        langs.into_iter().cloned().collect()
+    }
+
+    /// A table row per available format, in yt-dlp's order
+    #[must_use]
+    pub fn format_rows(&self) -> Vec<FormatRow> {
+        self.formats.iter().flatten().map(FormatRow::from).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"{
+        "id": "abc123",
+        "title": "Sample",
+        "format_id": "303+251",
+        "requested_formats": [
+            {"format_id": "303", "ext": "webm", "width": 1920, "height": 1080,
+             "vcodec": "vp9", "acodec": "none", "filesize": 52428800},
+            {"format_id": "251", "ext": "webm", "vcodec": "none", "acodec": "opus",
+             "filesize_approx": 3145728.0}
+        ]
+    }"#;
+
+    #[test]
+    fn test_parse_requested_formats() {
+        let info = VideoInfo::from_json(SAMPLE).unwrap();
+        let formats = info.selected_formats();
+
+        assert_eq!(info.title.as_deref(), Some("Sample"));
+        assert_eq!(formats.len(), 2);
+        assert_eq!(formats[0].format_id.as_deref(), Some("303"));
+        assert_eq!(formats[0].resolution(), "1920x1080");
+        assert_eq!(formats[0].codec(), "vp9");
+        assert_eq!(formats[0].size_bytes(), Some(52_428_800));
+        assert_eq!(formats[1].resolution(), "audio only");
+        assert_eq!(formats[1].codec(), "opus");
+        assert_eq!(formats[1].size_bytes(), Some(3_145_728));
+    }
+
+    #[test]
+    fn test_subtitle_langs() {
+        let info = VideoInfo::from_json(
+            r#"{"title": "Clip",
+                "subtitles": {"en": [{"ext": "vtt"}], "pt-BR": [{"ext": "vtt"}]},
+                "automatic_captions": {"en": [], "de": [{"ext": "srv3"}]}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(info.subtitle_langs(false), ["en", "pt-BR"]);
+        assert_eq!(info.subtitle_langs(true), ["de", "en", "pt-BR"]);
+        assert!(
+            VideoInfo::from_json(SAMPLE)
+                .unwrap()
+                .subtitle_langs(true)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_format_rows_from_formats_array() {
+        let info = VideoInfo::from_json(
+            r#"{"title": "Clip", "formats": [
+                {"format_id": "251", "ext": "webm", "vcodec": "none", "acodec": "opus",
+                 "filesize": 3145728},
+                {"format_id": "303", "ext": "webm", "width": 1920, "height": 1080,
+                 "fps": 60.0, "vcodec": "vp9", "acodec": "none", "filesize_approx": 52428800},
+                {"format_id": "sb0", "ext": "mhtml", "vcodec": "none", "acodec": "none"}
+            ]}"#,
+        )
+        .unwrap();
+        let rows = info.format_rows();
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[1],
+            FormatRow {
+                id: "303".to_string(),
+                ext: "webm".to_string(),
+                resolution: "1920x1080".to_string(),
+                fps: "60".to_string(),
+                vcodec: "vp9".to_string(),
+                acodec: "none".to_string(),
+                filesize: "50.0 MiB".to_string(),
+            }
+        );
+        assert_eq!(rows[0].resolution, "audio only");
+        assert_eq!(rows[0].fps, "-");
+        assert_eq!(rows[0].filesize, "3.0 MiB");
+        assert_eq!(rows[2].filesize, "-");
+        assert!(VideoInfo::default().format_rows().is_empty());
+    }
+
+    #[test]
+    fn test_single_format_fallback() {
+        let info = VideoInfo::from_json(
+            r#"{"format_id": "18", "height": 360, "vcodec": "avc1", "acodec": "mp4a"}"#,
+        )
+        .unwrap();
+        let formats = info.selected_formats();
+
+        assert_eq!(formats.len(), 1);
+        assert_eq!(formats[0].format_id.as_deref(), Some("18"));
+        assert_eq!(formats[0].resolution(), "360p");
+    }
+}
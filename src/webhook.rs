@@ -0,0 +1,155 @@
+//! `--webhook`: the outcome of a finished batch, POSTed as JSON for dashboards
+
+use serde::Serialize;
+
+use crate::report::{DownloadReport, ReportStatus};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchStatus {
+    /// Nothing failed
+    Success,
+    /// Some downloads failed and some completed
+    Partial,
+    /// Downloads failed and none completed
+    Failed,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct FailedUrl {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct WebhookPayload {
+    pub status: BatchStatus,
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub failed_urls: Vec<FailedUrl>,
+}
+
+impl From<&DownloadReport> for WebhookPayload {
+    fn from(report: &DownloadReport) -> Self {
+        let count = |status| {
+            report
+                .entries
+                .iter()
+                .filter(|entry| entry.status == status)
+                .count()
+        };
+        let completed = count(ReportStatus::Completed);
+        let failed_urls: Vec<FailedUrl> = report
+            .entries
+            .iter()
+            .filter(|entry| entry.status == ReportStatus::Failed)
+            .map(|entry| FailedUrl {
+                url: entry.url.clone(),
+                reason: entry.reason.clone(),
+            })
+            .collect();
+        let status = match (failed_urls.len(), completed) {
+            (0, _) => BatchStatus::Success,
+            (_, 0) => BatchStatus::Failed,
+            _ => BatchStatus::Partial,
+        };
+
+        Self {
+            status,
+            total: report.entries.len(),
+            completed,
+            failed: failed_urls.len(),
+            skipped: count(ReportStatus::Skipped),
+            failed_urls,
+        }
+    }
+}
+
+/// POSTs the payload; a failed send is only a warning, the batch result stands
+#[cfg(feature = "webhook")]
+pub async fn send_webhook(url: &str, payload: &WebhookPayload) {
+    use std::time::Duration;
+
+    use crate::config::WEBHOOK_TIMEOUT_SECONDS;
+    use crate::theme::Themed;
+
+    let sent = reqwest::Client::new()
+        .post(url)
+        .timeout(Duration::from_secs(WEBHOOK_TIMEOUT_SECONDS))
+        .json(payload)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status);
+    if let Err(e) = sent {
+        eprintln!("{} Webhook to {url} failed: {e}", "Warning:".warning());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::ReportEntry;
+
+    fn entry(url: &str, status: ReportStatus, reason: Option<&str>) -> ReportEntry {
+        ReportEntry {
+            url: url.to_string(),
+            status,
+            reason: reason.map(ToString::to_string),
+        }
+    }
+
+    #[test]
+    fn test_payload_from_report() {
+        let report = DownloadReport {
+            entries: vec![
+                entry("https://a.example/1", ReportStatus::Completed, None),
+                entry(
+                    "https://a.example/2",
+                    ReportStatus::Failed,
+                    Some("Video unavailable"),
+                ),
+                entry("https://a.example/3", ReportStatus::Skipped, None),
+            ],
+        };
+        let payload = serde_json::to_value(WebhookPayload::from(&report)).unwrap();
+
+        assert_eq!(
+            payload,
+            serde_json::json!({
+                "status": "partial",
+                "total": 3,
+                "completed": 1,
+                "failed": 1,
+                "skipped": 1,
+                "failed_urls": [
+                    { "url": "https://a.example/2", "reason": "Video unavailable" }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_payload_status() {
+        let status = |entries| WebhookPayload::from(&DownloadReport { entries }).status;
+
+        assert_eq!(
+            status(vec![entry(
+                "https://a.example/1",
+                ReportStatus::Completed,
+                None
+            )]),
+            BatchStatus::Success
+        );
+        assert_eq!(
+            status(vec![
+                entry("https://a.example/1", ReportStatus::Failed, None),
+                entry("https://a.example/2", ReportStatus::Skipped, None),
+            ]),
+            BatchStatus::Failed
+        );
+        assert_eq!(status(Vec::new()), BatchStatus::Success);
+    }
+}